@@ -1,23 +1,30 @@
 use std::{
-    io,
+    io, mem,
     sync::{mpsc, Arc},
     thread,
     time::Duration,
 };
 
 use crate::{
-    backend::Backend,
+    backend::{Backend, Operation},
+    clipboard,
+    config::{Config, KeyBindings},
     mode::{self, ModeContext, ModeKind, ModeResponse},
     platform::{Key, Platform, PlatformEventReader},
-    ui::Drawer,
+    ui::{Color, Drawer, Glyphs, SpinnerStyle, DEFAULT_MAX_LINE_ROWS},
 };
 
 enum Event {
     Key(Key),
+    Paste(String),
     Resize(u16, u16),
     Response(ModeResponse),
     ModeChange(ModeKind),
     ModeRefresh(ModeKind),
+    BackendChange(Arc<dyn Backend>),
+    OperationChange(Option<Operation>),
+    TitleChange(String, Option<bool>),
+    ClipboardCopy(Result<(), String>),
 }
 
 #[derive(Clone)]
@@ -34,49 +41,134 @@ impl EventSender {
     pub fn send_mode_refresh(&self, mode: ModeKind) {
         self.0.send(Event::ModeRefresh(mode)).unwrap();
     }
+
+    pub fn send_backend_change(&self, backend: Arc<dyn Backend>) {
+        self.0.send(Event::BackendChange(backend)).unwrap();
+    }
+
+    pub fn send_operation_change(&self, operation: Option<Operation>) {
+        self.0.send(Event::OperationChange(operation)).unwrap();
+    }
+
+    // `dirty` is none when the sender did not look at the working tree
+    pub fn send_title_change(&self, header: String, dirty: Option<bool>) {
+        self.0.send(Event::TitleChange(header, dirty)).unwrap();
+    }
+
+    pub fn send_clipboard_copy(&self, result: Result<(), String>) {
+        self.0.send(Event::ClipboardCopy(result)).unwrap();
+    }
 }
 
 #[derive(Default)]
 struct Application {
     current_mode: ModeKind,
+    // where esc goes back to while the current mode is still loading
+    previous_mode: Option<ModeKind>,
+    // modes whose pending refresh was cancelled and whose response is dropped
+    cancelled_refreshes: Vec<ModeKind>,
 
     status_mode: mode::status::Mode,
     log_mode: mode::log::Mode,
     revision_details_mode: mode::revision_details::Mode,
+    file_log_mode: mode::file_log::Mode,
     branches_mode: mode::branches::Mode,
     tags_mode: mode::tags::Mode,
-
-    spinner_state: u8,
+    stash_mode: mode::stash::Mode,
+    reflog_mode: mode::reflog::Mode,
+    remotes_mode: mode::remotes::Mode,
+    sparse_mode: mode::sparse::Mode,
+    clone_mode: mode::clone::Mode,
+
+    spinner_style: SpinnerStyle,
+    spinner_state: usize,
+    operation: Option<Operation>,
+    last_revision: Option<String>,
+    notice: Option<(String, Color)>,
 }
 impl Application {
     pub fn enter_mode(&mut self, ctx: &ModeContext, mode: ModeKind) {
-        self.current_mode = mode;
+        if mem::discriminant(&self.current_mode) == mem::discriminant(&mode) {
+            self.current_mode = mode;
+        } else {
+            self.previous_mode = Some(mem::replace(&mut self.current_mode, mode));
+        }
         match &self.current_mode {
             ModeKind::Status => self.status_mode.on_enter(ctx),
             ModeKind::Log => self.log_mode.on_enter(ctx),
             ModeKind::RevisionDetails(revision) => {
+                self.last_revision = Some(revision.clone());
                 self.revision_details_mode.on_enter(ctx, revision);
             }
+            ModeKind::FileLog(path) => self.file_log_mode.on_enter(ctx, path),
             ModeKind::Branches => self.branches_mode.on_enter(ctx),
             ModeKind::Tags => self.tags_mode.on_enter(ctx),
+            ModeKind::Stash => self.stash_mode.on_enter(ctx),
+            ModeKind::Reflog => self.reflog_mode.on_enter(ctx),
+            ModeKind::Remotes => self.remotes_mode.on_enter(ctx),
+            ModeKind::Sparse => self.sparse_mode.on_enter(ctx),
+            ModeKind::Clone => self.clone_mode.on_enter(ctx),
         }
     }
 
     pub fn refresh_mode(&mut self, ctx: &ModeContext, mode: ModeKind) {
-        if std::mem::discriminant(&self.current_mode) == std::mem::discriminant(&mode) {
+        if mem::discriminant(&self.current_mode) == mem::discriminant(&mode) {
             self.enter_mode(ctx, mode);
         }
     }
 
+    // esc while the current mode is still loading goes back to the previous mode
+    fn cancel_refresh(&mut self, ctx: &ModeContext) -> bool {
+        if self.previous_mode.is_none() {
+            return false;
+        }
+
+        let cancelled = match &self.current_mode {
+            ModeKind::Status => self.status_mode.cancel_refresh(),
+            ModeKind::Log => self.log_mode.cancel_refresh(),
+            ModeKind::FileLog(_) => self.file_log_mode.cancel_refresh(),
+            ModeKind::Branches => self.branches_mode.cancel_refresh(),
+            ModeKind::Tags => self.tags_mode.cancel_refresh(),
+            ModeKind::Stash => self.stash_mode.cancel_refresh(),
+            ModeKind::Reflog => self.reflog_mode.cancel_refresh(),
+            ModeKind::Remotes => self.remotes_mode.cancel_refresh(),
+            ModeKind::Sparse => self.sparse_mode.cancel_refresh(),
+            ModeKind::RevisionDetails(_) | ModeKind::Clone => false,
+        };
+        if cancelled {
+            self.cancelled_refreshes.push(self.current_mode.clone());
+            if let Some(mode) = self.previous_mode.take() {
+                self.enter_mode(ctx, mode);
+            }
+            // a second esc should not bounce back into the cancelled load
+            self.previous_mode = None;
+        }
+        cancelled
+    }
+
     pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> bool {
+        self.notice = None;
+
+        if let Key::Esc = key {
+            if self.cancel_refresh(ctx) {
+                return true;
+            }
+        }
+
         let status = match &self.current_mode {
             ModeKind::Status => self.status_mode.on_key(ctx, key),
             ModeKind::Log => self.log_mode.on_key(ctx, key),
             ModeKind::RevisionDetails(revision) => {
                 self.revision_details_mode.on_key(ctx, revision, key)
             }
+            ModeKind::FileLog(_) => self.file_log_mode.on_key(ctx, key),
             ModeKind::Branches => self.branches_mode.on_key(ctx, key),
             ModeKind::Tags => self.tags_mode.on_key(ctx, key),
+            ModeKind::Stash => self.stash_mode.on_key(ctx, key),
+            ModeKind::Reflog => self.reflog_mode.on_key(ctx, key),
+            ModeKind::Remotes => self.remotes_mode.on_key(ctx, key),
+            ModeKind::Sparse => self.sparse_mode.on_key(ctx, key),
+            ModeKind::Clone => self.clone_mode.on_key(ctx, key),
         };
 
         if !status.pending_input {
@@ -84,11 +176,37 @@ impl Application {
                 return false;
             }
 
+            // keys rebound in the current mode take precedence over global ones
+            if ctx
+                .key_bindings
+                .is_bound(self.current_mode.key_scope(), key)
+            {
+                return true;
+            }
+            let key = ctx.key_bindings.translate("global", key);
+
             match key {
                 Key::Char('s') => self.enter_mode(ctx, ModeKind::Status),
                 Key::Char('l') => self.enter_mode(ctx, ModeKind::Log),
                 Key::Char('b') => self.enter_mode(ctx, ModeKind::Branches),
                 Key::Char('t') => self.enter_mode(ctx, ModeKind::Tags),
+                Key::Char('S') => self.enter_mode(ctx, ModeKind::Stash),
+                Key::Char('L') => self.enter_mode(ctx, ModeKind::Reflog),
+                Key::Char('u') => self.enter_mode(ctx, ModeKind::Remotes),
+                Key::Char('w') => self.enter_mode(ctx, ModeKind::Sparse),
+                Key::Char('Y') => {
+                    if let Some(text) = self.clipboard_text().filter(|t| !t.is_empty()) {
+                        let event_sender = ctx.event_sender.clone();
+                        thread::spawn(move || {
+                            event_sender.send_clipboard_copy(clipboard::copy(&text));
+                        });
+                    }
+                }
+                Key::Char('h') => {
+                    if let Some(revision) = self.last_revision.clone() {
+                        self.enter_mode(ctx, ModeKind::RevisionDetails(revision));
+                    }
+                }
                 _ => (),
             }
         }
@@ -96,7 +214,32 @@ impl Application {
         true
     }
 
+    pub fn on_paste(&mut self, text: &str) {
+        match &self.current_mode {
+            ModeKind::Status => self.status_mode.on_paste(text),
+            ModeKind::Log => self.log_mode.on_paste(text),
+            ModeKind::Branches => self.branches_mode.on_paste(text),
+            ModeKind::Tags => self.tags_mode.on_paste(text),
+            ModeKind::Stash => self.stash_mode.on_paste(text),
+            ModeKind::Remotes => self.remotes_mode.on_paste(text),
+            ModeKind::Sparse => self.sparse_mode.on_paste(text),
+            ModeKind::Clone => self.clone_mode.on_paste(text),
+            _ => (),
+        }
+    }
+
     pub fn on_response(&mut self, response: ModeResponse) {
+        if let Some(mode) = response.refreshed_mode() {
+            let cancelled = self
+                .cancelled_refreshes
+                .iter()
+                .position(|m| mem::discriminant(m) == mem::discriminant(&mode));
+            if let Some(index) = cancelled {
+                self.cancelled_refreshes.remove(index);
+                return;
+            }
+        }
+
         match response {
             ModeResponse::Status(response) => {
                 self.status_mode.on_response(response);
@@ -105,12 +248,44 @@ impl Application {
             ModeResponse::RevisionDetails(response) => {
                 self.revision_details_mode.on_response(response);
             }
+            ModeResponse::FileLog(response) => self.file_log_mode.on_response(response),
             ModeResponse::Branches(response) => {
                 self.branches_mode.on_response(response);
             }
             ModeResponse::Tags(response) => {
                 self.tags_mode.on_response(response);
             }
+            ModeResponse::Stash(response) => {
+                self.stash_mode.on_response(response);
+            }
+            ModeResponse::Reflog(response) => {
+                self.reflog_mode.on_response(response);
+            }
+            ModeResponse::Remotes(response) => {
+                self.remotes_mode.on_response(response);
+            }
+            ModeResponse::Sparse(response) => {
+                self.sparse_mode.on_response(response);
+            }
+            ModeResponse::Clone(response) => {
+                self.clone_mode.on_response(response);
+            }
+        }
+    }
+
+    fn clipboard_text(&self) -> Option<String> {
+        match &self.current_mode {
+            ModeKind::Status => self.status_mode.clipboard_text(),
+            ModeKind::Log => self.log_mode.clipboard_text(),
+            ModeKind::RevisionDetails(_) => self.revision_details_mode.clipboard_text(),
+            ModeKind::FileLog(_) => self.file_log_mode.clipboard_text(),
+            ModeKind::Branches => self.branches_mode.clipboard_text(),
+            ModeKind::Tags => self.tags_mode.clipboard_text(),
+            ModeKind::Stash => self.stash_mode.clipboard_text(),
+            ModeKind::Reflog => self.reflog_mode.clipboard_text(),
+            ModeKind::Remotes => self.remotes_mode.clipboard_text(),
+            ModeKind::Sparse => self.sparse_mode.clipboard_text(),
+            ModeKind::Clone => self.clone_mode.clipboard_text(),
         }
     }
 
@@ -119,27 +294,73 @@ impl Application {
             ModeKind::Status => self.status_mode.is_waiting_response(),
             ModeKind::Log => self.log_mode.is_waiting_response(),
             ModeKind::RevisionDetails(_) => self.revision_details_mode.is_waiting_response(),
+            ModeKind::FileLog(_) => self.file_log_mode.is_waiting_response(),
             ModeKind::Branches => self.branches_mode.is_waiting_response(),
             ModeKind::Tags => self.tags_mode.is_waiting_response(),
+            ModeKind::Stash => self.stash_mode.is_waiting_response(),
+            ModeKind::Reflog => self.reflog_mode.is_waiting_response(),
+            ModeKind::Remotes => self.remotes_mode.is_waiting_response(),
+            ModeKind::Sparse => self.sparse_mode.is_waiting_response(),
+            ModeKind::Clone => self.clone_mode.is_waiting_response(),
         }
     }
 
-    pub fn draw_header(&mut self, drawer: &mut Drawer) {
-        let spinner = [b'-', b'\\', b'|', b'/'];
-        self.spinner_state = (self.spinner_state + 1) % spinner.len() as u8;
+    pub fn draw_header(&mut self, drawer: &mut Drawer, key_bindings: &KeyBindings) {
+        let spinner = drawer.glyphs.spinner_frames(self.spinner_style);
+        self.spinner_state = (self.spinner_state + 1) % spinner.len();
         let spinner = match self.is_waiting_response() {
-            true => spinner[self.spinner_state as usize],
-            false => b' ',
+            true => spinner[self.spinner_state],
+            false => ' ',
         };
 
         let (mode_name, left_help, right_help) = match &self.current_mode {
             ModeKind::Status => self.status_mode.header(),
             ModeKind::Log => self.log_mode.header(),
             ModeKind::RevisionDetails(_) => self.revision_details_mode.header(),
+            ModeKind::FileLog(_) => self.file_log_mode.header(),
             ModeKind::Branches => self.branches_mode.header(),
             ModeKind::Tags => self.tags_mode.header(),
+            ModeKind::Stash => self.stash_mode.header(),
+            ModeKind::Reflog => self.reflog_mode.header(),
+            ModeKind::Remotes => self.remotes_mode.header(),
+            ModeKind::Sparse => self.sparse_mode.header(),
+            ModeKind::Clone => self.clone_mode.header(),
+        };
+        let following = match &self.current_mode {
+            ModeKind::Status => self.status_mode.is_following_output(),
+            ModeKind::Log => self.log_mode.is_following_output(),
+            ModeKind::Clone => self.clone_mode.is_following_output(),
+            _ => false,
+        };
+        let following_mode_name;
+        let mode_name = if following {
+            following_mode_name = format!("{} (following)", mode_name);
+            &following_mode_name
+        } else {
+            mode_name
         };
-        drawer.header(mode_name, left_help, right_help, spinner);
+        let counter = match &self.current_mode {
+            ModeKind::Status => (self.status_mode.selected_count(), "selected"),
+            ModeKind::Log => (self.log_mode.loaded_count(), "loaded"),
+            ModeKind::RevisionDetails(_) => {
+                (self.revision_details_mode.selected_count(), "selected")
+            }
+            ModeKind::FileLog(_) => (self.file_log_mode.loaded_count(), "loaded"),
+            _ => (0, ""),
+        };
+        let counter = Some(counter).filter(|&(count, _)| count > 0);
+        let operation = self.operation.as_ref().map(Operation::as_str);
+        let scope = self.current_mode.key_scope();
+        let left_help = key_bindings.help(scope, left_help);
+        let right_help = key_bindings.help(scope, right_help);
+        drawer.header(
+            mode_name,
+            (&left_help, &right_help),
+            counter,
+            operation,
+            spinner,
+            key_bindings,
+        );
     }
 
     pub fn draw_body(&self, drawer: &mut Drawer) {
@@ -149,8 +370,17 @@ impl Application {
             ModeKind::RevisionDetails(_) => {
                 self.revision_details_mode.draw(drawer);
             }
+            ModeKind::FileLog(_) => self.file_log_mode.draw(drawer),
             ModeKind::Branches => self.branches_mode.draw(drawer),
             ModeKind::Tags => self.tags_mode.draw(drawer),
+            ModeKind::Stash => self.stash_mode.draw(drawer),
+            ModeKind::Reflog => self.reflog_mode.draw(drawer),
+            ModeKind::Remotes => self.remotes_mode.draw(drawer),
+            ModeKind::Sparse => self.sparse_mode.draw(drawer),
+            ModeKind::Clone => self.clone_mode.draw(drawer),
+        }
+        if let Some((text, color)) = &self.notice {
+            drawer.notice(text, *color);
         }
         drawer.clear_to_bottom();
     }
@@ -160,17 +390,23 @@ fn terminal_event_loop(mut event_reader: PlatformEventReader, sender: mpsc::Sync
     event_reader.init();
 
     let mut keys = Vec::new();
+    let mut pastes = Vec::new();
     loop {
         keys.clear();
         let mut resize = None;
 
-        event_reader.read_terminal_events(&mut keys, &mut resize);
+        event_reader.read_terminal_events(&mut keys, &mut pastes, &mut resize);
 
         for &key in &keys {
             if sender.send(Event::Key(key)).is_err() {
                 break;
             }
         }
+        for text in pastes.drain(..) {
+            if sender.send(Event::Paste(text)).is_err() {
+                break;
+            }
+        }
         if let Some(resize) = resize {
             if sender.send(Event::Resize(resize.0, resize.1)).is_err() {
                 break;
@@ -179,25 +415,48 @@ fn terminal_event_loop(mut event_reader: PlatformEventReader, sender: mpsc::Sync
     }
 }
 
-pub fn run(platform_event_reader: PlatformEventReader, backend: Arc<dyn Backend>) {
+pub fn run(platform_event_reader: PlatformEventReader, backend: Arc<dyn Backend>, config: Config) {
     let (event_sender, event_receiver) = mpsc::sync_channel(1);
 
     let mut ctx = ModeContext {
         backend,
         event_sender: EventSender(event_sender.clone()),
         viewport_size: Platform::terminal_size(),
+        key_bindings: Arc::new(config.key_bindings),
     };
 
     let _ = thread::spawn(move || {
         terminal_event_loop(platform_event_reader, event_sender);
     });
 
-    let mut application = Application::default();
-    application.enter_mode(&ctx, ModeKind::default());
+    let mut application = Application {
+        spinner_style: config.spinner_style,
+        status_mode: mode::status::Mode::new(config.entry_sort, config.retry),
+        log_mode: mode::log::Mode::new(config.log_count, config.retry),
+        revision_details_mode: mode::revision_details::Mode::new(config.entry_sort),
+        ..Default::default()
+    };
+    application.enter_mode(&ctx, config.initial_mode);
+    application.previous_mode = None;
+    if let Some(warning) = config.warnings.first() {
+        let text = match config.warnings.len() {
+            1 => warning.clone(),
+            len => format!("{} (and {} more)", warning, len - 1),
+        };
+        application.notice = Some((text, Color::DarkRed));
+    }
+
+    let glyphs = if config.ascii {
+        &Glyphs::ASCII
+    } else {
+        &Glyphs::UNICODE
+    };
+    let max_line_rows = config.max_line_rows.unwrap_or(DEFAULT_MAX_LINE_ROWS);
 
     let stdout = io::stdout();
     let mut stdout = stdout.lock();
     let mut stdout_buf = Vec::new();
+    let mut dirty = false;
 
     const TIMEOUT: Duration = Duration::from_millis(100);
 
@@ -211,6 +470,7 @@ pub fn run(platform_event_reader: PlatformEventReader, backend: Arc<dyn Backend>
         };
 
         let mut draw_body = true;
+        let mut title = None;
 
         match event {
             Ok(Event::Key(key)) => {
@@ -218,18 +478,41 @@ pub fn run(platform_event_reader: PlatformEventReader, backend: Arc<dyn Backend>
                     break;
                 }
             }
+            Ok(Event::Paste(text)) => application.on_paste(&text),
             Ok(Event::Resize(width, height)) => {
                 ctx.viewport_size = (width, height);
             }
             Ok(Event::Response(response)) => application.on_response(response),
             Ok(Event::ModeChange(mode)) => application.enter_mode(&ctx, mode),
             Ok(Event::ModeRefresh(mode)) => application.refresh_mode(&ctx, mode),
+            Ok(Event::BackendChange(backend)) => ctx.backend = backend,
+            Ok(Event::OperationChange(operation)) => application.operation = operation,
+            Ok(Event::ClipboardCopy(result)) => {
+                application.notice = Some(match result {
+                    Ok(()) => ("copied to clipboard".into(), Color::DarkGreen),
+                    Err(error) => (error, Color::DarkRed),
+                });
+            }
+            Ok(Event::TitleChange(header, new_dirty)) => {
+                dirty = new_dirty.unwrap_or(dirty);
+                title = Some(mode::window_title(&header, dirty));
+                draw_body = false;
+            }
             Err(mpsc::RecvTimeoutError::Timeout) => draw_body = false,
             Err(mpsc::RecvTimeoutError::Disconnected) => break,
         }
 
-        let mut drawer = Drawer::new(stdout_buf, ctx.viewport_size);
-        application.draw_header(&mut drawer);
+        let mut drawer = Drawer::new(
+            stdout_buf,
+            ctx.viewport_size,
+            glyphs,
+            config.theme,
+            max_line_rows,
+        );
+        if let Some(title) = &title {
+            drawer.title(title);
+        }
+        application.draw_header(&mut drawer, &ctx.key_bindings);
         if draw_body {
             application.draw_body(&mut drawer);
         }
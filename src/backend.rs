@@ -1,7 +1,9 @@
 use std::{
+    fmt, io,
     path::PathBuf,
-    process::{Child, Command, Stdio},
+    process::{Child, Command, ExitStatus, Stdio},
     sync::Arc,
+    thread,
 };
 
 use crate::ui::{Drawer, SelectEntryDraw};
@@ -49,10 +51,31 @@ impl FileStatus {
 pub struct StatusInfo {
     pub header: String,
     pub entries: Vec<RevisionEntry>,
+    // entries whose only change is their file mode
+    pub mode_changes: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum SignatureStatus {
+    Good,
+    Bad,
+    Unverified,
+    Unsigned,
+}
+impl SignatureStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Good => "good signature",
+            Self::Bad => "bad signature",
+            Self::Unverified => "unverified signature",
+            Self::Unsigned => "unsigned",
+        }
+    }
 }
 
 pub struct RevisionInfo {
     pub message: String,
+    pub signature: Option<SignatureStatus>,
     pub entries: Vec<RevisionEntry>,
 }
 
@@ -62,15 +85,77 @@ pub struct RevisionEntry {
     pub status: FileStatus,
 }
 
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Public,
+    Draft,
+    Secret,
+}
+impl Phase {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Public => "public",
+            Self::Draft => "draft",
+            Self::Secret => "secret",
+        }
+    }
+}
+
 pub struct LogEntry {
     pub graph: String,
     pub hash: String,
+    pub timestamp: i64,
     pub date: String,
     pub author: String,
+    pub phase: Option<Phase>,
     pub refs: String,
     pub message: String,
 }
 
+#[derive(Clone, Copy, Default)]
+pub enum LogOrder {
+    #[default]
+    Default,
+    Date,
+    Topological,
+}
+impl LogOrder {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Default => Self::Date,
+            Self::Date => Self::Topological,
+            Self::Topological => Self::Default,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LogFilter {
+    pub message: String,
+    pub since: String,
+    pub until: String,
+}
+impl LogFilter {
+    pub fn is_empty(&self) -> bool {
+        self.message.is_empty() && self.since.is_empty() && self.until.is_empty()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ResetMode {
+    Soft,
+    Mixed,
+    Hard,
+}
+
+#[derive(Clone)]
+pub struct PushOptions {
+    pub remote: String,
+    pub branch: String,
+    pub force_with_lease: bool,
+    pub set_upstream: bool,
+}
+
 pub struct BranchEntry {
     pub name: String,
     pub checked_out: bool,
@@ -80,6 +165,109 @@ pub struct TagEntry {
     pub name: String,
 }
 
+pub struct StashEntry {
+    pub name: String,
+    pub message: String,
+}
+
+pub struct RemoteEntry {
+    pub name: String,
+    pub fetch_url: String,
+    pub push_url: String,
+}
+
+pub struct FileLogEntry {
+    pub entry: LogEntry,
+    // the path the file had at this revision which differs from the current one across renames
+    pub path: String,
+}
+
+pub struct ReflogEntry {
+    pub selector: String,
+    pub old_hash: String,
+    pub new_hash: String,
+    pub action: String,
+}
+
+#[derive(Clone, Copy)]
+pub enum Operation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+impl Operation {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Merge => "merge",
+            Self::Rebase => "rebase",
+            Self::CherryPick => "cherry-pick",
+            Self::Revert => "revert",
+            Self::Bisect => "bisect",
+        }
+    }
+}
+
+#[derive(Default)]
+pub enum RerereStatus {
+    #[default]
+    Unavailable,
+    Disabled,
+    Enabled {
+        resolved: Vec<String>,
+    },
+}
+
+pub struct ContainingRefs {
+    pub branches: Vec<String>,
+    pub tags: Vec<String>,
+}
+impl fmt::Display for ContainingRefs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (label, names)) in [("branches", &self.branches), ("tags", &self.tags)]
+            .iter()
+            .enumerate()
+        {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}:", label)?;
+            if names.is_empty() {
+                writeln!(f, "  (none)")?;
+            }
+            for name in names.iter() {
+                writeln!(f, "  {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct BranchDivergence {
+    pub head: String,
+    pub base: String,
+    pub merge_base: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+impl fmt::Display for BranchDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn plural(count: usize) -> &'static str {
+            if count == 1 {
+                ""
+            } else {
+                "s"
+            }
+        }
+
+        writeln!(f, "'{}' compared to '{}'", self.head, self.base)?;
+        writeln!(f, "merge base: {}", self.merge_base)?;
+        writeln!(f, "ahead: {} commit{}", self.ahead, plural(self.ahead))?;
+        writeln!(f, "behind: {} commit{}", self.behind, plural(self.behind))
+    }
+}
+
 #[derive(Clone)]
 pub struct SelectableRevisionEntry {
     pub selected: bool,
@@ -97,14 +285,15 @@ impl From<RevisionEntry> for SelectableRevisionEntry {
 }
 impl SelectEntryDraw for SelectableRevisionEntry {
     fn draw(&self, drawer: &mut Drawer, _: bool, _: bool) -> usize {
-        const NAME_TOO_LONG_PREFIX: &str = "...";
+        let name_too_long_prefix = drawer.glyphs.ellipsis;
 
-        let name_available_width = (drawer.viewport_size.0 as usize)
-            .saturating_sub(2 + 1 + FileStatus::max_len() + 1 + 1 + NAME_TOO_LONG_PREFIX.len());
+        let name_available_width = (drawer.viewport_size.0 as usize).saturating_sub(
+            2 + 1 + FileStatus::max_len() + 1 + 1 + name_too_long_prefix.chars().count(),
+        );
 
         let (name_prefix, trimmed_name) =
             match self.name.char_indices().nth_back(name_available_width) {
-                Some((i, _)) => (NAME_TOO_LONG_PREFIX, &self.name[i..]),
+                Some((i, _)) => (name_too_long_prefix, &self.name[i..]),
                 None => ("", &self.name[..]),
             };
 
@@ -124,41 +313,154 @@ impl SelectEntryDraw for SelectableRevisionEntry {
 
 pub trait Backend: 'static + Send + Sync {
     fn status(&self) -> BackendResult<StatusInfo>;
+    // the first line of the status header, without looking at the working tree
+    fn branch_header(&self) -> BackendResult<String>;
     fn commit(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()>;
+    fn commit_amend(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()>;
+    fn commit_hunks(&self, message: &str, file: &str, hunk_indices: &[usize]) -> BackendResult<()>;
+    fn last_commit_message(&self) -> BackendResult<String>;
     fn discard(&self, entries: &[RevisionEntry]) -> BackendResult<()>;
     fn diff(&self, revision: Option<&str>, entries: &[RevisionEntry]) -> BackendResult<String>;
     fn resolve_taking_ours(&self, entries: &[RevisionEntry]) -> BackendResult<()>;
     fn resolve_taking_theirs(&self, entries: &[RevisionEntry]) -> BackendResult<()>;
+    fn in_progress_operation(&self) -> BackendResult<Option<Operation>>;
+    fn continue_operation(&self, operation: Operation) -> BackendResult<()>;
+    fn rebase(&self, onto: &str) -> BackendResult<()>;
+    fn rebase_continue(&self) -> BackendResult<()>;
+    fn rebase_abort(&self) -> BackendResult<()>;
+    fn rebase_skip(&self) -> BackendResult<()>;
+    fn cherry_pick_continue(&self) -> BackendResult<()>;
+    fn cherry_pick_abort(&self) -> BackendResult<()>;
+    fn rerere_status(&self) -> BackendResult<RerereStatus>;
+    fn config_set(&self, key: &str, value: &str) -> BackendResult<()>;
 
-    fn log(&self, start: usize, len: usize) -> BackendResult<Vec<LogEntry>>;
+    fn log(
+        &self,
+        start: usize,
+        len: usize,
+        order: LogOrder,
+        graph: bool,
+        filter: &LogFilter,
+    ) -> BackendResult<Vec<LogEntry>>;
+    fn file_log(&self, path: &str, len: usize, follow: bool) -> BackendResult<Vec<FileLogEntry>>;
+    fn reflog(&self, len: usize) -> BackendResult<Vec<ReflogEntry>>;
     fn checkout(&self, revision: &str) -> BackendResult<()>;
     fn merge(&self, revision: &str) -> BackendResult<()>;
-    fn fetch(&self) -> BackendResult<()>;
-    fn pull(&self) -> BackendResult<()>;
-    fn push(&self) -> BackendResult<()>;
+    fn revert_commit(&self, revision: &str, no_commit: bool) -> BackendResult<()>;
+    fn reset(&self, revision: &str, mode: ResetMode) -> BackendResult<()>;
+    fn cherry_pick(&self, revisions: &[String]) -> BackendResult<()>;
+    // network operations return the warnings and hints they printed along the way
+    fn fetch(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String>;
+    fn pull(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String>;
+    fn push(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String>;
+    fn push_set_upstream(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String>;
+    // defaults to the upstream of the current branch when there is one
+    fn push_options(&self) -> BackendResult<PushOptions>;
+    fn push_with_options(
+        &self,
+        options: &PushOptions,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<String>;
 
     fn revision_details(&self, revision: &str) -> BackendResult<RevisionInfo>;
+    fn show(&self, revision: &str) -> BackendResult<String>;
+    fn containing_refs(&self, revision: &str) -> BackendResult<ContainingRefs>;
+    fn ref_candidates(&self) -> BackendResult<Vec<String>>;
 
     fn branches(&self) -> BackendResult<Vec<BranchEntry>>;
-    fn new_branch(&self, name: &str) -> BackendResult<()>;
+    fn branch_exists(&self, name: &str) -> BackendResult<bool>;
+    fn new_branch(&self, name: &str, start_point: Option<&str>, force: bool) -> BackendResult<()>;
     fn delete_branch(&self, name: &str) -> BackendResult<()>;
+    fn rename_branch(&self, old_name: &str, new_name: &str) -> BackendResult<()>;
+    fn branch_divergence(&self, base: &str) -> BackendResult<BranchDivergence>;
+    fn divergence(&self, head: &str, base: &str) -> BackendResult<BranchDivergence>;
 
     fn tags(&self) -> BackendResult<Vec<TagEntry>>;
-    fn new_tag(&self, name: &str) -> BackendResult<()>;
+    fn tag_exists(&self, name: &str) -> BackendResult<bool>;
+    fn new_tag(&self, name: &str, message: Option<&str>, force: bool) -> BackendResult<()>;
     fn delete_tag(&self, name: &str) -> BackendResult<()>;
+    fn delete_remote_tag(&self, name: &str) -> BackendResult<()>;
+
+    fn stash_list(&self) -> BackendResult<Vec<StashEntry>>;
+    fn stash_push(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()>;
+    fn stash_apply(&self, name: &str) -> BackendResult<()>;
+    fn stash_pop(&self, name: &str) -> BackendResult<()>;
+    fn stash_drop(&self, name: &str) -> BackendResult<()>;
+
+    fn remote_list(&self) -> BackendResult<Vec<RemoteEntry>>;
+    fn remote_add(&self, name: &str, url: &str) -> BackendResult<()>;
+    fn remote_remove(&self, name: &str) -> BackendResult<()>;
+    fn remote_rename(&self, old_name: &str, new_name: &str) -> BackendResult<()>;
+
+    fn sparse_checkout_list(&self) -> BackendResult<Option<Vec<String>>>;
+    fn sparse_checkout_set(&self, patterns: &[String]) -> BackendResult<()>;
+    fn sparse_checkout_disable(&self) -> BackendResult<()>;
+
+    fn clone_repository(
+        &self,
+        url: &str,
+        directory: &str,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<()>;
+    fn is_bare(&self) -> bool;
+    fn init(&self, path: &str) -> BackendResult<()>;
+}
+
+pub struct ProcessOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+impl ProcessOutput {
+    pub fn diagnostics(&self) -> String {
+        const PREFIXES: &[&str] = &["warning:", "hint:"];
+
+        let mut diagnostics = String::new();
+        for line in self.stderr.lines() {
+            let message = line.trim_start_matches("remote: ").trim_start();
+            if PREFIXES.iter().any(|p| message.starts_with(p)) {
+                diagnostics.push_str(line);
+                diagnostics.push('\n');
+            }
+        }
+        diagnostics
+    }
 }
 
 pub struct Process(Child);
 impl Process {
     pub fn spawn(command_name: &str, args: &[&str]) -> BackendResult<Self> {
+        Self::spawn_with_stdin(command_name, args, Stdio::null())
+    }
+
+    pub fn spawn_with_input(command_name: &str, args: &[&str], input: &str) -> BackendResult<Self> {
+        use io::Write;
+
+        let mut process = Self::spawn_with_stdin(command_name, args, Stdio::piped())?;
+        if let Some(mut stdin) = process.0.stdin.take() {
+            if let Err(error) = stdin.write_all(input.as_bytes()) {
+                return Err(format!(
+                    "could not write to process '{}': {}",
+                    command_name, error
+                ));
+            }
+        }
+        Ok(process)
+    }
+
+    fn spawn_with_stdin(command_name: &str, args: &[&str], stdin: Stdio) -> BackendResult<Self> {
         let mut command = Command::new(command_name);
         command.args(args);
-        command.stdin(Stdio::null());
+        command.env("GIT_PAGER", "cat");
+        command.env("PAGER", "cat");
+        command.stdin(stdin);
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
 
         match command.spawn() {
             Ok(child) => Ok(Self(child)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Err(format!("{} not found in PATH", command_name))
+            }
             Err(error) => Err(format!(
                 "could not spawn process '{}': {}",
                 command_name, error
@@ -167,25 +469,124 @@ impl Process {
     }
 
     pub fn wait(self) -> BackendResult<String> {
+        self.wait_output().map(|output| output.stdout)
+    }
+
+    pub fn wait_output(self) -> BackendResult<ProcessOutput> {
         let output = match self.0.wait_with_output() {
             Ok(output) => output,
             Err(error) => return Err(format!("could not wait for process: {}", error)),
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        if output.status.success() {
-            Ok(stdout.into())
+        Self::result(output.status, &output.stdout, &output.stderr)
+    }
+
+    pub fn wait_with_progress(
+        mut self,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<ProcessOutput> {
+        use io::Read;
+
+        let stdout = self.0.stdout.take();
+        let stdout = thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut stdout) = stdout {
+                let _ = stdout.read_to_end(&mut buf);
+            }
+            buf
+        });
+
+        let mut stderr_buf = Vec::new();
+        if let Some(mut stderr) = self.0.stderr.take() {
+            let mut buf = [0; 1024];
+            let mut line_start = 0;
+            loop {
+                let len = match stderr.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => len,
+                };
+
+                for &b in &buf[..len] {
+                    match b {
+                        b'\r' | b'\n' => {
+                            let line = String::from_utf8_lossy(&stderr_buf[line_start..]);
+                            let line = line.trim();
+                            if !line.is_empty() {
+                                progress(line);
+                            }
+
+                            if b == b'\r' {
+                                stderr_buf.truncate(line_start);
+                            } else {
+                                stderr_buf.push(b);
+                                line_start = stderr_buf.len();
+                            }
+                        }
+                        _ => stderr_buf.push(b),
+                    }
+                }
+            }
+        }
+
+        let status = match self.0.wait() {
+            Ok(status) => status,
+            Err(error) => return Err(format!("could not wait for process: {}", error)),
+        };
+        let stdout = stdout.join().unwrap_or_default();
+
+        Self::result(status, &stdout, &stderr_buf)
+    }
+
+    fn result(status: ExitStatus, stdout: &[u8], stderr: &[u8]) -> BackendResult<ProcessOutput> {
+        let stdout = String::from_utf8_lossy(stdout);
+        let stderr = String::from_utf8_lossy(stderr);
+        if status.success() {
+            Ok(ProcessOutput {
+                stdout: stdout.into(),
+                stderr: stderr.into(),
+            })
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
             let mut error = String::new();
-            error.push_str(&stdout);
-            error.push('\n');
+            if !stdout.trim().is_empty() {
+                error.push_str(stdout.trim_end());
+                error.push_str("\n\n");
+            }
             error.push_str(&stderr);
+            if error.trim().is_empty() {
+                error = format!("process failed with {}", status);
+            }
             Err(error)
         }
     }
 }
 
+pub fn is_program_available(command_name: &str) -> bool {
+    match Process::spawn(command_name, &[]) {
+        Ok(process) => {
+            let _ = process.wait();
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+pub const INIT_PROGRAMS: &[&str] = &["git", "hg"];
+
+pub fn backend_from_program(command_name: &str) -> Option<Arc<dyn Backend>> {
+    match command_name {
+        "git" => Some(Arc::new(git::Git::default())),
+        "hg" => Some(Arc::new(hg::Hg)),
+        _ => None,
+    }
+}
+
+pub fn backend_from_available_program() -> Option<Arc<dyn Backend>> {
+    INIT_PROGRAMS
+        .iter()
+        .find(|p| is_program_available(p))
+        .and_then(|p| backend_from_program(p))
+}
+
 pub fn backend_from_current_repository() -> Option<(PathBuf, Arc<dyn Backend>)> {
     if let Some((root, git)) = git::Git::try_new() {
         Some((root, Arc::new(git)))
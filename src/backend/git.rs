@@ -1,77 +1,236 @@
-use std::path::{Path, PathBuf};
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
 
-use crate::backend::{
-    Backend, BackendResult, BranchEntry, FileStatus, LogEntry, Process, RevisionEntry,
-    RevisionInfo, StatusInfo, TagEntry,
+use crate::{
+    backend::{
+        Backend, BackendResult, BranchDivergence, BranchEntry, ContainingRefs, FileLogEntry,
+        FileStatus, LogEntry, LogFilter, LogOrder, Operation, Process, PushOptions, ReflogEntry,
+        RemoteEntry, RerereStatus, ResetMode, RevisionEntry, RevisionInfo, SignatureStatus,
+        StashEntry, StatusInfo, TagEntry,
+    },
+    diff::FileDiff,
 };
 
-pub struct Git;
+#[derive(Default)]
+pub struct Git {
+    bare: bool,
+}
 
 impl Git {
     pub fn try_new() -> Option<(PathBuf, Self)> {
-        let output = Process::spawn("git", &["rev-parse", "--show-toplevel"])
+        // the current dir changes to the repository root once it's found,
+        // so relative paths in these would point somewhere else afterwards
+        for var in &["GIT_DIR", "GIT_WORK_TREE"] {
+            if let Some(path) = env::var_os(var) {
+                if let Ok(path) = fs::canonicalize(path) {
+                    env::set_var(var, path);
+                }
+            }
+        }
+
+        let bare = git(&["rev-parse", "--is-bare-repository"])
             .ok()?
             .wait()
-            .ok()?;
+            .ok()?
+            .trim()
+            == "true";
+
+        let root_arg = if bare {
+            "--absolute-git-dir"
+        } else {
+            "--show-toplevel"
+        };
+        let output = git(&["rev-parse", root_arg]).ok()?.wait().ok()?;
 
         let root = Path::new(output.trim()).into();
-        Some((root, Self))
+        Some((root, Self { bare }))
+    }
+
+    fn require_work_tree(&self) -> BackendResult<()> {
+        if self.bare {
+            Err("this operation needs a working tree but this is a bare repository".into())
+        } else {
+            Ok(())
+        }
     }
 }
 
 impl Backend for Git {
     fn status(&self) -> BackendResult<StatusInfo> {
-        let output = Process::spawn("git", &["status", "--branch", "--null"])?.wait()?;
-        let mut splits = output.split('\0').map(str::trim);
-
-        let header = splits.next().unwrap_or("").into();
-        let entries = splits
-            .filter(|e| e.len() >= 2)
-            .map(|e| {
-                let (status, filename) = e.split_at(2);
-                RevisionEntry {
-                    name: filename.trim().into(),
-                    status: parse_file_status(status.trim()),
+        self.require_work_tree()?;
+
+        let output = git(&["status", "--porcelain=v2", "--branch", "-z"])?.wait()?;
+
+        let mut branch = BranchHeader::default();
+        let mut entries = Vec::new();
+        let mut mode_changes = Vec::new();
+        let mut unstaged_mode_changes = Vec::new();
+        let mut fields = output.split('\0');
+        while let Some(field) = fields.next() {
+            if let Some(header) = field.strip_prefix("# ") {
+                branch.parse_line(header);
+                continue;
+            }
+
+            let (kind, rest) = field.split_at(field.len().min(2));
+            let (status, name) = match kind {
+                "? " => (FileStatus::Untracked, rest),
+                // ordinary entries are "1 XY sub mH mI mW hH hI path"
+                "1 " => match rest.splitn(8, ' ').collect::<Vec<_>>()[..] {
+                    [xy, _, head_mode, _, worktree_mode, head_hash, index_hash, name] => {
+                        if head_mode != worktree_mode
+                            && worktree_mode != "000000"
+                            && head_hash == index_hash
+                        {
+                            if xy.ends_with('.') {
+                                mode_changes.push(name.into());
+                            } else {
+                                unstaged_mode_changes.push(name);
+                            }
+                        }
+                        (parse_status_code(xy), name)
+                    }
+                    _ => continue,
+                },
+                // renames and copies are followed by the original path
+                "2 " => {
+                    fields.next();
+                    match rest.splitn(9, ' ').collect::<Vec<_>>()[..] {
+                        [xy, _, _, _, _, _, _, _, name] => (parse_status_code(xy), name),
+                        _ => continue,
+                    }
                 }
-            })
-            .collect();
+                "u " => match rest.splitn(10, ' ').nth(9) {
+                    Some(name) => (FileStatus::Unmerged, name),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            entries.push(RevisionEntry {
+                name: name.into(),
+                status,
+            });
+        }
+
+        // porcelain v2 has no worktree hash, so unstaged mode changes need a content check
+        if !unstaged_mode_changes.is_empty() {
+            let mut args = vec!["diff", "--numstat", "--"];
+            args.extend(&unstaged_mode_changes);
+            let output = git(&args)?.wait()?;
+            mode_changes.extend(
+                output
+                    .lines()
+                    .filter_map(|l| l.strip_prefix("0\t0\t"))
+                    .map(String::from),
+            );
+        }
 
-        Ok(StatusInfo { header, entries })
+        Ok(StatusInfo {
+            header: branch.to_string(),
+            entries,
+            mode_changes,
+        })
+    }
+
+    fn branch_header(&self) -> BackendResult<String> {
+        let head = match git(&["symbolic-ref", "--quiet", "--short", "HEAD"])?.wait() {
+            Ok(head) => head.trim().into(),
+            Err(_) => "(detached)".into(),
+        };
+        let oid = match git(&["rev-parse", "--verify", "--quiet", "HEAD"])?.wait() {
+            Ok(oid) => oid.trim().into(),
+            Err(_) => "(initial)".into(),
+        };
+        let mut branch = BranchHeader {
+            oid,
+            head,
+            ..Default::default()
+        };
+        if let Ok(upstream) = git(&["rev-parse", "--abbrev-ref", "@{upstream}"])?.wait() {
+            branch.upstream = Some(upstream.trim().into());
+            let counts =
+                git(&["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])?.wait()?;
+            if let Some((ahead, behind)) = counts.trim().split_once('\t') {
+                branch.ahead_behind = Some((ahead.into(), behind.into()));
+            }
+        }
+        Ok(branch.to_string())
     }
 
     fn commit(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
         if entries.is_empty() {
-            Process::spawn("git", &["add", "--all"])?.wait()?;
+            git(&["add", "--all"])?.wait()?;
         } else {
-            let mut args = Vec::new();
-            args.push("add");
-            args.push("--");
+            let mut args = vec!["add", "--"];
             for entry in entries {
                 args.push(&entry.name);
             }
 
-            Process::spawn("git", &args)?.wait()?;
+            git(&args)?.wait()?;
         }
 
-        Process::spawn("git", &["commit", "-m", message])?.wait()?;
+        git(&["commit", "-m", message])?.wait()?;
         Ok(())
     }
 
+    fn commit_hunks(&self, message: &str, file: &str, hunk_indices: &[usize]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        if hunk_indices.is_empty() {
+            return Err("no hunks selected".into());
+        }
+
+        let diff = git(&["diff", "--", file])?.wait()?;
+        let patch = FileDiff::parse(&diff).patch(hunk_indices)?;
+        git_with_input(&["apply", "--cached", "-"], &patch)?.wait()?;
+
+        git(&["commit", "-m", message])?.wait()?;
+        Ok(())
+    }
+
+    fn commit_amend(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        // with nothing selected only the message changes
+        if !entries.is_empty() {
+            let mut args = vec!["add", "--"];
+            for entry in entries {
+                args.push(&entry.name);
+            }
+
+            git(&args)?.wait()?;
+        }
+
+        if message.is_empty() {
+            git(&["commit", "--amend", "--no-edit"])?.wait()?;
+        } else {
+            git(&["commit", "--amend", "-m", message])?.wait()?;
+        }
+        Ok(())
+    }
+
+    fn last_commit_message(&self) -> BackendResult<String> {
+        git(&["log", "-1", "--format=%B"])?.wait()
+    }
+
     fn discard(&self, entries: &[RevisionEntry]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
         if entries.is_empty() {
-            Process::spawn("git", &["reset", "--hard"])?.wait()?;
-            Process::spawn("git", &["clean", "-d", "--force"])?.wait()?;
+            git(&["reset", "--hard"])?.wait()?;
+            git(&["clean", "-d", "--force"])?.wait()?;
         } else {
-            let mut args = Vec::new();
-            args.push("clean");
-            args.push("--force");
-            args.push("--");
+            let mut args = vec!["clean", "--force", "--"];
             for entry in entries {
                 if let FileStatus::Untracked = entry.status {
                     args.push(&entry.name);
                 }
             }
-            let clean = Process::spawn("git", &args)?;
+            let clean = git(&args)?;
 
             args.clear();
             args.push("rm");
@@ -82,7 +241,7 @@ impl Backend for Git {
                     args.push(&entry.name);
                 }
             }
-            let rm = Process::spawn("git", &args)?;
+            let rm = git(&args)?;
 
             clean.wait()?;
             rm.wait()?;
@@ -95,7 +254,7 @@ impl Backend for Git {
                     args.push(&entry.name);
                 }
             }
-            Process::spawn("git", &args)?.wait()?;
+            git(&args)?.wait()?;
         }
 
         Ok(())
@@ -106,39 +265,35 @@ impl Backend for Git {
             Some(revision) => {
                 let parent = format!("{}^@", revision);
                 if entries.is_empty() {
-                    Process::spawn("git", &["diff", &parent, revision])?.wait()
+                    git(&["diff", &parent, revision])?.wait()
                 } else {
-                    let mut args = Vec::new();
-                    args.push("diff");
-                    args.push(&parent);
-                    args.push(revision);
-                    args.push("--");
+                    let mut args = vec!["diff", &parent, revision, "--"];
                     for entry in entries {
                         args.push(&entry.name);
                     }
 
-                    Process::spawn("git", &args)?.wait()
+                    git(&args)?.wait()
                 }
             }
             None => {
                 if entries.is_empty() {
-                    Process::spawn("git", &["diff", "-z"])?.wait()
+                    git(&["diff", "-z"])?.wait()
                 } else {
-                    let mut args = Vec::new();
-                    args.push("diff");
-                    args.push("--");
+                    let mut args = vec!["diff", "--"];
                     for entry in entries {
                         args.push(&entry.name);
                     }
-                    Process::spawn("git", &args)?.wait()
+                    git(&args)?.wait()
                 }
             }
         }
     }
 
     fn resolve_taking_ours(&self, entries: &[RevisionEntry]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
         if entries.is_empty() {
-            Process::spawn("git", &["checkout", ".", "--ours"])?.wait()?;
+            git(&["checkout", ".", "--ours"])?.wait()?;
         } else {
             if !entries
                 .iter()
@@ -147,11 +302,7 @@ impl Backend for Git {
                 return Ok(());
             }
 
-            let mut args = Vec::new();
-            args.push("checkout");
-            args.push(".");
-            args.push("--ours");
-            args.push("--");
+            let mut args = vec!["checkout", ".", "--ours", "--"];
 
             for entry in entries {
                 if let FileStatus::Unmerged = entry.status {
@@ -159,15 +310,17 @@ impl Backend for Git {
                 }
             }
 
-            Process::spawn("git", &args)?.wait()?;
+            git(&args)?.wait()?;
         }
 
         Ok(())
     }
 
     fn resolve_taking_theirs(&self, entries: &[RevisionEntry]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
         if entries.is_empty() {
-            Process::spawn("git", &["checkout", ".", "--theirs"])?.wait()?;
+            git(&["checkout", ".", "--theirs"])?.wait()?;
         } else {
             if !entries
                 .iter()
@@ -176,11 +329,7 @@ impl Backend for Git {
                 return Ok(());
             }
 
-            let mut args = Vec::new();
-            args.push("checkout");
-            args.push(".");
-            args.push("--theirs");
-            args.push("--");
+            let mut args = vec!["checkout", ".", "--theirs", "--"];
 
             for entry in entries {
                 if let FileStatus::Unmerged = entry.status {
@@ -188,32 +337,150 @@ impl Backend for Git {
                 }
             }
 
-            Process::spawn("git", &args)?.wait()?;
+            git(&args)?.wait()?;
+        }
+
+        Ok(())
+    }
+
+    fn in_progress_operation(&self) -> BackendResult<Option<Operation>> {
+        let git_dir = git(&["rev-parse", "--git-dir"])?.wait()?;
+        let git_dir = Path::new(git_dir.trim());
+
+        let operation =
+            if git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists() {
+                Some(Operation::Rebase)
+            } else if git_dir.join("MERGE_HEAD").exists() {
+                Some(Operation::Merge)
+            } else if git_dir.join("CHERRY_PICK_HEAD").exists() {
+                Some(Operation::CherryPick)
+            } else if git_dir.join("REVERT_HEAD").exists() {
+                Some(Operation::Revert)
+            } else if git_dir.join("BISECT_LOG").exists() {
+                Some(Operation::Bisect)
+            } else {
+                None
+            };
+        Ok(operation)
+    }
+
+    fn continue_operation(&self, operation: Operation) -> BackendResult<()> {
+        let args: &[&str] = match operation {
+            Operation::Merge => &["commit", "--no-edit"],
+            Operation::Rebase => return self.rebase_continue(),
+            Operation::CherryPick => return self.cherry_pick_continue(),
+            Operation::Revert => &["-c", "core.editor=true", "revert", "--continue"],
+            Operation::Bisect => return Err("bisect can not be continued".into()),
+        };
+        git(args)?.wait()?;
+        Ok(())
+    }
+
+    fn rebase(&self, onto: &str) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        git(&["-c", "core.editor=true", "rebase", onto])?.wait()?;
+        Ok(())
+    }
+
+    fn rebase_continue(&self) -> BackendResult<()> {
+        git(&["-c", "core.editor=true", "rebase", "--continue"])?.wait()?;
+        Ok(())
+    }
+
+    fn rebase_abort(&self) -> BackendResult<()> {
+        git(&["rebase", "--abort"])?.wait()?;
+        Ok(())
+    }
+
+    fn rebase_skip(&self) -> BackendResult<()> {
+        git(&["rebase", "--skip"])?.wait()?;
+        Ok(())
+    }
+
+    fn cherry_pick_continue(&self) -> BackendResult<()> {
+        git(&["-c", "core.editor=true", "cherry-pick", "--continue"])?.wait()?;
+        Ok(())
+    }
+
+    fn cherry_pick_abort(&self) -> BackendResult<()> {
+        git(&["cherry-pick", "--abort"])?.wait()?;
+        Ok(())
+    }
+
+    fn rerere_status(&self) -> BackendResult<RerereStatus> {
+        let enabled = git(&["config", "--bool", "rerere.enabled"])?
+            .wait()
+            .map(|output| output.trim() == "true")
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(RerereStatus::Disabled);
         }
 
+        let recorded = git(&["rerere", "status"])?;
+        let remaining = git(&["rerere", "remaining"])?;
+        let recorded = recorded.wait()?;
+        let remaining = remaining.wait()?;
+
+        let resolved = recorded
+            .lines()
+            .filter(|r| !remaining.lines().any(|l| l == *r))
+            .map(String::from)
+            .collect();
+        Ok(RerereStatus::Enabled { resolved })
+    }
+
+    fn config_set(&self, key: &str, value: &str) -> BackendResult<()> {
+        git(&["config", key, value])?.wait()?;
         Ok(())
     }
 
-    fn log(&self, skip: usize, len: usize) -> BackendResult<Vec<LogEntry>> {
+    fn log(
+        &self,
+        skip: usize,
+        len: usize,
+        order: LogOrder,
+        graph: bool,
+        filter: &LogFilter,
+    ) -> BackendResult<Vec<LogEntry>> {
         let skip = skip.to_string();
         let len = len.to_string();
-        let template = "--format=format:%x00%h%x00%as%x00%aN%x00%D%x00%s";
-        let output = Process::spawn(
-            "git",
-            &[
-                "log",
-                "--all",
-                "--decorate",
-                "--oneline",
-                "--graph",
-                "--skip",
-                &skip,
-                "--max-count",
-                &len,
-                template,
-            ],
-        )?
-        .wait()?;
+        let grep = format!("--grep={}", filter.message);
+        let since = format!("--since={}", filter.since);
+        let until = format!("--until={}", filter.until);
+        let template = "--format=format:%x00%h%x00%at%x00%aN%x00%D%x00%s";
+        let mut args = vec![
+            "log",
+            "--all",
+            "--decorate",
+            "--oneline",
+            "--skip",
+            &skip,
+            "--max-count",
+            &len,
+            template,
+        ];
+        if graph {
+            args.push("--graph");
+        }
+        if !filter.message.is_empty() {
+            args.push("--regexp-ignore-case");
+            args.push(&grep);
+        }
+        if !filter.since.is_empty() {
+            check_date(&filter.since)?;
+            args.push(&since);
+        }
+        if !filter.until.is_empty() {
+            check_date(&filter.until)?;
+            args.push(&until);
+        }
+        match order {
+            LogOrder::Default => (),
+            LogOrder::Date => args.push("--date-order"),
+            LogOrder::Topological => args.push("--topo-order"),
+        }
+        let output = git(&args)?.wait()?;
 
         let mut entries = Vec::new();
         for line in output.lines() {
@@ -221,7 +488,7 @@ impl Backend for Git {
 
             let graph = splits.next().unwrap_or("").into();
             let hash = splits.next().unwrap_or("").into();
-            let date = splits.next().unwrap_or("").into();
+            let timestamp = splits.next().unwrap_or("").parse().unwrap_or(0);
             let author = splits.next().unwrap_or("").into();
             let refs = splits.next().unwrap_or("").into();
             let message = splits.next().unwrap_or("").into();
@@ -229,8 +496,10 @@ impl Backend for Git {
             entries.push(LogEntry {
                 graph,
                 hash,
-                date,
+                timestamp,
+                date: String::new(),
                 author,
+                phase: None,
                 refs,
                 message,
             });
@@ -239,56 +508,227 @@ impl Backend for Git {
         Ok(entries)
     }
 
+    fn file_log(&self, path: &str, len: usize, follow: bool) -> BackendResult<Vec<FileLogEntry>> {
+        let len = len.to_string();
+        let mut args = vec![
+            "log",
+            "--decorate",
+            "--name-only",
+            "--max-count",
+            &len,
+            "--format=format:%x00%h%x00%at%x00%aN%x00%D%x00%s",
+        ];
+        if follow {
+            args.push("--follow");
+        }
+        args.push("--");
+        args.push(path);
+        let output = git(&args)?.wait()?;
+
+        let mut entries: Vec<FileLogEntry> = Vec::new();
+        for line in output.lines() {
+            if let Some(line) = line.strip_prefix('\0') {
+                let mut splits = line.splitn(5, '\0');
+
+                let hash = splits.next().unwrap_or("").into();
+                let timestamp = splits.next().unwrap_or("").parse().unwrap_or(0);
+                let author = splits.next().unwrap_or("").into();
+                let refs = splits.next().unwrap_or("").into();
+                let message = splits.next().unwrap_or("").into();
+
+                entries.push(FileLogEntry {
+                    entry: LogEntry {
+                        graph: String::new(),
+                        hash,
+                        timestamp,
+                        date: String::new(),
+                        author,
+                        phase: None,
+                        refs,
+                        message,
+                    },
+                    path: path.into(),
+                });
+            } else if !line.is_empty() {
+                // --name-only lists the file under the name it had in that commit
+                if let Some(entry) = entries.last_mut() {
+                    entry.path = line.into();
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn reflog(&self, len: usize) -> BackendResult<Vec<ReflogEntry>> {
+        let len = len.to_string();
+        let output = git(&["reflog", "--format=%gd%x00%h%x00%gs", "--max-count", &len])?.wait()?;
+
+        let mut entries: Vec<ReflogEntry> = Vec::new();
+        for line in output.lines() {
+            let mut splits = line.splitn(3, '\0');
+            let selector = splits.next().unwrap_or("").into();
+            let new_hash: String = splits.next().unwrap_or("").into();
+            let action = splits.next().unwrap_or("").into();
+
+            // entries are listed newest first, so this hash is what the previous one moved from
+            if let Some(previous) = entries.last_mut() {
+                previous.old_hash = new_hash.clone();
+            }
+            entries.push(ReflogEntry {
+                selector,
+                old_hash: String::new(),
+                new_hash,
+                action,
+            });
+        }
+
+        Ok(entries)
+    }
+
     fn checkout(&self, revision: &str) -> BackendResult<()> {
-        Process::spawn("git", &["checkout", revision])?.wait()?;
+        self.require_work_tree()?;
+
+        git(&["checkout", revision])?.wait()?;
         Ok(())
     }
 
     fn merge(&self, revision: &str) -> BackendResult<()> {
-        Process::spawn("git", &["merge", revision])?.wait()?;
+        self.require_work_tree()?;
+
+        git(&["merge", revision])?.wait()?;
         Ok(())
     }
 
-    fn fetch(&self) -> BackendResult<()> {
-        Process::spawn("git", &["fetch", "--all"])?.wait()?;
+    fn revert_commit(&self, revision: &str, no_commit: bool) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        if no_commit {
+            git(&["revert", "--no-commit", revision])?.wait()?;
+        } else {
+            git(&["revert", "--no-edit", revision])?.wait()?;
+        }
         Ok(())
     }
 
-    fn pull(&self) -> BackendResult<()> {
-        Process::spawn("git", &["pull", "--all"])?.wait()?;
+    fn reset(&self, revision: &str, mode: ResetMode) -> BackendResult<()> {
+        let mode = match mode {
+            ResetMode::Soft => "--soft",
+            ResetMode::Mixed => "--mixed",
+            ResetMode::Hard => "--hard",
+        };
+        git(&["reset", mode, revision])?.wait()?;
         Ok(())
     }
 
-    fn push(&self) -> BackendResult<()> {
-        Process::spawn("git", &["push"])?.wait()?;
+    fn cherry_pick(&self, revisions: &[String]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        let mut args = vec!["cherry-pick"];
+        for revision in revisions {
+            args.push(&revision[..]);
+        }
+        git(&args)?.wait()?;
         Ok(())
     }
 
+    fn fetch(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let output = git(&["fetch", "--all", "--progress"])?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn pull(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let output = git(&["pull", "--all", "--progress"])?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn push(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let output = git(&["push", "--progress"])?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn push_set_upstream(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let remote = default_remote()?;
+        let output = git(&["push", "--progress", "--set-upstream", &remote, "HEAD"])?
+            .wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn push_options(&self) -> BackendResult<PushOptions> {
+        let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])?.wait()?;
+        let branch = branch.trim();
+        if branch == "HEAD" {
+            return Err("can not push a detached HEAD".into());
+        }
+
+        let remote_key = format!("branch.{}.remote", branch);
+        let merge_key = format!("branch.{}.merge", branch);
+        let remote = git(&["config", &remote_key])?.wait();
+        let merge = git(&["config", &merge_key])?.wait();
+
+        if let (Ok(remote), Ok(merge)) = (remote, merge) {
+            let merge = merge.trim();
+            return Ok(PushOptions {
+                remote: remote.trim().into(),
+                branch: merge.strip_prefix("refs/heads/").unwrap_or(merge).into(),
+                force_with_lease: false,
+                set_upstream: false,
+            });
+        }
+
+        Ok(PushOptions {
+            remote: default_remote()?,
+            branch: branch.into(),
+            force_with_lease: false,
+            set_upstream: true,
+        })
+    }
+
+    fn push_with_options(
+        &self,
+        options: &PushOptions,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<String> {
+        let refspec = format!("HEAD:{}", options.branch);
+        let mut args = vec!["push", "--progress"];
+        if options.force_with_lease {
+            args.push("--force-with-lease");
+        }
+        if options.set_upstream {
+            args.push("--set-upstream");
+        }
+        args.push(&options.remote);
+        args.push(&refspec);
+        let output = git(&args)?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
     fn revision_details(&self, revision: &str) -> BackendResult<RevisionInfo> {
-        let message = Process::spawn("git", &["show", "-s", "--format=%B", revision])?;
-        let changes = Process::spawn(
-            "git",
-            &[
-                "diff-tree",
-                "--no-commit-id",
-                "--name-status",
-                "-r",
-                "-z",
-                revision,
-            ],
-        )?;
+        let message = git(&["show", "-s", "--format=%B", revision])?;
+        let signature = git(&["show", "-s", "--format=%G?", revision])?;
+        let changes = git(&[
+            "diff-tree",
+            "--no-commit-id",
+            "--name-status",
+            "-r",
+            "-z",
+            revision,
+        ])?;
 
         let message = message.wait()?.trim().into();
+        let signature = match signature.wait()?.trim() {
+            "G" | "U" => SignatureStatus::Good,
+            "B" | "X" | "Y" | "R" => SignatureStatus::Bad,
+            "E" => SignatureStatus::Unverified,
+            _ => SignatureStatus::Unsigned,
+        };
 
         let changes = changes.wait()?;
         let mut splits = changes.split('\0').map(str::trim);
 
         let mut entries = Vec::new();
-        loop {
-            let status = match splits.next() {
-                Some(status) => parse_file_status(status),
-                None => break,
-            };
+        while let Some(status) = splits.next() {
+            let status = parse_file_status(status);
             let name = match splits.next() {
                 Some(name) => name.into(),
                 None => break,
@@ -297,19 +737,67 @@ impl Backend for Git {
             entries.push(RevisionEntry { name, status });
         }
 
-        Ok(RevisionInfo { message, entries })
+        Ok(RevisionInfo {
+            message,
+            signature: Some(signature),
+            entries,
+        })
+    }
+
+    fn show(&self, revision: &str) -> BackendResult<String> {
+        // merge commits are diffed against their first parent instead of showing a combined diff
+        git(&[
+            "show",
+            "-m",
+            "--first-parent",
+            "--stat",
+            "--patch",
+            revision,
+        ])?
+        .wait()
+    }
+
+    fn containing_refs(&self, revision: &str) -> BackendResult<ContainingRefs> {
+        let branches = git(&[
+            "branch",
+            "--all",
+            "--contains",
+            revision,
+            "--format=%(refname:short)",
+        ])?;
+        let tags = git(&["tag", "--contains", revision, "--format=%(refname:short)"])?;
+
+        let branches = branches.wait()?.lines().map(String::from).collect();
+        let tags = tags.wait()?.lines().map(String::from).collect();
+        Ok(ContainingRefs { branches, tags })
+    }
+
+    fn ref_candidates(&self) -> BackendResult<Vec<String>> {
+        let refs = git(&[
+            "for-each-ref",
+            "--format=%(refname:short)",
+            "refs/heads",
+            "refs/tags",
+            "refs/remotes",
+        ])?;
+        let hashes = git(&["log", "--format=%h", "--max-count=50"])?;
+
+        let refs = refs.wait()?;
+        let hashes = hashes.wait()?;
+        Ok(refs
+            .lines()
+            .chain(hashes.lines())
+            .map(String::from)
+            .collect())
     }
 
     fn branches(&self) -> BackendResult<Vec<BranchEntry>> {
-        let entries = Process::spawn(
-            "git",
-            &[
-                "branch",
-                "--list",
-                "--all",
-                "--format=%(refname:short)%00%(HEAD)",
-            ],
-        )?
+        let entries = git(&[
+            "branch",
+            "--list",
+            "--all",
+            "--format=%(refname:short)%00%(HEAD)",
+        ])?
         .wait()?
         .lines()
         .map(|l| {
@@ -322,23 +810,68 @@ impl Backend for Git {
         Ok(entries)
     }
 
-    fn new_branch(&self, name: &str) -> BackendResult<()> {
-        let remote = Process::spawn("git", &["remote"])?.wait()?;
-        Process::spawn("git", &["branch", name])?.wait()?;
-        Process::spawn("git", &["checkout", name])?.wait()?;
-        Process::spawn("git", &["push", "--set-upstream", remote.trim(), name])?.wait()?;
+    fn branch_exists(&self, name: &str) -> BackendResult<bool> {
+        let reference = format!("refs/heads/{}", name);
+        let process = git(&["rev-parse", "--verify", "--quiet", &reference])?;
+        Ok(process.wait().is_ok())
+    }
+
+    fn new_branch(&self, name: &str, start_point: Option<&str>, force: bool) -> BackendResult<()> {
+        let remote = git(&["remote"])?.wait()?;
+        let mut args = vec!["branch"];
+        if force {
+            args.push("--force");
+        }
+        args.push(name);
+        args.extend(start_point);
+        git(&args)?.wait()?;
+        git(&["checkout", name])?.wait()?;
+
+        // an overwrite only replaces the local branch, the remote one is left untouched
+        if !force {
+            git(&["push", "--set-upstream", remote.trim(), name])?.wait()?;
+        }
         Ok(())
     }
 
     fn delete_branch(&self, name: &str) -> BackendResult<()> {
-        let remote = Process::spawn("git", &["remote"])?.wait()?;
-        Process::spawn("git", &["branch", "--delete", name])?.wait()?;
-        Process::spawn("git", &["push", "--delete", remote.trim(), name])?.wait()?;
+        let remote = git(&["remote"])?.wait()?;
+        git(&["branch", "--delete", name])?.wait()?;
+        git(&["push", "--delete", remote.trim(), name])?.wait()?;
+        Ok(())
+    }
+
+    fn rename_branch(&self, old_name: &str, new_name: &str) -> BackendResult<()> {
+        git(&["branch", "--move", old_name, new_name])?.wait()?;
         Ok(())
     }
 
+    fn branch_divergence(&self, base: &str) -> BackendResult<BranchDivergence> {
+        self.divergence("HEAD", base)
+    }
+
+    fn divergence(&self, head: &str, base: &str) -> BackendResult<BranchDivergence> {
+        let range = format!("{}...{}", head, base);
+        let merge_base = git(&["merge-base", head, base])?;
+        let counts = git(&["rev-list", "--left-right", "--count", &range])?;
+
+        let merge_base = merge_base.wait()?.trim().into();
+        let counts = counts.wait()?;
+        let mut counts = counts.split_whitespace().map(|c| c.parse().unwrap_or(0));
+        let ahead = counts.next().unwrap_or(0);
+        let behind = counts.next().unwrap_or(0);
+
+        Ok(BranchDivergence {
+            head: head.into(),
+            base: base.into(),
+            merge_base,
+            ahead,
+            behind,
+        })
+    }
+
     fn tags(&self) -> BackendResult<Vec<TagEntry>> {
-        let entries = Process::spawn("git", &["tag", "--list", "--format=%(refname:short)"])?
+        let entries = git(&["tag", "--list", "--format=%(refname:short)"])?
             .wait()?
             .lines()
             .map(|l| TagEntry { name: l.into() })
@@ -346,17 +879,310 @@ impl Backend for Git {
         Ok(entries)
     }
 
-    fn new_tag(&self, name: &str) -> BackendResult<()> {
-        let remote = Process::spawn("git", &["remote"])?.wait()?;
-        Process::spawn("git", &["tag", "--force", name])?.wait()?;
-        Process::spawn("git", &["push", remote.trim(), name])?.wait()?;
+    fn tag_exists(&self, name: &str) -> BackendResult<bool> {
+        let reference = format!("refs/tags/{}", name);
+        let process = git(&["rev-parse", "--verify", "--quiet", &reference])?;
+        Ok(process.wait().is_ok())
+    }
+
+    fn new_tag(&self, name: &str, message: Option<&str>, force: bool) -> BackendResult<()> {
+        let remote = git(&["remote"])?.wait()?;
+        let mut args = vec!["tag"];
+        if let Some(message) = message {
+            args.extend(["--annotate", "--message", message]);
+        }
+        if force {
+            args.push("--force");
+        }
+        args.push(name);
+        git(&args)?.wait()?;
+
+        // an overwrite only replaces the local tag, the remote one is left untouched
+        if !force {
+            git(&["push", remote.trim(), name])?.wait()?;
+        }
         Ok(())
     }
 
     fn delete_tag(&self, name: &str) -> BackendResult<()> {
-        let remote = Process::spawn("git", &["remote"])?.wait()?;
-        Process::spawn("git", &["tag", "--delete", name])?.wait()?;
-        Process::spawn("git", &["push", "--delete", remote.trim(), name])?.wait()?;
+        git(&["tag", "--delete", name])?.wait()?;
+        Ok(())
+    }
+
+    fn delete_remote_tag(&self, name: &str) -> BackendResult<()> {
+        let remote = default_remote()?;
+        git(&["push", "--delete", &remote, name])?.wait()?;
+        Ok(())
+    }
+
+    fn stash_list(&self) -> BackendResult<Vec<StashEntry>> {
+        let entries = git(&["stash", "list", "--format=%gd%x00%s"])?
+            .wait()?
+            .lines()
+            .map(|l| {
+                let mut splits = l.splitn(2, '\0');
+                let name = splits.next().unwrap_or("").into();
+                let message = splits.next().unwrap_or("").into();
+                StashEntry { name, message }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn stash_push(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        let mut args = vec!["stash", "push", "--include-untracked"];
+        if !message.is_empty() {
+            args.extend(&["--message", message]);
+        }
+        if !entries.is_empty() {
+            args.push("--");
+            for entry in entries {
+                args.push(&entry.name);
+            }
+        }
+        git(&args)?.wait()?;
+        Ok(())
+    }
+
+    fn stash_apply(&self, name: &str) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        git(&["stash", "apply", name])?.wait()?;
+        Ok(())
+    }
+
+    fn stash_pop(&self, name: &str) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        git(&["stash", "pop", name])?.wait()?;
+        Ok(())
+    }
+
+    fn stash_drop(&self, name: &str) -> BackendResult<()> {
+        git(&["stash", "drop", name])?.wait()?;
+        Ok(())
+    }
+
+    fn remote_list(&self) -> BackendResult<Vec<RemoteEntry>> {
+        let output = git(&["remote", "--verbose"])?.wait()?;
+
+        let mut entries: Vec<RemoteEntry> = Vec::new();
+        for line in output.lines() {
+            let mut splits = line.splitn(2, '\t');
+            let name = splits.next().unwrap_or("");
+            let rest = splits.next().unwrap_or("");
+            let (url, kind) = match rest.rfind(" (") {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, ""),
+            };
+
+            let entry = match entries.iter_mut().find(|e| e.name == name) {
+                Some(entry) => entry,
+                None => {
+                    entries.push(RemoteEntry {
+                        name: name.into(),
+                        fetch_url: String::new(),
+                        push_url: String::new(),
+                    });
+                    entries.last_mut().unwrap()
+                }
+            };
+            match kind {
+                " (push)" => entry.push_url = url.into(),
+                _ => entry.fetch_url = url.into(),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn remote_add(&self, name: &str, url: &str) -> BackendResult<()> {
+        git(&["remote", "add", name, url])?.wait()?;
+        Ok(())
+    }
+
+    fn remote_remove(&self, name: &str) -> BackendResult<()> {
+        git(&["remote", "remove", name])?.wait()?;
+        Ok(())
+    }
+
+    fn remote_rename(&self, old_name: &str, new_name: &str) -> BackendResult<()> {
+        git(&["remote", "rename", old_name, new_name])?.wait()?;
+        Ok(())
+    }
+
+    fn sparse_checkout_list(&self) -> BackendResult<Option<Vec<String>>> {
+        self.require_work_tree()?;
+
+        let enabled = git(&["config", "--bool", "core.sparseCheckout"])?
+            .wait()
+            .map(|output| output.trim() == "true")
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+
+        let patterns = git(&["sparse-checkout", "list"])?
+            .wait()?
+            .lines()
+            .map(String::from)
+            .collect();
+        Ok(Some(patterns))
+    }
+
+    fn sparse_checkout_set(&self, patterns: &[String]) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        let mut args = vec!["sparse-checkout", "set", "--"];
+        args.extend(patterns.iter().map(String::as_str));
+        git(&args)?.wait()?;
+        Ok(())
+    }
+
+    fn sparse_checkout_disable(&self) -> BackendResult<()> {
+        self.require_work_tree()?;
+
+        git(&["sparse-checkout", "disable"])?.wait()?;
+        Ok(())
+    }
+
+    fn clone_repository(
+        &self,
+        url: &str,
+        directory: &str,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<()> {
+        git(&["clone", "--progress", url, directory])?.wait_with_progress(progress)?;
+        Ok(())
+    }
+
+    fn is_bare(&self) -> bool {
+        self.bare
+    }
+
+    fn init(&self, path: &str) -> BackendResult<()> {
+        git(&["init", path])?.wait()?;
+        Ok(())
+    }
+}
+
+// user config that would change the output parsed here is overridden
+const CONFIG_OVERRIDES: &[&str] = &[
+    "-c",
+    "color.ui=never",
+    "-c",
+    "color.branch=never",
+    "-c",
+    "color.diff=never",
+    "-c",
+    "color.status=never",
+    "-c",
+    "core.quotePath=false",
+    "-c",
+    "diff.noprefix=false",
+    "-c",
+    "diff.mnemonicPrefix=false",
+    "-c",
+    "log.date=default",
+    "-c",
+    "log.showSignature=false",
+    "-c",
+    "status.relativePaths=false",
+];
+
+fn git(args: &[&str]) -> BackendResult<Process> {
+    let mut all_args = CONFIG_OVERRIDES.to_vec();
+    all_args.extend_from_slice(args);
+    Process::spawn("git", &all_args)
+}
+
+fn git_with_input(args: &[&str], input: &str) -> BackendResult<Process> {
+    let mut all_args = CONFIG_OVERRIDES.to_vec();
+    all_args.extend_from_slice(args);
+    Process::spawn_with_input("git", &all_args, input)
+}
+
+// prefers origin, falling back to the first remote
+fn default_remote() -> BackendResult<String> {
+    let remotes = git(&["remote"])?.wait()?;
+    match remotes.lines().find(|&r| r == "origin") {
+        Some(remote) => Ok(remote.into()),
+        None => remotes
+            .lines()
+            .next()
+            .map(Into::into)
+            .ok_or_else(|| "there are no remotes".into()),
+    }
+}
+
+// git silently falls back to the current time for dates it can't make sense of
+fn check_date(date: &str) -> BackendResult<()> {
+    let since = format!("--since={}", date);
+    let output = git(&["rev-parse", &since, "--since=now"])?.wait()?;
+    let mut lines = output.lines();
+    if lines.next() == lines.next() && !matches!(date.trim(), "now" | "today") {
+        return Err(format!("invalid date '{}'", date));
+    }
+    Ok(())
+}
+
+// the "XY" code of a porcelain v2 entry, where '.' marks an unchanged side
+fn parse_status_code(xy: &str) -> FileStatus {
+    let code = xy.chars().find(|&c| c != '.').unwrap_or('M');
+    parse_file_status(code.encode_utf8(&mut [0; 4]))
+}
+
+// the "# branch.*" lines of porcelain v2 put back into the "## branch...upstream" header
+#[derive(Default)]
+struct BranchHeader {
+    oid: String,
+    head: String,
+    upstream: Option<String>,
+    ahead_behind: Option<(String, String)>,
+}
+impl BranchHeader {
+    fn parse_line(&mut self, line: &str) {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "branch.oid" => self.oid = value.into(),
+            "branch.head" => self.head = value.into(),
+            "branch.upstream" => self.upstream = Some(value.into()),
+            "branch.ab" => {
+                let (ahead, behind) = value.split_once(' ').unwrap_or((value, ""));
+                self.ahead_behind = Some((
+                    ahead.trim_start_matches('+').into(),
+                    behind.trim_start_matches('-').into(),
+                ));
+            }
+            _ => (),
+        }
+    }
+}
+impl fmt::Display for BranchHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.head == "(detached)" {
+            return f.write_str("## HEAD (no branch)");
+        }
+        if self.oid == "(initial)" {
+            write!(f, "## No commits yet on {}", self.head)?;
+        } else {
+            write!(f, "## {}", self.head)?;
+        }
+
+        if let Some(upstream) = &self.upstream {
+            write!(f, "...{}", upstream)?;
+            match &self.ahead_behind {
+                Some((ahead, behind)) => match (&ahead[..], &behind[..]) {
+                    ("0", "0") => (),
+                    (ahead, "0") => write!(f, " [ahead {}]", ahead)?,
+                    ("0", behind) => write!(f, " [behind {}]", behind)?,
+                    (ahead, behind) => write!(f, " [ahead {}, behind {}]", ahead, behind)?,
+                },
+                None => f.write_str(" [gone]")?,
+            }
+        }
         Ok(())
     }
 }
@@ -1,8 +1,10 @@
 use std::path::{Path, PathBuf};
 
 use crate::backend::{
-    Backend, BackendResult, BranchEntry, FileStatus, LogEntry, Process, RevisionEntry,
-    RevisionInfo, StatusInfo, TagEntry,
+    Backend, BackendResult, BranchDivergence, BranchEntry, ContainingRefs, FileLogEntry,
+    FileStatus, LogEntry, LogFilter, LogOrder, Operation, Phase, Process, PushOptions, ReflogEntry,
+    RemoteEntry, RerereStatus, ResetMode, RevisionEntry, RevisionInfo, StashEntry, StatusInfo,
+    TagEntry,
 };
 
 pub struct Hg;
@@ -19,6 +21,7 @@ impl Backend for Hg {
     fn status(&self) -> BackendResult<StatusInfo> {
         let header = Process::spawn("hg", &["summary"])?;
         let output = Process::spawn("hg", &["status"])?;
+        let diff = Process::spawn("hg", &["diff", "--git"])?;
 
         let header = header.wait()?.lines().next().unwrap_or("").into();
         let output = output.wait()?;
@@ -32,15 +35,38 @@ impl Backend for Hg {
             entries.push(RevisionEntry { name, status });
         }
 
-        Ok(StatusInfo { header, entries })
+        // hg status does not tell mode changes apart, only the git style diff does
+        let mode_changes = diff
+            .wait()
+            .map(|diff| parse_mode_only_changes(&diff))
+            .unwrap_or_default();
+        Ok(StatusInfo {
+            header,
+            entries,
+            mode_changes,
+        })
+    }
+
+    fn branch_header(&self) -> BackendResult<String> {
+        // the same line `hg summary` starts with
+        Process::spawn(
+            "hg",
+            &[
+                "log",
+                "--rev",
+                ".",
+                "--template",
+                "parent: {rev}:{node|short} {tags}",
+            ],
+        )?
+        .wait()
     }
 
     fn commit(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
         if entries.is_empty() {
             Process::spawn("hg", &["commit", "--addremove", "-m", message])?.wait()?;
         } else {
-            let mut args = Vec::new();
-            args.push("remove");
+            let mut args = vec!["remove"];
             for entry in entries {
                 if let FileStatus::Missing | FileStatus::Deleted = entry.status {
                     args.push(&entry.name);
@@ -66,13 +92,42 @@ impl Backend for Hg {
         Ok(())
     }
 
+    fn commit_hunks(&self, _: &str, _: &str, _: &[usize]) -> BackendResult<()> {
+        Err("committing hunks is not supported for hg".into())
+    }
+
+    fn commit_amend(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
+        let last_message;
+        let message = if message.is_empty() {
+            last_message = self.last_commit_message()?;
+            &last_message
+        } else {
+            message
+        };
+
+        if entries.is_empty() {
+            Process::spawn("hg", &["commit", "--amend", "-m", message])?.wait()?;
+        } else {
+            let mut args = vec!["commit", "--amend", "--addremove", "-m", message];
+            for entry in entries {
+                args.push(&entry.name);
+            }
+            Process::spawn("hg", &args)?.wait()?;
+        }
+
+        Ok(())
+    }
+
+    fn last_commit_message(&self) -> BackendResult<String> {
+        Process::spawn("hg", &["log", "--rev", ".", "--template", "{desc}"])?.wait()
+    }
+
     fn discard(&self, entries: &[RevisionEntry]) -> BackendResult<()> {
         if entries.is_empty() {
             Process::spawn("hg", &["revert", "-C", "--all"])?.wait()?;
             Process::spawn("hg", &["purge"])?.wait()?;
         } else {
-            let mut args = Vec::new();
-            args.push("purge");
+            let mut args = vec!["purge"];
             for entry in entries {
                 if let FileStatus::Untracked = entry.status {
                     args.push(&entry.name);
@@ -103,11 +158,7 @@ impl Backend for Hg {
                 if entries.is_empty() {
                     Process::spawn("hg", &["diff", "--change", revision])?.wait()
                 } else {
-                    let mut args = Vec::new();
-                    args.push("diff");
-                    args.push("--change");
-                    args.push(revision);
-                    args.push("--");
+                    let mut args = vec!["diff", "--change", revision, "--"];
                     for entry in entries {
                         args.push(&entry.name);
                     }
@@ -119,9 +170,7 @@ impl Backend for Hg {
                 if entries.is_empty() {
                     Process::spawn("hg", &["diff"])?.wait()
                 } else {
-                    let mut args = Vec::new();
-                    args.push("diff");
-                    args.push("--");
+                    let mut args = vec!["diff", "--"];
                     for entry in entries {
                         args.push(&entry.name);
                     }
@@ -142,11 +191,7 @@ impl Backend for Hg {
                 return Ok(());
             }
 
-            let mut args = Vec::new();
-            args.push("resolve");
-            args.push("--tool");
-            args.push("internal:local");
-            args.push("--");
+            let mut args = vec!["resolve", "--tool", "internal:local", "--"];
 
             for entry in entries {
                 if let FileStatus::Unmerged = entry.status {
@@ -171,11 +216,7 @@ impl Backend for Hg {
                 return Ok(());
             }
 
-            let mut args = Vec::new();
-            args.push("resolve");
-            args.push("--tool");
-            args.push("internal:other");
-            args.push("--");
+            let mut args = vec!["resolve", "--tool", "internal:other", "--"];
 
             for entry in entries {
                 if let FileStatus::Unmerged = entry.status {
@@ -189,40 +230,152 @@ impl Backend for Hg {
         Ok(())
     }
 
-    fn log(&self, skip: usize, len: usize) -> BackendResult<Vec<LogEntry>> {
+    fn in_progress_operation(&self) -> BackendResult<Option<Operation>> {
+        let operation = if Path::new(".hg/rebasestate").exists() {
+            Some(Operation::Rebase)
+        } else if Path::new(".hg/graftstate").exists() {
+            Some(Operation::CherryPick)
+        } else if Path::new(".hg/bisect.state").exists() {
+            Some(Operation::Bisect)
+        } else {
+            let second_parent =
+                Process::spawn("hg", &["log", "--rev", "p2()", "--template", "{node}"])?.wait()?;
+            if second_parent.trim().is_empty() {
+                None
+            } else {
+                Some(Operation::Merge)
+            }
+        };
+        Ok(operation)
+    }
+
+    fn continue_operation(&self, operation: Operation) -> BackendResult<()> {
+        let args: &[&str] = match operation {
+            Operation::Merge => &["commit", "--message", "merge"],
+            Operation::Rebase => return self.rebase_continue(),
+            Operation::CherryPick => return self.cherry_pick_continue(),
+            Operation::Revert | Operation::Bisect => {
+                return Err(format!("{} can not be continued", operation.as_str()))
+            }
+        };
+        Process::spawn("hg", args)?.wait()?;
+        Ok(())
+    }
+
+    fn rebase(&self, onto: &str) -> BackendResult<()> {
+        Process::spawn("hg", &["rebase", "--dest", onto])?.wait()?;
+        Ok(())
+    }
+
+    fn rebase_continue(&self) -> BackendResult<()> {
+        Process::spawn("hg", &["rebase", "--continue"])?.wait()?;
+        Ok(())
+    }
+
+    fn rebase_abort(&self) -> BackendResult<()> {
+        Process::spawn("hg", &["rebase", "--abort"])?.wait()?;
+        Ok(())
+    }
+
+    fn rebase_skip(&self) -> BackendResult<()> {
+        Err("rebase skip is not supported for hg".into())
+    }
+
+    fn cherry_pick_continue(&self) -> BackendResult<()> {
+        Process::spawn("hg", &["graft", "--continue"])?.wait()?;
+        Ok(())
+    }
+
+    fn cherry_pick_abort(&self) -> BackendResult<()> {
+        Process::spawn("hg", &["graft", "--abort"])?.wait()?;
+        Ok(())
+    }
+
+    fn rerere_status(&self) -> BackendResult<RerereStatus> {
+        Ok(RerereStatus::Unavailable)
+    }
+
+    fn config_set(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err("config set is not implemented for hg".into())
+    }
+
+    fn log(
+        &self,
+        skip: usize,
+        len: usize,
+        order: LogOrder,
+        graph: bool,
+        filter: &LogFilter,
+    ) -> BackendResult<Vec<LogEntry>> {
         let limit = (skip + len).to_string();
-        let template = "\x1f{node|short}\x1f{date|shortdate}\x1f{author|person}\x1f{ifeq(phase,'secret','(secret) ','')}{ifeq(phase,'draft','(draft) ','')}{if(topics,'[{topics}] ')}{tags % '{tag} '}{branch}\x1f{desc}";
-        let output = Process::spawn(
-            "hg",
-            &[
-                "log",
-                "--config",
-                "experimental.graphshorten=True",
-                "--graph",
-                "--template",
-                template,
-                "--limit",
-                &limit,
-            ],
-        )?
-        .wait()?;
+        // only --graph ends each changeset with a newline on its own
+        let template = format!(
+            "\x1f{{node|short}}\x1f{{date|hgdate}}\x1f{{author|person}}\x1f{{phase}}\x1f{{if(topics,'[{{topics}}] ')}}{{tags % '{{tag}} '}}{{branch}}\x1f{{desc|firstline}}{}",
+            if graph { "" } else { "\\n" }
+        );
+        let mut args = vec![
+            "log",
+            "--config",
+            "experimental.graphshorten=True",
+            "--template",
+            &template,
+            "--limit",
+            &limit,
+        ];
+        if graph {
+            args.push("--graph");
+        }
+        // hg keyword matching is already case insensitive
+        if !filter.message.is_empty() {
+            args.extend(&["--keyword", &filter.message]);
+        }
+        let date = match (filter.since.is_empty(), filter.until.is_empty()) {
+            (true, true) => String::new(),
+            (false, true) => format!(">{}", filter.since),
+            (true, false) => format!("<{}", filter.until),
+            (false, false) => format!("{} to {}", filter.since, filter.until),
+        };
+        if !date.is_empty() {
+            args.extend(&["--date", &date]);
+        }
+        match order {
+            LogOrder::Default => (),
+            LogOrder::Date => args.extend(&["--rev", "sort(all(), -date)"]),
+            LogOrder::Topological => args.extend(&["--rev", "sort(all(), -topo)"]),
+        }
+        let output = Process::spawn("hg", &args)?.wait()?;
 
         let mut entries = Vec::new();
         for line in output.lines().skip(skip) {
-            let mut splits = line.splitn(6, '\x1f');
+            let mut splits = line.splitn(7, '\x1f');
 
             let graph = splits.next().unwrap_or("").into();
             let hash = splits.next().unwrap_or("").into();
-            let date = splits.next().unwrap_or("").into();
+            let timestamp = splits
+                .next()
+                .unwrap_or("")
+                .split(' ')
+                .next()
+                .unwrap_or("")
+                .parse()
+                .unwrap_or(0);
             let author = splits.next().unwrap_or("").into();
+            let phase = match splits.next().unwrap_or("") {
+                "public" => Some(Phase::Public),
+                "draft" => Some(Phase::Draft),
+                "secret" => Some(Phase::Secret),
+                _ => None,
+            };
             let refs = splits.next().unwrap_or("").into();
             let message = splits.next().unwrap_or("").into();
 
             entries.push(LogEntry {
                 graph,
                 hash,
-                date,
+                timestamp,
+                date: String::new(),
                 author,
+                phase,
                 refs,
                 message,
             });
@@ -231,6 +384,63 @@ impl Backend for Hg {
         Ok(entries)
     }
 
+    fn file_log(&self, path: &str, len: usize, follow: bool) -> BackendResult<Vec<FileLogEntry>> {
+        let len = len.to_string();
+        let template = "{node|short}\x1f{date|hgdate}\x1f{author|person}\x1f{phase}\x1f{if(topics,'[{topics}] ')}{tags % '{tag} '}{branch}\x1f{desc|firstline}\n";
+        let mut args = vec!["log", "--template", template, "--limit", &len];
+        if follow {
+            args.push("--follow");
+        }
+        args.push("--");
+        args.push(path);
+        let output = Process::spawn("hg", &args)?.wait()?;
+
+        let mut entries = Vec::new();
+        for line in output.lines() {
+            let mut splits = line.splitn(6, '\x1f');
+
+            let hash = splits.next().unwrap_or("").into();
+            let timestamp = splits
+                .next()
+                .unwrap_or("")
+                .split(' ')
+                .next()
+                .unwrap_or("")
+                .parse()
+                .unwrap_or(0);
+            let author = splits.next().unwrap_or("").into();
+            let phase = match splits.next().unwrap_or("") {
+                "public" => Some(Phase::Public),
+                "draft" => Some(Phase::Draft),
+                "secret" => Some(Phase::Secret),
+                _ => None,
+            };
+            let refs = splits.next().unwrap_or("").into();
+            let message = splits.next().unwrap_or("").into();
+
+            // hg doesn't report the name a followed file had, so older diffs use the current one
+            entries.push(FileLogEntry {
+                entry: LogEntry {
+                    graph: String::new(),
+                    hash,
+                    timestamp,
+                    date: String::new(),
+                    author,
+                    phase,
+                    refs,
+                    message,
+                },
+                path: path.into(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn reflog(&self, _: usize) -> BackendResult<Vec<ReflogEntry>> {
+        Err("reflog is not implemented for hg".into())
+    }
+
     fn checkout(&self, revision: &str) -> BackendResult<()> {
         Process::spawn("hg", &["update", revision])?.wait()?;
         Ok(())
@@ -241,20 +451,90 @@ impl Backend for Hg {
         Ok(())
     }
 
-    fn fetch(&self) -> BackendResult<()> {
-        self.pull()
+    fn revert_commit(&self, revision: &str, no_commit: bool) -> BackendResult<()> {
+        if no_commit {
+            Process::spawn("hg", &["backout", "--no-commit", "--rev", revision])?.wait()?;
+        } else {
+            let message = format!("backed out changeset {}", revision);
+            Process::spawn("hg", &["backout", "--rev", revision, "--message", &message])?.wait()?;
+        }
+        Ok(())
     }
 
-    fn pull(&self) -> BackendResult<()> {
-        Process::spawn("hg", &["pull"])?.wait()?;
+    fn reset(&self, revision: &str, mode: ResetMode) -> BackendResult<()> {
+        match mode {
+            ResetMode::Soft => return Err("soft reset is not supported by hg".into()),
+            ResetMode::Mixed => {
+                Process::spawn("hg", &["revert", "--all", "--rev", revision])?.wait()?;
+            }
+            ResetMode::Hard => {
+                Process::spawn("hg", &["update", "--clean", "--rev", revision])?.wait()?;
+            }
+        }
         Ok(())
     }
 
-    fn push(&self) -> BackendResult<()> {
-        Process::spawn("hg", &["push", "--new-branch"])?.wait()?;
+    fn cherry_pick(&self, revisions: &[String]) -> BackendResult<()> {
+        let mut args = vec!["graft"];
+        for revision in revisions {
+            args.push("--rev");
+            args.push(&revision[..]);
+        }
+        Process::spawn("hg", &args)?.wait()?;
         Ok(())
     }
 
+    fn fetch(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        self.pull(progress)
+    }
+
+    fn pull(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let output = Process::spawn("hg", &["pull"])?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn push(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let output =
+            Process::spawn("hg", &["push", "--new-branch"])?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn push_set_upstream(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        self.push(progress)
+    }
+
+    fn push_options(&self) -> BackendResult<PushOptions> {
+        let branch = Process::spawn("hg", &["branch"])?.wait()?;
+        Ok(PushOptions {
+            remote: "default".into(),
+            branch: branch.trim().into(),
+            force_with_lease: false,
+            set_upstream: false,
+        })
+    }
+
+    fn push_with_options(
+        &self,
+        options: &PushOptions,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<String> {
+        if options.force_with_lease {
+            return Err("force with lease is not supported by hg".into());
+        }
+        let output = Process::spawn(
+            "hg",
+            &[
+                "push",
+                "--new-branch",
+                "--branch",
+                &options.branch,
+                &options.remote,
+            ],
+        )?
+        .wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
     fn revision_details(&self, revision: &str) -> BackendResult<RevisionInfo> {
         let message = Process::spawn("hg", &["log", "--rev", revision, "--template", "{desc}"])?;
         let output = Process::spawn("hg", &["status", "--change", revision])?;
@@ -271,25 +551,102 @@ impl Backend for Hg {
             entries.push(RevisionEntry { name, status });
         }
 
-        Ok(RevisionInfo { message, entries })
+        Ok(RevisionInfo {
+            message,
+            signature: None,
+            entries,
+        })
+    }
+
+    fn show(&self, revision: &str) -> BackendResult<String> {
+        Process::spawn("hg", &["export", "--rev", revision])?.wait()
+    }
+
+    fn containing_refs(&self, revision: &str) -> BackendResult<ContainingRefs> {
+        let branches_revset = format!("descendants({}) and head()", revision);
+        let tags_revset = format!("descendants({}) and tag()", revision);
+        let branches = Process::spawn(
+            "hg",
+            &[
+                "log",
+                "--rev",
+                &branches_revset,
+                "--template",
+                "{branch}\\n",
+            ],
+        )?;
+        let tags = Process::spawn(
+            "hg",
+            &[
+                "log",
+                "--rev",
+                &tags_revset,
+                "--template",
+                "{join(tags, '\\n')}\\n",
+            ],
+        )?;
+
+        let mut branches: Vec<_> = branches.wait()?.lines().map(String::from).collect();
+        branches.sort_unstable();
+        branches.dedup();
+        let tags = tags
+            .wait()?
+            .lines()
+            .filter(|t| !t.is_empty() && *t != "tip")
+            .map(String::from)
+            .collect();
+        Ok(ContainingRefs { branches, tags })
+    }
+
+    fn ref_candidates(&self) -> BackendResult<Vec<String>> {
+        let branches = Process::spawn("hg", &["branches", "--template", "{branch}\\n"])?;
+        let bookmarks = Process::spawn("hg", &["bookmarks", "--template", "{bookmark}\\n"])?;
+        let tags = Process::spawn("hg", &["tags", "--template", "{tag}\\n"])?;
+        let hashes = Process::spawn(
+            "hg",
+            &["log", "--template", "{node|short}\\n", "--limit", "50"],
+        )?;
+
+        let mut candidates = Vec::new();
+        for process in [branches, bookmarks, tags, hashes] {
+            candidates.extend(process.wait()?.lines().map(String::from));
+        }
+        Ok(candidates)
     }
 
     fn branches(&self) -> BackendResult<Vec<BranchEntry>> {
-        let entries = Process::spawn("hg", &["branches", "--template", "{branch}\x1f#\\n"])?
+        let current_branch = Process::spawn("hg", &["branch"])?;
+        let entries = Process::spawn("hg", &["branches", "--template", "{branch}\\n"])?;
+
+        let current_branch = current_branch.wait()?;
+        let current_branch = current_branch.trim();
+
+        let entries = entries
             .wait()?
             .lines()
-            .map(|l| {
-                let mut splits = l.splitn(2, '\x1f');
-                let name = splits.next().unwrap_or("").into();
-                let checked_out = splits.next().unwrap_or("") == "*";
+            .map(|name| {
+                let name = name.into();
+                let checked_out = name == current_branch;
                 BranchEntry { name, checked_out }
             })
             .collect();
         Ok(entries)
     }
 
-    fn new_branch(&self, name: &str) -> BackendResult<()> {
-        Process::spawn("hg", &["branch", name])?.wait()?;
+    fn branch_exists(&self, name: &str) -> BackendResult<bool> {
+        Ok(self.branches()?.iter().any(|b| b.name == name))
+    }
+
+    fn new_branch(&self, name: &str, start_point: Option<&str>, force: bool) -> BackendResult<()> {
+        // hg branches start with the next commit, so update to where it should begin first
+        if let Some(start_point) = start_point {
+            self.checkout(start_point)?;
+        }
+        if force {
+            Process::spawn("hg", &["branch", "--force", name])?.wait()?;
+        } else {
+            Process::spawn("hg", &["branch", name])?.wait()?;
+        }
         Ok(())
     }
 
@@ -301,6 +658,37 @@ impl Backend for Hg {
         Ok(())
     }
 
+    fn rename_branch(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err(
+            "renaming branches is not supported by hg, the branch name is recorded in every commit"
+                .into(),
+        )
+    }
+
+    fn branch_divergence(&self, base: &str) -> BackendResult<BranchDivergence> {
+        self.divergence(".", base)
+    }
+
+    fn divergence(&self, head: &str, base: &str) -> BackendResult<BranchDivergence> {
+        let merge_base_revset = format!("ancestor({}, {})", head, base);
+        let ahead_revset = format!("only({}, {})", head, base);
+        let behind_revset = format!("only({}, {})", base, head);
+        let merge_base = Process::spawn(
+            "hg",
+            &["log", "--rev", &merge_base_revset, "--template", "{node}"],
+        )?;
+        let ahead = Process::spawn("hg", &["log", "--rev", &ahead_revset, "--template", "."])?;
+        let behind = Process::spawn("hg", &["log", "--rev", &behind_revset, "--template", "."])?;
+
+        Ok(BranchDivergence {
+            head: head.into(),
+            base: base.into(),
+            merge_base: merge_base.wait()?.trim().into(),
+            ahead: ahead.wait()?.len(),
+            behind: behind.wait()?.len(),
+        })
+    }
+
     fn tags(&self) -> BackendResult<Vec<TagEntry>> {
         let entries = Process::spawn("hg", &["tags", "--template", "{tag}\\n"])?
             .wait()?
@@ -310,8 +698,20 @@ impl Backend for Hg {
         Ok(entries)
     }
 
-    fn new_tag(&self, name: &str) -> BackendResult<()> {
-        Process::spawn("hg", &["tag", "--force", name])?.wait()?;
+    fn tag_exists(&self, name: &str) -> BackendResult<bool> {
+        Ok(self.tags()?.iter().any(|t| t.name == name))
+    }
+
+    fn new_tag(&self, name: &str, message: Option<&str>, force: bool) -> BackendResult<()> {
+        let mut args = vec!["tag"];
+        if let Some(message) = message {
+            args.extend(["--message", message]);
+        }
+        if force {
+            args.push("--force");
+        }
+        args.push(name);
+        Process::spawn("hg", &args)?.wait()?;
         Ok(())
     }
 
@@ -319,6 +719,147 @@ impl Backend for Hg {
         Process::spawn("hg", &["tag", "--remove", name])?.wait()?;
         Ok(())
     }
+
+    fn delete_remote_tag(&self, _: &str) -> BackendResult<()> {
+        Err("hg removes tags with a commit, push it to remove the tag remotely".into())
+    }
+
+    fn stash_list(&self) -> BackendResult<Vec<StashEntry>> {
+        let entries = Process::spawn("hg", &["shelve", "--list"])?
+            .wait()?
+            .lines()
+            .map(|l| {
+                let mut splits = l.splitn(2, ' ');
+                let name = splits.next().unwrap_or("").into();
+                let message = splits.next().unwrap_or("").trim_start();
+                let message = match message.strip_prefix('(') {
+                    Some(message) => match message.find(')') {
+                        Some(i) => message[i + 1..].trim_start(),
+                        None => message,
+                    },
+                    None => message,
+                };
+                let message = message.into();
+                StashEntry { name, message }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn stash_push(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
+        let mut args = vec!["shelve", "--addremove"];
+        if !message.is_empty() {
+            args.extend(&["--message", message]);
+        }
+        for entry in entries {
+            args.push(&entry.name);
+        }
+        Process::spawn("hg", &args)?.wait()?;
+        Ok(())
+    }
+
+    fn stash_apply(&self, name: &str) -> BackendResult<()> {
+        Process::spawn("hg", &["unshelve", "--keep", name])?.wait()?;
+        Ok(())
+    }
+
+    fn stash_pop(&self, name: &str) -> BackendResult<()> {
+        Process::spawn("hg", &["unshelve", name])?.wait()?;
+        Ok(())
+    }
+
+    fn stash_drop(&self, name: &str) -> BackendResult<()> {
+        Process::spawn("hg", &["shelve", "--delete", name])?.wait()?;
+        Ok(())
+    }
+
+    fn remote_list(&self) -> BackendResult<Vec<RemoteEntry>> {
+        let entries = Process::spawn("hg", &["paths"])?
+            .wait()?
+            .lines()
+            .map(|l| {
+                let mut splits = l.splitn(2, " = ");
+                let name = splits.next().unwrap_or("").into();
+                let url: String = splits.next().unwrap_or("").into();
+                RemoteEntry {
+                    name,
+                    fetch_url: url.clone(),
+                    push_url: url,
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn remote_add(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err("adding remotes is not supported by hg, edit [paths] in .hg/hgrc instead".into())
+    }
+
+    fn remote_remove(&self, _: &str) -> BackendResult<()> {
+        Err("removing remotes is not supported by hg, edit [paths] in .hg/hgrc instead".into())
+    }
+
+    fn remote_rename(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err("renaming remotes is not supported by hg, edit [paths] in .hg/hgrc instead".into())
+    }
+
+    fn sparse_checkout_list(&self) -> BackendResult<Option<Vec<String>>> {
+        Err("sparse checkout is not implemented for hg".into())
+    }
+
+    fn sparse_checkout_set(&self, _: &[String]) -> BackendResult<()> {
+        Err("sparse checkout is not implemented for hg".into())
+    }
+
+    fn sparse_checkout_disable(&self) -> BackendResult<()> {
+        Err("sparse checkout is not implemented for hg".into())
+    }
+
+    fn clone_repository(
+        &self,
+        url: &str,
+        directory: &str,
+        progress: &mut dyn FnMut(&str),
+    ) -> BackendResult<()> {
+        Process::spawn("hg", &["clone", url, directory])?.wait_with_progress(progress)?;
+        Ok(())
+    }
+
+    fn is_bare(&self) -> bool {
+        false
+    }
+
+    fn init(&self, path: &str) -> BackendResult<()> {
+        Process::spawn("hg", &["init", path])?.wait()?;
+        Ok(())
+    }
+}
+
+fn parse_mode_only_changes(diff: &str) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut current = None;
+    let mut mode_changed = false;
+    let mut content_changed = false;
+
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            if let (Some(name), true, false) = (current, mode_changed, content_changed) {
+                changes.push(String::from(name));
+            }
+            current = header.rsplit(" b/").next();
+            mode_changed = false;
+            content_changed = false;
+        } else if line.starts_with("new mode ") {
+            mode_changed = true;
+        } else if line.starts_with("@@") || line.starts_with("GIT binary patch") {
+            content_changed = true;
+        }
+    }
+    if let (Some(name), true, false) = (current, mode_changed, content_changed) {
+        changes.push(String::from(name));
+    }
+
+    changes
 }
 
 fn parse_file_status(s: &str) -> FileStatus {
@@ -3,9 +3,14 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::backend::{
-    Backend, BackendResult, BranchEntry, FileStatus, LogEntry, Process, RevisionEntry,
-    RevisionInfo, StatusInfo, TagEntry,
+use crate::{
+    backend::{
+        Backend, BackendResult, BranchDivergence, BranchEntry, ContainingRefs, FileLogEntry,
+        FileStatus, LogEntry, LogFilter, LogOrder, Operation, Process, PushOptions, ReflogEntry,
+        RemoteEntry, RerereStatus, ResetMode, RevisionEntry, RevisionInfo, StashEntry, StatusInfo,
+        TagEntry,
+    },
+    date,
 };
 
 pub struct Plastic;
@@ -52,7 +57,16 @@ impl Backend for Plastic {
             entries.push(RevisionEntry { name, status });
         }
 
-        Ok(StatusInfo { header, entries })
+        Ok(StatusInfo {
+            header,
+            entries,
+            mode_changes: Vec::new(),
+        })
+    }
+
+    fn branch_header(&self) -> BackendResult<String> {
+        let header = Process::spawn("cm", &["status", "--header"])?.wait()?;
+        Ok(header.trim().into())
     }
 
     fn commit(&self, message: &str, entries: &[RevisionEntry]) -> BackendResult<()> {
@@ -70,8 +84,7 @@ impl Backend for Plastic {
             )?
             .wait()?;
 
-            let mut args = Vec::new();
-            args.push("add");
+            let mut args = vec!["add"];
             for line in untracked.lines() {
                 if let Some(name) = line.split('\x1f').nth(1) {
                     args.push(name);
@@ -83,8 +96,7 @@ impl Backend for Plastic {
 
             Process::spawn("cm", &["checkin", "--all"])?.wait()?;
         } else {
-            let mut args = Vec::new();
-            args.push("add");
+            let mut args = vec!["add"];
             for entry in entries {
                 if let FileStatus::Untracked = entry.status {
                     args.push(&entry.name);
@@ -106,6 +118,18 @@ impl Backend for Plastic {
         Ok(())
     }
 
+    fn commit_hunks(&self, _: &str, _: &str, _: &[usize]) -> BackendResult<()> {
+        Err("committing hunks is not supported for plastic".into())
+    }
+
+    fn commit_amend(&self, _: &str, _: &[RevisionEntry]) -> BackendResult<()> {
+        Err("amending commits is not implemented for plastic".into())
+    }
+
+    fn last_commit_message(&self) -> BackendResult<String> {
+        Err("amending commits is not implemented for plastic".into())
+    }
+
     fn discard(&self, entries: &[RevisionEntry]) -> BackendResult<()> {
         fn delete_file(name: &str) -> BackendResult<()> {
             fs::remove_file(name).map_err(|e| e.to_string())
@@ -184,7 +208,6 @@ impl Backend for Plastic {
         Ok("".into())
     }
 
-    // TODO
     fn resolve_taking_ours(&self, entries: &[RevisionEntry]) -> BackendResult<()> {
         if entries.is_empty() {
             Process::spawn(
@@ -200,11 +223,7 @@ impl Backend for Plastic {
                 return Ok(());
             }
 
-            let mut args = Vec::new();
-            args.push("checkout");
-            args.push(".");
-            args.push("--ours");
-            args.push("--");
+            let mut args = vec!["checkout", ".", "--ours", "--"];
 
             for entry in entries {
                 if let FileStatus::Unmerged = entry.status {
@@ -230,11 +249,7 @@ impl Backend for Plastic {
                 return Ok(());
             }
 
-            let mut args = Vec::new();
-            args.push("checkout");
-            args.push(".");
-            args.push("--theirs");
-            args.push("--");
+            let mut args = vec!["checkout", ".", "--theirs", "--"];
 
             for entry in entries {
                 if let FileStatus::Unmerged = entry.status {
@@ -248,7 +263,57 @@ impl Backend for Plastic {
         Ok(())
     }
 
-    fn log(&self, skip: usize, len: usize) -> BackendResult<Vec<LogEntry>> {
+    fn in_progress_operation(&self) -> BackendResult<Option<Operation>> {
+        Ok(None)
+    }
+
+    fn continue_operation(&self, _: Operation) -> BackendResult<()> {
+        Err("continue is not implemented for plastic".into())
+    }
+
+    fn rebase(&self, _: &str) -> BackendResult<()> {
+        Err("rebase is not implemented for plastic".into())
+    }
+
+    fn rebase_continue(&self) -> BackendResult<()> {
+        Err("rebase is not implemented for plastic".into())
+    }
+
+    fn rebase_abort(&self) -> BackendResult<()> {
+        Err("rebase is not implemented for plastic".into())
+    }
+
+    fn rebase_skip(&self) -> BackendResult<()> {
+        Err("rebase is not implemented for plastic".into())
+    }
+
+    fn cherry_pick_continue(&self) -> BackendResult<()> {
+        Err("cherry-pick is not implemented for plastic".into())
+    }
+
+    fn cherry_pick_abort(&self) -> BackendResult<()> {
+        Err("cherry-pick is not implemented for plastic".into())
+    }
+
+    fn rerere_status(&self) -> BackendResult<RerereStatus> {
+        Ok(RerereStatus::Unavailable)
+    }
+
+    fn config_set(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err("config set is not implemented for plastic".into())
+    }
+
+    fn log(
+        &self,
+        skip: usize,
+        len: usize,
+        _: LogOrder,
+        _: bool,
+        filter: &LogFilter,
+    ) -> BackendResult<Vec<LogEntry>> {
+        if !filter.is_empty() {
+            return Err("filtering the log is not implemented for plastic".into());
+        }
         let current_changeset = Process::spawn(
             "cm",
             &[
@@ -264,6 +329,7 @@ impl Backend for Plastic {
                 "find",
                 "changeset",
                 "--nototal",
+                "--dateformat=yyyy-MM-dd HH:mm:ss",
                 "--format={changesetid}\x1f{date}\x1f{owner}\x1f{branch}\x1f{comment}\x1e",
             ],
         )?;
@@ -280,7 +346,7 @@ impl Backend for Plastic {
             let graph = if hash == current_changeset { "*" } else { " " };
             let graph = graph.into();
 
-            let date = splits.next().unwrap_or("").into();
+            let timestamp = parse_date(splits.next().unwrap_or(""));
             let author = splits.next().unwrap_or("").into();
             let refs = splits.next().unwrap_or("").into();
             let message = splits.next().unwrap_or("").into();
@@ -288,8 +354,10 @@ impl Backend for Plastic {
             entries.push(LogEntry {
                 graph,
                 hash,
-                date,
+                timestamp,
+                date: String::new(),
                 author,
+                phase: None,
                 refs,
                 message,
             });
@@ -298,6 +366,14 @@ impl Backend for Plastic {
         Ok(entries)
     }
 
+    fn file_log(&self, _: &str, _: usize, _: bool) -> BackendResult<Vec<FileLogEntry>> {
+        Err("file log is not implemented for plastic".into())
+    }
+
+    fn reflog(&self, _: usize) -> BackendResult<Vec<ReflogEntry>> {
+        Err("reflog is not implemented for plastic".into())
+    }
+
     fn checkout(&self, revision: &str) -> BackendResult<()> {
         Process::spawn("cm", &["switch", revision])?.wait()?;
         Ok(())
@@ -316,17 +392,40 @@ impl Backend for Plastic {
         result
     }
 
-    fn fetch(&self) -> BackendResult<()> {
-        self.pull()
+    fn revert_commit(&self, _: &str, _: bool) -> BackendResult<()> {
+        Err("revert commit is not implemented for plastic".into())
+    }
+    fn reset(&self, _: &str, _: ResetMode) -> BackendResult<()> {
+        Err("reset is not implemented for plastic".into())
     }
 
-    fn pull(&self) -> BackendResult<()> {
-        Process::spawn("cm", &["update"])?.wait()?;
-        Ok(())
+    fn cherry_pick(&self, _: &[String]) -> BackendResult<()> {
+        Err("cherry-pick is not implemented for plastic".into())
     }
 
-    fn push(&self) -> BackendResult<()> {
-        Ok(())
+    fn fetch(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        self.pull(progress)
+    }
+
+    fn pull(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        let output = Process::spawn("cm", &["update"])?.wait_with_progress(progress)?;
+        Ok(output.diagnostics())
+    }
+
+    fn push(&self, _: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        Ok(String::new())
+    }
+
+    fn push_set_upstream(&self, progress: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        self.push(progress)
+    }
+
+    fn push_options(&self) -> BackendResult<PushOptions> {
+        Err("pushing to a remote is not implemented for plastic".into())
+    }
+
+    fn push_with_options(&self, _: &PushOptions, _: &mut dyn FnMut(&str)) -> BackendResult<String> {
+        Err("pushing to a remote is not implemented for plastic".into())
     }
 
     fn revision_details(&self, revision: &str) -> BackendResult<RevisionInfo> {
@@ -361,7 +460,25 @@ impl Backend for Plastic {
             entries.push(RevisionEntry { name, status });
         }
 
-        Ok(RevisionInfo { message, entries })
+        Ok(RevisionInfo {
+            message,
+            signature: None,
+            entries,
+        })
+    }
+
+    fn show(&self, _: &str) -> BackendResult<String> {
+        Err("show is not implemented for plastic".into())
+    }
+
+    fn containing_refs(&self, _: &str) -> BackendResult<ContainingRefs> {
+        Err("containing refs is not implemented for plastic".into())
+    }
+
+    fn ref_candidates(&self) -> BackendResult<Vec<String>> {
+        let branches = self.branches()?.into_iter().map(|b| b.name);
+        let tags = self.tags()?.into_iter().map(|t| t.name);
+        Ok(branches.chain(tags).collect())
     }
 
     fn branches(&self) -> BackendResult<Vec<BranchEntry>> {
@@ -384,7 +501,17 @@ impl Backend for Plastic {
         Ok(entries)
     }
 
-    fn new_branch(&self, name: &str) -> BackendResult<()> {
+    fn branch_exists(&self, name: &str) -> BackendResult<bool> {
+        Ok(self.branches()?.iter().any(|b| b.name == name))
+    }
+
+    fn new_branch(&self, name: &str, start_point: Option<&str>, force: bool) -> BackendResult<()> {
+        if start_point.is_some() {
+            return Err("creating a branch at a revision is not implemented for plastic".into());
+        }
+        if force {
+            self.delete_branch(name)?;
+        }
         Process::spawn("cm", &["branch", "create", name])?.wait()?;
         Ok(())
     }
@@ -394,6 +521,19 @@ impl Backend for Plastic {
         Ok(())
     }
 
+    fn rename_branch(&self, old_name: &str, new_name: &str) -> BackendResult<()> {
+        Process::spawn("cm", &["branch", "rename", old_name, new_name])?.wait()?;
+        Ok(())
+    }
+
+    fn branch_divergence(&self, _: &str) -> BackendResult<BranchDivergence> {
+        Err("branch divergence is not implemented for plastic".into())
+    }
+
+    fn divergence(&self, _: &str, _: &str) -> BackendResult<BranchDivergence> {
+        Err("divergence is not implemented for plastic".into())
+    }
+
     fn tags(&self) -> BackendResult<Vec<TagEntry>> {
         let entries = Process::spawn("cm", &["find", "label", "--nototal", "--format={name}"])?
             .wait()?
@@ -403,8 +543,23 @@ impl Backend for Plastic {
         Ok(entries)
     }
 
-    fn new_tag(&self, name: &str) -> BackendResult<()> {
-        Process::spawn("cm", &["label", "create", name])?.wait()?;
+    fn tag_exists(&self, name: &str) -> BackendResult<bool> {
+        Ok(self.tags()?.iter().any(|t| t.name == name))
+    }
+
+    fn new_tag(&self, name: &str, message: Option<&str>, force: bool) -> BackendResult<()> {
+        if force {
+            self.delete_tag(name)?;
+        }
+        match message {
+            Some(message) => {
+                let comment = format!("-c={}", message);
+                Process::spawn("cm", &["label", "create", name, &comment])?.wait()?;
+            }
+            None => {
+                Process::spawn("cm", &["label", "create", name])?.wait()?;
+            }
+        }
         Ok(())
     }
 
@@ -412,6 +567,78 @@ impl Backend for Plastic {
         Process::spawn("cm", &["label", "delete", name])?.wait()?;
         Ok(())
     }
+
+    fn delete_remote_tag(&self, _: &str) -> BackendResult<()> {
+        Err("plastic labels live on the server, delete them with [D] instead".into())
+    }
+
+    fn stash_list(&self) -> BackendResult<Vec<StashEntry>> {
+        Err("stash is not implemented for plastic".into())
+    }
+
+    fn stash_push(&self, _message: &str, _entries: &[RevisionEntry]) -> BackendResult<()> {
+        Err("stash is not implemented for plastic".into())
+    }
+
+    fn stash_apply(&self, _name: &str) -> BackendResult<()> {
+        Err("stash is not implemented for plastic".into())
+    }
+
+    fn stash_pop(&self, _name: &str) -> BackendResult<()> {
+        Err("stash is not implemented for plastic".into())
+    }
+
+    fn stash_drop(&self, _name: &str) -> BackendResult<()> {
+        Err("stash is not implemented for plastic".into())
+    }
+
+    fn remote_list(&self) -> BackendResult<Vec<RemoteEntry>> {
+        Err("remotes are not implemented for plastic".into())
+    }
+
+    fn remote_add(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err("remotes are not implemented for plastic".into())
+    }
+
+    fn remote_remove(&self, _: &str) -> BackendResult<()> {
+        Err("remotes are not implemented for plastic".into())
+    }
+
+    fn remote_rename(&self, _: &str, _: &str) -> BackendResult<()> {
+        Err("remotes are not implemented for plastic".into())
+    }
+
+    fn sparse_checkout_list(&self) -> BackendResult<Option<Vec<String>>> {
+        Err("sparse checkout is not implemented for plastic".into())
+    }
+
+    fn sparse_checkout_set(&self, _: &[String]) -> BackendResult<()> {
+        Err("sparse checkout is not implemented for plastic".into())
+    }
+
+    fn sparse_checkout_disable(&self) -> BackendResult<()> {
+        Err("sparse checkout is not implemented for plastic".into())
+    }
+
+    fn clone_repository(&self, _: &str, _: &str, _: &mut dyn FnMut(&str)) -> BackendResult<()> {
+        Err("clone is not implemented for plastic".into())
+    }
+
+    fn is_bare(&self) -> bool {
+        false
+    }
+
+    fn init(&self, _: &str) -> BackendResult<()> {
+        Err("init is not implemented for plastic".into())
+    }
+}
+
+fn parse_date(s: &str) -> i64 {
+    let mut fields = s
+        .split(&['-', ' ', ':'][..])
+        .map(|f| f.trim().parse().unwrap_or(0));
+    let mut next = || fields.next().unwrap_or(0);
+    date::timestamp_from_civil(next(), next(), next(), next(), next(), next())
 }
 
 fn parse_file_status(s: &str) -> FileStatus {
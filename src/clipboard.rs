@@ -0,0 +1,60 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+#[cfg(target_os = "windows")]
+const PROGRAMS: &[(&str, &[&str])] = &[("clip", &[])];
+
+#[cfg(target_os = "macos")]
+const PROGRAMS: &[(&str, &[&str])] = &[("pbcopy", &[])];
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const PROGRAMS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+pub fn copy(text: &str) -> Result<(), String> {
+    let mut error = None;
+    for &(program, args) in PROGRAMS {
+        match copy_with(program, args, text) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => {
+                error = Some(format!(
+                    "could not copy to clipboard with {}: {}",
+                    program, e
+                ))
+            }
+        }
+    }
+
+    Err(error.unwrap_or_else(|| {
+        let programs: Vec<_> = PROGRAMS.iter().map(|(program, _)| *program).collect();
+        format!(
+            "could not copy to clipboard: none of {} found in PATH",
+            programs.join(", ")
+        )
+    }))
+}
+
+fn copy_with(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(status.to_string()))
+    }
+}
@@ -0,0 +1,459 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    mode::{log::RetryPolicy, EntrySort, ModeKind},
+    platform::Key,
+    theme::Theme,
+    ui::SpinnerStyle,
+};
+
+const CONFIG_FILE_NAME: &str = "config";
+const KEY_BINDINGS_FILE_NAME: &str = "keybindings.toml";
+const THEME_FILE_NAME: &str = "theme.toml";
+const REPOSITORY_CONFIG_DIR: &str = ".verco";
+
+pub struct Config {
+    pub initial_mode: ModeKind,
+    pub spinner_style: SpinnerStyle,
+    pub ascii: bool,
+    pub log_count: Option<usize>,
+    pub entry_sort: EntrySort,
+    pub retry: RetryPolicy,
+    pub max_line_rows: Option<usize>,
+    pub mouse: bool,
+    pub key_bindings: KeyBindings,
+    pub theme: Theme,
+    pub warnings: Vec<String>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            initial_mode: ModeKind::default(),
+            spinner_style: SpinnerStyle::default(),
+            ascii: false,
+            log_count: None,
+            entry_sort: EntrySort::default(),
+            retry: RetryPolicy::default(),
+            max_line_rows: None,
+            mouse: true,
+            key_bindings: KeyBindings::default(),
+            theme: Theme::default(),
+            warnings: Vec::new(),
+        }
+    }
+}
+impl Config {
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        let paths = config_dir()
+            .into_iter()
+            .chain(Some(PathBuf::from(REPOSITORY_CONFIG_DIR)));
+        for path in paths {
+            if let Ok(text) = fs::read_to_string(path.join(CONFIG_FILE_NAME)) {
+                config.parse(&text);
+            }
+            let key_bindings_path = path.join(KEY_BINDINGS_FILE_NAME);
+            if let Ok(text) = fs::read_to_string(&key_bindings_path) {
+                let warnings = config.key_bindings.parse(&text);
+                config.push_warnings(&key_bindings_path, warnings);
+            }
+            let theme_path = path.join(THEME_FILE_NAME);
+            if let Ok(text) = fs::read_to_string(&theme_path) {
+                let warnings = config.theme.parse(&text);
+                config.push_warnings(&theme_path, warnings);
+            }
+        }
+        config
+    }
+
+    fn push_warnings(&mut self, path: &Path, warnings: Vec<(usize, String)>) {
+        for (line_index, warning) in warnings {
+            self.warnings.push(format!(
+                "{}:{}: {}",
+                path.display(),
+                line_index + 1,
+                warning
+            ));
+        }
+    }
+
+    fn parse(&mut self, text: &str) {
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut splits = line.splitn(2, '=');
+            let key = splits.next().unwrap_or("").trim();
+            let value = splits.next().unwrap_or("").trim().trim_matches('"');
+
+            match key {
+                "mode" => {
+                    if let Some(mode) = ModeKind::from_name(value) {
+                        self.initial_mode = mode;
+                    }
+                }
+                "spinner" => {
+                    if let Some(style) = SpinnerStyle::from_name(value) {
+                        self.spinner_style = style;
+                    }
+                }
+                "log_count" => {
+                    if let Ok(count @ 1..) = value.parse() {
+                        self.log_count = Some(count);
+                    }
+                }
+                "sort" => {
+                    if let Some(sort) = EntrySort::from_name(value) {
+                        self.entry_sort = sort;
+                    }
+                }
+                "retry_count" => {
+                    if let Ok(count) = value.parse() {
+                        self.retry.count = count;
+                    }
+                }
+                "retry_delay" => {
+                    if let Ok(seconds) = value.parse() {
+                        self.retry.delay = Duration::from_secs(seconds);
+                    }
+                }
+                "max_line_rows" => {
+                    if let Ok(rows) = value.parse() {
+                        self.max_line_rows = Some(rows);
+                    }
+                }
+                "ascii" => {
+                    if let Some(ascii) = parse_bool(value) {
+                        self.ascii = ascii;
+                    }
+                }
+                "mouse" => {
+                    if let Some(mouse) = parse_bool(value) {
+                        self.mouse = mouse;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+// (scope, action, default key)
+const ACTIONS: &[(&str, &str, Key)] = &[
+    ("global", "status", Key::Char('s')),
+    ("global", "log", Key::Char('l')),
+    ("global", "details", Key::Char('h')),
+    ("global", "branches", Key::Char('b')),
+    ("global", "tags", Key::Char('t')),
+    ("global", "stash", Key::Char('S')),
+    ("global", "reflog", Key::Char('L')),
+    ("global", "remotes", Key::Char('u')),
+    ("global", "sparse", Key::Char('w')),
+    ("global", "copy", Key::Char('Y')),
+    ("status", "commit", Key::Char('c')),
+    ("status", "commit_all", Key::Char('x')),
+    ("status", "commit_and_push", Key::Char('P')),
+    ("status", "amend", Key::Char('m')),
+    ("status", "revert", Key::Char('R')),
+    ("status", "diff", Key::Char('d')),
+    ("status", "hunks", Key::Char('H')),
+    ("status", "file_log", Key::Char('f')),
+    ("status", "take_local", Key::Char('O')),
+    ("status", "take_other", Key::Char('T')),
+    ("status", "continue", Key::Char('C')),
+    ("status", "abort", Key::Char('A')),
+    ("status", "skip", Key::Char('K')),
+    ("status", "sort", Key::Char('o')),
+    ("status", "enable_rerere", Key::Char('E')),
+    ("status", "stash", Key::Char('z')),
+    ("log", "checkout", Key::Char('g')),
+    ("log", "merge", Key::Char('m')),
+    ("log", "details", Key::Char('d')),
+    ("log", "show", Key::Char('v')),
+    ("log", "containing_refs", Key::Char('i')),
+    ("log", "load_more", Key::Char('n')),
+    ("log", "order", Key::Char('o')),
+    ("log", "graph", Key::Char('G')),
+    ("log", "grep", Key::Char('/')),
+    ("log", "date_range", Key::Char('T')),
+    ("log", "revert", Key::Char('V')),
+    ("log", "cherry_pick", Key::Char('C')),
+    ("log", "reset", Key::Char('X')),
+    ("log", "new_branch", Key::Char('B')),
+    ("log", "fetch", Key::Char('f')),
+    ("log", "pull", Key::Char('p')),
+    ("log", "push", Key::Char('P')),
+    ("log", "push_to", Key::Char('U')),
+    ("log", "sync", Key::Char('y')),
+    ("log", "dates", Key::Char('r')),
+    ("log", "compact", Key::Char('e')),
+    ("revision_details", "diff", Key::Char('d')),
+    ("revision_details", "containing_refs", Key::Char('i')),
+    ("revision_details", "revert", Key::Char('V')),
+    ("revision_details", "file_log", Key::Char('f')),
+    ("revision_details", "sort", Key::Char('o')),
+    ("file_log", "diff", Key::Char('d')),
+    ("file_log", "follow", Key::Char('F')),
+    ("branches", "checkout", Key::Char('g')),
+    ("branches", "new", Key::Char('n')),
+    ("branches", "rename", Key::Char('r')),
+    ("branches", "delete", Key::Char('D')),
+    ("branches", "merge", Key::Char('m')),
+    ("branches", "rebase", Key::Char('R')),
+    ("branches", "compare", Key::Char('c')),
+    ("branches", "merge_base", Key::Char('M')),
+    ("tags", "checkout", Key::Char('g')),
+    ("tags", "new", Key::Char('n')),
+    ("tags", "delete", Key::Char('D')),
+    ("tags", "delete_remote", Key::Char('R')),
+    ("stash", "new", Key::Char('n')),
+    ("stash", "apply", Key::Char('a')),
+    ("stash", "pop", Key::Char('p')),
+    ("stash", "drop", Key::Char('D')),
+    ("reflog", "details", Key::Char('d')),
+    ("reflog", "reset", Key::Char('X')),
+    ("remotes", "new", Key::Char('n')),
+    ("remotes", "rename", Key::Char('r')),
+    ("remotes", "remove", Key::Char('D')),
+    ("sparse", "new", Key::Char('n')),
+    ("sparse", "remove", Key::Char('D')),
+    ("sparse", "toggle", Key::Char('E')),
+];
+
+#[derive(Default)]
+pub struct KeyBindings {
+    // (scope, user key, default key)
+    bindings: Vec<(&'static str, Key, Key)>,
+}
+impl KeyBindings {
+    // returns the default key of the action bound to `key` in `scope`
+    // so modes can keep matching on their default keys
+    pub fn translate(&self, scope: &str, key: Key) -> Key {
+        for &(s, user_key, default_key) in self.bindings.iter().rev() {
+            if s == scope && user_key == key {
+                return default_key;
+            }
+        }
+        // the default key of a rebound action no longer triggers it
+        if self
+            .bindings
+            .iter()
+            .any(|&(s, _, default_key)| s == scope && default_key == key)
+        {
+            return Key::Unbound;
+        }
+        key
+    }
+
+    // rewrites the "[key]label" hints of actions rebound in `scope` to show their user keys
+    pub fn help(&self, scope: &str, text: &str) -> String {
+        let mut help = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('[') {
+            let end = match rest[start..].find(']') {
+                Some(len) => start + len,
+                None => break,
+            };
+            let name = &rest[start + 1..end];
+            let label = rest[end + 1..].split(" [").next().unwrap_or("");
+
+            help.push_str(&rest[..=start]);
+            match self.rebound_key(scope, name, label) {
+                Some(key) => help.push_str(&key_name(key)),
+                None => help.push_str(name),
+            }
+            rest = &rest[end..];
+        }
+        help.push_str(rest);
+        help
+    }
+
+    // the label must name the action so prompts reusing a default key are left alone
+    fn rebound_key(&self, scope: &str, name: &str, label: &str) -> Option<Key> {
+        let label = label.replace('-', " ");
+        let &(_, _, default_key) = ACTIONS.iter().find(|&&(s, action, default_key)| {
+            s == scope && key_name(default_key) == name && label.contains(&action.replace('_', " "))
+        })?;
+        self.bindings
+            .iter()
+            .rev()
+            .find(|&&(s, _, d)| s == scope && d == default_key)
+            .map(|&(_, user_key, _)| user_key)
+    }
+
+    pub fn is_bound(&self, scope: &str, key: Key) -> bool {
+        self.bindings
+            .iter()
+            .any(|&(s, user_key, _)| s == scope && user_key == key)
+    }
+
+    fn parse(&mut self, text: &str) -> Vec<(usize, String)> {
+        let mut warnings = Vec::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut splits = line.splitn(2, '=');
+            let action = splits.next().unwrap_or("").trim();
+            let key = splits.next().unwrap_or("").trim().trim_matches('"');
+
+            let mut action_splits = action.splitn(2, '.');
+            let scope = action_splits.next().unwrap_or("");
+            let name = action_splits.next().unwrap_or("");
+
+            let entry = ACTIONS.iter().find(|(s, n, _)| *s == scope && *n == name);
+            let (scope, default_key) = match entry {
+                Some(&(scope, _, default_key)) => (scope, default_key),
+                None => {
+                    warnings.push((line_index, format!("unknown action '{}'", action)));
+                    continue;
+                }
+            };
+
+            match parse_key(key) {
+                Some(key) => self.bindings.push((scope, key, default_key)),
+                None => warnings.push((line_index, format!("invalid key '{}'", key))),
+            }
+        }
+        warnings
+    }
+}
+
+fn parse_key(text: &str) -> Option<Key> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() => return Some(Key::Char(c)),
+        _ => (),
+    }
+
+    let c = text.strip_prefix("ctrl+")?;
+    let mut chars = c.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_lowercase() => Some(Key::Ctrl(c)),
+        _ => None,
+    }
+}
+
+fn key_name(key: Key) -> String {
+    match key {
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl+{}", c),
+        _ => String::new(),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn config_dir() -> Option<PathBuf> {
+    let dir = env::var_os("APPDATA")?;
+    Some(PathBuf::from(dir).join("verco"))
+}
+
+#[cfg(target_os = "macos")]
+fn config_dir() -> Option<PathBuf> {
+    let home = env::var_os("HOME")?;
+    Some(PathBuf::from(home).join("Library/Application Support/verco"))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("verco")),
+        _ => {
+            let home = env::var_os("HOME")?;
+            Some(PathBuf::from(home).join(".config/verco"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_bindings(text: &str) -> KeyBindings {
+        let mut key_bindings = KeyBindings::default();
+        assert!(key_bindings.parse(text).is_empty());
+        key_bindings
+    }
+
+    #[test]
+    fn rebinding_releases_the_default_key() {
+        let key_bindings = key_bindings("log.checkout = \"ctrl+o\"\nstash.drop = \"x\"");
+        assert_eq!(
+            Key::Char('g'),
+            key_bindings.translate("log", Key::Ctrl('o'))
+        );
+        assert_eq!(Key::Unbound, key_bindings.translate("log", Key::Char('g')));
+        assert_eq!(
+            Key::Char('D'),
+            key_bindings.translate("stash", Key::Char('x'))
+        );
+        assert_eq!(
+            Key::Unbound,
+            key_bindings.translate("stash", Key::Char('D'))
+        );
+
+        // other scopes and actions keep their default keys
+        assert_eq!(
+            Key::Char('g'),
+            key_bindings.translate("branches", Key::Char('g'))
+        );
+        assert_eq!(
+            Key::Char('m'),
+            key_bindings.translate("log", Key::Char('m'))
+        );
+    }
+
+    #[test]
+    fn default_key_can_be_taken_by_another_action() {
+        let key_bindings = key_bindings("log.checkout = \"x\"\nlog.merge = \"g\"");
+        assert_eq!(
+            Key::Char('g'),
+            key_bindings.translate("log", Key::Char('x'))
+        );
+        assert_eq!(
+            Key::Char('m'),
+            key_bindings.translate("log", Key::Char('g'))
+        );
+        assert_eq!(Key::Unbound, key_bindings.translate("log", Key::Char('m')));
+    }
+
+    #[test]
+    fn help_shows_user_keys() {
+        let key_bindings = key_bindings("log.checkout = \"ctrl+o\"\nlog.cherry_pick = \"c\"");
+        assert_eq!(
+            "[ctrl+o]checkout [d]details [c]cherry-pick",
+            key_bindings.help("log", "[g]checkout [d]details [C]cherry-pick"),
+        );
+        assert_eq!("[g]checkout", key_bindings.help("branches", "[g]checkout"));
+    }
+
+    #[test]
+    fn help_keeps_prompt_keys_sharing_a_default_key() {
+        let key_bindings = key_bindings("log.merge = \"M\"\nlog.fetch = \"F\"");
+        assert_eq!(
+            "[s]soft [m]mixed [h]hard",
+            key_bindings.help("log", "[s]soft [m]mixed [h]hard"),
+        );
+        assert_eq!(
+            "[enter]push [f]force with lease [u]set upstream",
+            key_bindings.help("log", "[enter]push [f]force with lease [u]set upstream"),
+        );
+        assert_eq!("[F]fetch", key_bindings.help("log", "[f]fetch"));
+    }
+}
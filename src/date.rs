@@ -0,0 +1,78 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub fn now() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as _,
+        Err(_) => 0,
+    }
+}
+
+// https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+pub fn timestamp_from_civil(
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    days * SECONDS_PER_DAY + hour * 60 * 60 + minute * 60 + second
+}
+
+// https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_timestamp(timestamp: i64) -> (i64, i64, i64) {
+    let days = timestamp.div_euclid(SECONDS_PER_DAY) + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400;
+    let year = if month <= 2 { year + 1 } else { year };
+
+    (year, month, day)
+}
+
+pub fn format_absolute(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_timestamp(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub fn format_relative(timestamp: i64, now: i64) -> String {
+    const UNITS: &[(&str, i64)] = &[
+        ("year", 365 * SECONDS_PER_DAY),
+        ("month", 30 * SECONDS_PER_DAY),
+        ("week", 7 * SECONDS_PER_DAY),
+        ("day", SECONDS_PER_DAY),
+        ("hour", 60 * 60),
+        ("minute", 60),
+    ];
+
+    let elapsed = now.saturating_sub(timestamp);
+    for &(name, seconds) in UNITS {
+        let count = elapsed / seconds;
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{} {}{} ago", count, name, plural);
+        }
+    }
+
+    "just now".into()
+}
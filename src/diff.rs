@@ -0,0 +1,81 @@
+pub struct Hunk {
+    // the '@@' line followed by the hunk body, newlines included
+    text: String,
+}
+impl Hunk {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn header(&self) -> &str {
+        self.text.lines().next().unwrap_or("")
+    }
+
+    pub fn body(&self) -> impl Iterator<Item = &str> {
+        self.text.lines().skip(1)
+    }
+
+    pub fn line_counts(&self) -> (usize, usize) {
+        let mut additions = 0;
+        let mut deletions = 0;
+        for line in self.body() {
+            if line.starts_with('+') {
+                additions += 1;
+            } else if line.starts_with('-') {
+                deletions += 1;
+            }
+        }
+        (additions, deletions)
+    }
+}
+
+#[derive(Default)]
+pub struct FileDiff {
+    // everything before the first hunk ('diff --git', 'index', '---' and '+++' lines)
+    header: String,
+    pub hunks: Vec<Hunk>,
+}
+impl FileDiff {
+    // splits the unified diff of a single file into its '@@' delimited hunks
+    pub fn parse(diff: &str) -> Self {
+        let mut file_diff = Self::default();
+        for line in diff.split_inclusive('\n') {
+            if line.starts_with("@@") {
+                file_diff.hunks.push(Hunk { text: line.into() });
+            } else if let Some(hunk) = file_diff.hunks.last_mut() {
+                if line.starts_with("diff ") {
+                    break;
+                }
+                hunk.text.push_str(line);
+            } else {
+                file_diff.header.push_str(line);
+            }
+        }
+
+        for hunk in &mut file_diff.hunks {
+            if !hunk.text.ends_with('\n') {
+                hunk.text.push('\n');
+            }
+        }
+        file_diff
+    }
+
+    // builds a patch containing only the hunks at `hunk_indices`
+    pub fn patch(&self, hunk_indices: &[usize]) -> Result<String, String> {
+        let mut patch = self.header.clone();
+        for (i, hunk) in self.hunks.iter().enumerate() {
+            if hunk_indices.contains(&i) {
+                patch.push_str(&hunk.text);
+            }
+        }
+
+        match hunk_indices.iter().find(|&&i| i >= self.hunks.len()) {
+            Some(i) => Err(format!(
+                "hunk {} not found. the file has {} hunk(s)",
+                i + 1,
+                self.hunks.len()
+            )),
+            None => Ok(patch),
+        }
+    }
+}
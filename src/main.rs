@@ -1,44 +1,112 @@
-use std::{env, io};
+use std::{env, io, process};
 
 mod application;
 mod backend;
+mod clipboard;
+mod config;
+mod date;
+mod diff;
 mod mode;
 mod platform;
+mod print;
+mod theme;
 mod ui;
+mod unicode;
 
 fn main() {
+    let mut initial_mode = None;
+    let mut spinner_style = None;
+    let mut ascii = false;
+    let mut print_mode = None;
+
     let mut args = env::args();
     args.next();
-    if let Some(arg) = args.next() {
-        if args.next().is_some() {
-            eprintln!("too many args");
-        } else {
-            match &arg[..] {
-                "-h" | "--help" => {
-                    let name = env!("CARGO_PKG_NAME");
-                    let version = env!("CARGO_PKG_VERSION");
-                    println!("{} v{}", name, version);
-                    println!();
-                    println!("{}", env!("CARGO_PKG_DESCRIPTION"));
-                    println!();
-                    println!("\t-h --help\tprint this help message and exit");
-                    println!("\t-v --version\tprint version number and exit");
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "-h" | "--help" => {
+                let name = env!("CARGO_PKG_NAME");
+                let version = env!("CARGO_PKG_VERSION");
+                println!("{} v{}", name, version);
+                println!();
+                println!("{}", env!("CARGO_PKG_DESCRIPTION"));
+                println!();
+                println!("usage: {} [OPTIONS] [MODE]", name);
+                println!();
+                println!("\t-h --help\tprint this help message and exit");
+                println!("\t-v --version\tprint version number and exit");
+                println!(
+                    "\t-m --mode MODE\tstart in MODE ({})",
+                    mode::ModeKind::NAMES.join(", ")
+                );
+                println!(
+                    "\t-p --print MODE\tprint MODE as plain text and exit ({})",
+                    mode::ModeKind::NAMES.join(", ")
+                );
+                println!("\t--ascii\t\tuse only ascii characters for ui decorations");
+                println!(
+                    "\t--spinner STYLE\tset the header spinner STYLE ({})",
+                    ui::SpinnerStyle::NAMES.join(", ")
+                );
+                return;
+            }
+            "-v" | "--version" => {
+                println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            "-m" | "--mode" => match args.next() {
+                Some(name) => initial_mode = Some(parse_mode(&name)),
+                None => {
+                    eprintln!("missing mode after '{}'", arg);
+                    process::exit(1);
+                }
+            },
+            "-p" | "--print" => match args.next() {
+                Some(name) => print_mode = Some(parse_mode(&name)),
+                None => {
+                    eprintln!("missing mode after '{}'", arg);
+                    process::exit(1);
                 }
-                "-v" | "--version" => {
-                    print!("{}", env!("CARGO_PKG_VERSION"));
+            },
+            "--ascii" => ascii = true,
+            "--spinner" => match args.next().as_deref().map(ui::SpinnerStyle::from_name) {
+                Some(Some(style)) => spinner_style = Some(style),
+                _ => {
+                    eprintln!(
+                        "expected spinner style after '{}'. valid styles are: {}",
+                        arg,
+                        ui::SpinnerStyle::NAMES.join(", ")
+                    );
+                    process::exit(1);
                 }
-                arg => eprintln!("invalid argument '{}'", arg),
+            },
+            arg if arg.starts_with('-') => {
+                eprintln!("invalid argument '{}'", arg);
+                process::exit(1);
             }
+            name => initial_mode = Some(parse_mode(name)),
         }
-        return;
     }
 
     let (root, backend) = match backend::backend_from_current_repository() {
         Some((root, backend)) => (root, backend),
-        None => {
+        None if print_mode.is_some() => {
             eprintln!("no repository found");
-            return;
+            process::exit(1);
         }
+        None => match backend::backend_from_available_program() {
+            Some(backend) => {
+                initial_mode = Some(mode::ModeKind::Clone);
+                (env::current_dir().unwrap_or_default(), backend)
+            }
+            None => {
+                if backend::is_program_available("cm") {
+                    eprintln!("no repository found");
+                } else {
+                    eprintln!("no repository found: none of git, hg or cm were found in PATH");
+                }
+                return;
+            }
+        },
     };
 
     if std::env::set_current_dir(&root).is_err() {
@@ -46,7 +114,35 @@ fn main() {
         return;
     }
 
-    let (platform, platform_event_reader) = match platform::Platform::new() {
+    if let Some(mode) = print_mode {
+        let colored = platform::Platform::is_stdout_terminal();
+        match print::print(backend.as_ref(), &mode, colored) {
+            Ok(output) => print!("{}", output),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut config = config::Config::load();
+    if backend.is_bare() {
+        if let mode::ModeKind::Status = config.initial_mode {
+            config.initial_mode = mode::ModeKind::Log;
+        }
+    }
+    if let Some(mode) = initial_mode {
+        config.initial_mode = mode;
+    }
+    if let Some(style) = spinner_style {
+        config.spinner_style = style;
+    }
+    if ascii {
+        config.ascii = true;
+    }
+
+    let (platform, platform_event_reader) = match platform::Platform::new(config.mouse) {
         Some(platform) => platform,
         None => return,
     };
@@ -63,10 +159,15 @@ fn main() {
         stdout.write_all(ui::END_TITLE_CODE).unwrap();
         stdout.write_all(ui::ENTER_ALTERNATE_BUFFER_CODE).unwrap();
         stdout.write_all(ui::HIDE_CURSOR_CODE).unwrap();
+        stdout.write_all(ui::ENABLE_BRACKETED_PASTE_CODE).unwrap();
+        if config.mouse {
+            stdout.write_all(ui::ENABLE_MOUSE_CODE).unwrap();
+        }
         stdout.flush().unwrap();
     }
 
-    application::run(platform_event_reader, backend);
+    let mouse = config.mouse;
+    application::run(platform_event_reader, backend, config);
 
     {
         use io::Write;
@@ -74,6 +175,10 @@ fn main() {
         let mut stdout = stdout.lock();
 
         stdout.write_all(ui::RESET_STYLE_CODE).unwrap();
+        stdout.write_all(ui::DISABLE_BRACKETED_PASTE_CODE).unwrap();
+        if mouse {
+            stdout.write_all(ui::DISABLE_MOUSE_CODE).unwrap();
+        }
         stdout.write_all(ui::SHOW_CURSOR_CODE).unwrap();
         stdout.write_all(ui::EXIT_ALTERNATE_BUFFER_CODE).unwrap();
         stdout.flush().unwrap();
@@ -81,3 +186,17 @@ fn main() {
 
     drop(platform);
 }
+
+fn parse_mode(name: &str) -> mode::ModeKind {
+    match mode::ModeKind::from_name(name) {
+        Some(mode) => mode,
+        None => {
+            eprintln!(
+                "invalid mode '{}'. valid modes are: {}",
+                name,
+                mode::ModeKind::NAMES.join(", ")
+            );
+            process::exit(1);
+        }
+    }
+}
@@ -1,31 +1,151 @@
-use std::sync::Arc;
+use std::{cell::Cell, env, fs, sync::Arc};
 
-use crate::{application::EventSender, backend::Backend, platform::Key};
+use crate::{
+    application::EventSender,
+    backend::{Backend, FileStatus, SelectableRevisionEntry},
+    config::KeyBindings,
+    platform::Key,
+    ui::line_display_width,
+    unicode::is_grapheme_extend,
+};
 
 pub mod branches;
+pub mod clone;
+pub mod file_log;
 pub mod log;
+pub mod reflog;
+pub mod remotes;
 pub mod revision_details;
+pub mod sparse;
+pub mod stash;
 pub mod status;
 pub mod tags;
 
+pub fn has_conflicts(backend: &dyn Backend) -> bool {
+    match backend.status() {
+        Ok(info) => info
+            .entries
+            .iter()
+            .any(|e| matches!(e.status, FileStatus::Unmerged)),
+        Err(_) => false,
+    }
+}
+
+pub fn window_title(header: &str, dirty: bool) -> String {
+    let repository = env::current_dir()
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+    let branch = header.lines().next().unwrap_or("");
+    let branch = branch.trim_start_matches("## ");
+    let dirty = if dirty { " *" } else { "" };
+    format!("{} - {}{}", repository, branch, dirty)
+}
+
+pub fn is_valid_ref_name(name: &str) -> bool {
+    const FORBIDDEN_CHARS: &[char] = &[' ', '~', '^', ':', '?', '*', '[', '\\'];
+
+    if name.is_empty() || name == "@" || name.starts_with('-') || name.ends_with('.') {
+        return false;
+    }
+    if name.contains("..") || name.contains("@{") {
+        return false;
+    }
+    if name
+        .chars()
+        .any(|c| c.is_ascii_control() || FORBIDDEN_CHARS.contains(&c))
+    {
+        return false;
+    }
+
+    name.split('/')
+        .all(|c| !c.is_empty() && !c.starts_with('.') && !c.ends_with(".lock"))
+}
+
 pub enum ModeResponse {
     Status(status::Response),
     Log(log::Response),
     RevisionDetails(revision_details::Response),
+    FileLog(file_log::Response),
     Branches(branches::Response),
     Tags(tags::Response),
+    Stash(stash::Response),
+    Reflog(reflog::Response),
+    Remotes(remotes::Response),
+    Sparse(sparse::Response),
+    Clone(clone::Response),
+}
+impl ModeResponse {
+    // the mode whose entries this response reloads, if it is a refresh
+    pub fn refreshed_mode(&self) -> Option<ModeKind> {
+        match self {
+            Self::Status(status::Response::Refresh(_)) => Some(ModeKind::Status),
+            Self::Log(log::Response::Refresh(_)) => Some(ModeKind::Log),
+            Self::FileLog(file_log::Response::Refresh(path, _)) => {
+                Some(ModeKind::FileLog(path.clone()))
+            }
+            Self::Branches(branches::Response::Refresh(_)) => Some(ModeKind::Branches),
+            Self::Tags(tags::Response::Refresh(_)) => Some(ModeKind::Tags),
+            Self::Stash(stash::Response::Refresh(_)) => Some(ModeKind::Stash),
+            Self::Reflog(reflog::Response::Refresh(_)) => Some(ModeKind::Reflog),
+            Self::Remotes(remotes::Response::Refresh(_)) => Some(ModeKind::Remotes),
+            Self::Sparse(sparse::Response::Refresh(_)) => Some(ModeKind::Sparse),
+            _ => None,
+        }
+    }
 }
 
+#[derive(Clone, Default)]
 pub enum ModeKind {
+    #[default]
     Status,
     Log,
     RevisionDetails(String),
+    FileLog(String),
     Branches,
     Tags,
+    Stash,
+    Reflog,
+    Remotes,
+    Sparse,
+    Clone,
 }
-impl Default for ModeKind {
-    fn default() -> Self {
-        Self::Status
+impl ModeKind {
+    pub const NAMES: &'static [&'static str] = &[
+        "status", "log", "branches", "tags", "stash", "reflog", "remotes", "sparse",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "status" => Some(Self::Status),
+            "log" => Some(Self::Log),
+            "branches" => Some(Self::Branches),
+            "tags" => Some(Self::Tags),
+            "stash" => Some(Self::Stash),
+            "reflog" => Some(Self::Reflog),
+            "remotes" => Some(Self::Remotes),
+            "sparse" => Some(Self::Sparse),
+            _ => None,
+        }
+    }
+
+    pub fn key_scope(&self) -> &'static str {
+        match self {
+            Self::Status => "status",
+            Self::Log => "log",
+            Self::RevisionDetails(_) => "revision_details",
+            Self::FileLog(_) => "file_log",
+            Self::Branches => "branches",
+            Self::Tags => "tags",
+            Self::Stash => "stash",
+            Self::Reflog => "reflog",
+            Self::Remotes => "remotes",
+            Self::Sparse => "sparse",
+            Self::Clone => "clone",
+        }
     }
 }
 
@@ -34,49 +154,333 @@ pub struct ModeContext {
     pub backend: Arc<dyn Backend>,
     pub event_sender: EventSender,
     pub viewport_size: (u16, u16),
+    pub key_bindings: Arc<KeyBindings>,
 }
 
+const MOUSE_SCROLL_LINES: usize = 3;
+
 pub struct ModeStatus {
     pub pending_input: bool,
 }
 
+#[derive(Clone, Copy, Default)]
+pub enum OutputKind {
+    #[default]
+    Plain,
+    Diff,
+}
+
 #[derive(Default)]
 pub struct Output {
     text: String,
+    kind: OutputKind,
     line_count: usize,
     scroll: usize,
+    cursor: usize,
+    show_cursor: bool,
+    follow: bool,
+    search: ReadLine,
+    searching: bool,
+    search_pattern: String,
+    case_sensitive: bool,
+    search_matches: Vec<usize>,
+    current_match: usize,
+    no_wrap: bool,
+    h_scroll: usize,
+    max_line_len: usize,
 }
 impl Output {
     pub fn set(&mut self, output: String) {
         self.text = output;
+        self.kind = OutputKind::Plain;
         self.line_count = self.text.lines().count();
+        self.max_line_len = self.text.lines().map(line_display_width).max().unwrap_or(0);
+        self.h_scroll = 0;
         self.scroll = 0;
+        self.cursor = 0;
+        self.searching = false;
+        self.search("");
+    }
+
+    pub fn search(&mut self, pattern: &str) {
+        self.search_pattern.clear();
+        self.search_pattern.push_str(pattern);
+        self.current_match = 0;
+        self.search_matches.clear();
+        if self.search_pattern.is_empty() {
+            return;
+        }
+        for (i, line) in self.text.lines().enumerate() {
+            if !self.match_ranges(line).is_empty() {
+                self.search_matches.push(i);
+            }
+        }
+    }
+
+    pub fn match_ranges(&self, line: &str) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        if self.search_pattern.is_empty() {
+            return ranges;
+        }
+
+        let mut start = 0;
+        while start < line.len() {
+            let mut line_chars = line[start..].chars();
+            let mut end = start;
+            let found = self
+                .search_pattern
+                .chars()
+                .all(|p| match line_chars.next() {
+                    Some(c)
+                        if c == p
+                            || (!self.case_sensitive && c.to_lowercase().eq(p.to_lowercase())) =>
+                    {
+                        end += c.len_utf8();
+                        true
+                    }
+                    _ => false,
+                });
+
+            if found {
+                ranges.push((start, end));
+                start = end;
+            } else {
+                start += line[start..]
+                    .chars()
+                    .next()
+                    .map(char::len_utf8)
+                    .unwrap_or(1);
+            }
+        }
+        ranges
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    pub fn search_input(&self) -> &ReadLine {
+        &self.search
+    }
+
+    pub fn search_pattern(&self) -> &str {
+        &self.search_pattern
+    }
+
+    pub fn search_matches(&self) -> (usize, usize) {
+        (self.current_match, self.search_matches.len())
+    }
+
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    pub fn on_search_key(&mut self, available_height: usize, key: Key) -> bool {
+        if self.searching {
+            match key {
+                Key::Tab => self.case_sensitive = !self.case_sensitive,
+                _ => self.search.on_key(key),
+            }
+            if key.is_submit() {
+                self.searching = false;
+                let pattern = self.search.input().to_string();
+                self.search(&pattern);
+
+                let position = self.position(available_height);
+                self.current_match = self
+                    .search_matches
+                    .iter()
+                    .position(|&line| line >= position)
+                    .unwrap_or(0);
+                self.show_current_match(available_height);
+            } else if key.is_cancel() {
+                self.searching = false;
+            }
+            return true;
+        }
+
+        match key {
+            Key::Char('/') => {
+                self.searching = true;
+                self.search.set_input(&self.search_pattern);
+                true
+            }
+            Key::Char('n') | Key::Char('N') if !self.search_matches.is_empty() => {
+                let len = self.search_matches.len();
+                self.current_match = match key {
+                    Key::Char('n') => (self.current_match + 1) % len,
+                    _ => (self.current_match + len - 1) % len,
+                };
+                self.show_current_match(available_height);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn position(&self, available_height: usize) -> usize {
+        if self.show_cursor {
+            self.cursor
+        } else {
+            self.scroll(available_height)
+        }
+    }
+
+    fn show_current_match(&mut self, available_height: usize) {
+        if let Some(&line) = self.search_matches.get(self.current_match) {
+            self.follow = false;
+            self.cursor = line;
+            self.center_on_line(line, available_height);
+        }
     }
 
     pub fn text(&self) -> &str {
         &self.text
     }
 
+    pub fn kind(&self) -> OutputKind {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: OutputKind) {
+        self.kind = kind;
+    }
+
     pub fn line_count(&self) -> usize {
         self.line_count
     }
 
-    pub fn lines_from_scroll<'a>(&'a self) -> impl 'a + Iterator<Item = &'a str> {
-        self.text.lines().skip(self.scroll)
+    pub fn scroll(&self, available_height: usize) -> usize {
+        if self.follow && !self.show_cursor {
+            self.line_count.saturating_sub(available_height)
+        } else {
+            self.scroll
+        }
+    }
+
+    pub fn is_wrapping(&self) -> bool {
+        !self.no_wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.no_wrap = !wrap;
+        self.h_scroll = 0;
+    }
+
+    pub fn h_scroll(&self) -> usize {
+        self.h_scroll
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+
+    pub fn cursor(&self) -> Option<usize> {
+        if self.show_cursor {
+            Some(self.cursor)
+        } else {
+            None
+        }
+    }
+
+    pub fn center_on_line(&mut self, line: usize, available_height: usize) {
+        self.scroll = line.saturating_sub(available_height / 2);
+        self.scroll = self
+            .line_count
+            .saturating_sub(available_height)
+            .min(self.scroll);
+    }
+
+    pub fn lines_from_scroll<'a>(
+        &'a self,
+        available_height: usize,
+    ) -> impl 'a + Iterator<Item = &'a str> {
+        self.text.lines().skip(self.scroll(available_height))
     }
 
     pub fn on_key(&mut self, available_height: usize, key: Key) {
+        self.scroll = self.scroll(available_height);
+        match key {
+            Key::Char('v') => {
+                self.show_cursor = !self.show_cursor;
+                self.follow = false;
+                self.cursor = self.scroll;
+                return;
+            }
+            Key::Char('W') => {
+                self.set_wrap(self.no_wrap);
+                return;
+            }
+            // h_scroll and max_line_len are both in display columns
+            Key::ShiftLeft | Key::ShiftRight if self.no_wrap => {
+                const H_SCROLL_STEP: usize = 8;
+                self.h_scroll = match key {
+                    Key::ShiftRight => (self.h_scroll + H_SCROLL_STEP)
+                        .min(self.max_line_len.saturating_sub(H_SCROLL_STEP)),
+                    _ => self.h_scroll.saturating_sub(H_SCROLL_STEP),
+                };
+                return;
+            }
+            Key::Char('z') if self.show_cursor => {
+                self.center_on_line(self.cursor, available_height);
+                return;
+            }
+            // like in vim and less, ctrl+e and ctrl+y scroll the view by a single line
+            // and only move the cursor when it would leave the view
+            Key::Ctrl('e') | Key::Ctrl('y') | Key::ScrollDown | Key::ScrollUp => {
+                let max_scroll = self.line_count.saturating_sub(available_height);
+                self.scroll = match key {
+                    Key::Ctrl('e') => (self.scroll + 1).min(max_scroll),
+                    Key::Ctrl('y') => self.scroll.saturating_sub(1),
+                    Key::ScrollDown => (self.scroll + MOUSE_SCROLL_LINES).min(max_scroll),
+                    _ => self.scroll.saturating_sub(MOUSE_SCROLL_LINES),
+                };
+                self.cursor = self.cursor.max(self.scroll).min(
+                    (self.scroll + available_height)
+                        .saturating_sub(1)
+                        .min(self.line_count.saturating_sub(1)),
+                );
+                if let Key::Ctrl('y') | Key::ScrollUp = key {
+                    self.follow = false;
+                }
+                return;
+            }
+            _ => (),
+        }
+
         let half_height = available_height / 2;
+        let position = if self.show_cursor {
+            self.cursor
+        } else {
+            self.scroll
+        };
 
-        self.scroll = match key {
-            Key::Down | Key::Ctrl('n') | Key::Char('j') => self.scroll + 1,
-            Key::Up | Key::Ctrl('p') | Key::Char('k') => self.scroll.saturating_sub(1),
+        let position = match key {
+            Key::Down | Key::Ctrl('n') | Key::Char('j') => position + 1,
+            Key::Up | Key::Ctrl('p') | Key::Char('k') => position.saturating_sub(1),
             Key::Ctrl('h') | Key::Home => 0,
-            Key::Ctrl('e') | Key::End => usize::MAX,
-            Key::Ctrl('d') | Key::PageDown => self.scroll + half_height,
-            Key::Ctrl('u') | Key::PageUp => self.scroll.saturating_sub(half_height),
-            _ => self.scroll,
+            Key::End => usize::MAX,
+            Key::Ctrl('d') | Key::PageDown => position + half_height,
+            Key::Ctrl('u') | Key::PageUp => position.saturating_sub(half_height),
+            _ => position,
         };
+        match key {
+            Key::End => self.follow = !self.show_cursor,
+            Key::Up | Key::Ctrl('p') | Key::Char('k') => self.follow = false,
+            Key::Ctrl('h') | Key::Home | Key::Ctrl('u') | Key::PageUp => self.follow = false,
+            _ => (),
+        }
+
+        if self.show_cursor {
+            self.cursor = self.line_count.saturating_sub(1).min(position);
+            if self.cursor < self.scroll {
+                self.scroll = self.cursor;
+            } else if self.cursor >= self.scroll + available_height {
+                self.scroll = self.cursor + 1 - available_height;
+            }
+        } else {
+            self.scroll = position;
+        }
 
         self.scroll = self
             .line_count
@@ -98,37 +502,75 @@ impl ReadLine {
         &self.input
     }
 
+    pub fn paste(&mut self, text: &str) {
+        let text = text.trim_end_matches(&['\r', '\n'][..]);
+        for c in text.chars() {
+            match c {
+                '\r' | '\n' | '\t' => self.input.push(' '),
+                c if c.is_control() => (),
+                c => self.input.push(c),
+            }
+        }
+    }
+
+    pub fn complete(&mut self, candidates: &[String]) {
+        let mut matching = candidates.iter().filter(|c| c.starts_with(&self.input));
+        let mut prefix = match matching.next() {
+            Some(candidate) => candidate.as_str(),
+            None => return,
+        };
+        for candidate in matching {
+            let len = prefix
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map(|((i, _), _)| i)
+                .unwrap_or_else(|| prefix.len().min(candidate.len()));
+            prefix = &prefix[..len];
+        }
+        if prefix.len() > self.input.len() {
+            self.set_input(prefix);
+        }
+    }
+
+    pub fn set_input(&mut self, input: &str) {
+        self.input.clear();
+        self.input.push_str(input);
+    }
+
     pub fn on_key(&mut self, key: Key) {
         match key {
             Key::Home | Key::Ctrl('u') => self.input.clear(),
             Key::Ctrl('w') => {
-                fn is_word(c: char) -> bool {
-                    c.is_alphanumeric() || c == '_'
+                #[derive(PartialEq)]
+                enum CharClass {
+                    Word,
+                    Whitespace,
+                    Other,
                 }
 
-                fn rfind_boundary(mut chars: std::str::Chars, f: fn(&char) -> bool) -> usize {
-                    match chars.rfind(f) {
-                        Some(c) => chars.as_str().len() + c.len_utf8(),
-                        None => 0,
+                fn char_class(grapheme: &str) -> CharClass {
+                    match grapheme.chars().next() {
+                        Some(c) if c.is_alphanumeric() || c == '_' => CharClass::Word,
+                        Some(c) if c.is_ascii_whitespace() => CharClass::Whitespace,
+                        _ => CharClass::Other,
                     }
                 }
 
-                let mut chars = self.input.chars();
-                if let Some(c) = chars.next_back() {
-                    let len = if is_word(c) {
-                        rfind_boundary(chars, |&c| !is_word(c))
-                    } else if c.is_ascii_whitespace() {
-                        rfind_boundary(chars, |&c| is_word(c) || !c.is_ascii_whitespace())
-                    } else {
-                        rfind_boundary(chars, |&c| is_word(c) || c.is_ascii_whitespace())
-                    };
-                    self.input.truncate(len);
+                let class = char_class(&self.input[previous_grapheme_boundary(&self.input)..]);
+                let mut len = self.input.len();
+                while len > 0 {
+                    let start = previous_grapheme_boundary(&self.input[..len]);
+                    if char_class(&self.input[start..len]) != class {
+                        break;
+                    }
+                    len = start;
                 }
+                self.input.truncate(len);
             }
             Key::Backspace | Key::Ctrl('h') => {
-                if let Some((last_char_index, _)) = self.input.char_indices().next_back() {
-                    self.input.truncate(last_char_index);
-                }
+                let len = previous_grapheme_boundary(&self.input);
+                self.input.truncate(len);
             }
             Key::Char(c) => self.input.push(c),
             _ => (),
@@ -136,6 +578,202 @@ impl ReadLine {
     }
 }
 
+pub fn path_candidates(input: &str) -> Vec<String> {
+    let (dir, file_prefix) = match input.rfind(&['/', std::path::MAIN_SEPARATOR][..]) {
+        Some(i) => input.split_at(i + 1),
+        None => ("", input),
+    };
+    let entries = match fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(file_prefix) {
+                return None;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let separator = if is_dir { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, separator))
+        })
+        .collect()
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c, '\u{1f1e6}'..='\u{1f1ff}')
+}
+
+// approximates unicode grapheme segmentation for combining marks, emoji
+// modifiers, zwj sequences and flags
+fn previous_grapheme_boundary(text: &str) -> usize {
+    let mut chars = text.char_indices().rev().peekable();
+    let mut start = text.len();
+    loop {
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_grapheme_extend(c) {
+                break;
+            }
+            start = i;
+            chars.next();
+        }
+
+        let (i, c) = match chars.next() {
+            Some(entry) => entry,
+            None => return start,
+        };
+        start = i;
+
+        if is_regional_indicator(c) {
+            let preceding = chars
+                .clone()
+                .take_while(|&(_, c)| is_regional_indicator(c))
+                .count();
+            if preceding % 2 == 1 {
+                if let Some((i, _)) = chars.next() {
+                    start = i;
+                }
+            }
+            return start;
+        }
+
+        match chars.peek() {
+            Some(&(i, '\u{200d}')) => {
+                start = i;
+                chars.next();
+            }
+            _ => return start,
+        }
+    }
+}
+
+pub struct FormField {
+    pub label: &'static str,
+    pub readline: ReadLine,
+}
+
+#[derive(Default)]
+pub struct Form {
+    fields: Vec<FormField>,
+    focused: usize,
+}
+impl Form {
+    pub fn new(labels: &[&'static str]) -> Self {
+        let fields = labels
+            .iter()
+            .map(|&label| FormField {
+                label,
+                readline: ReadLine::default(),
+            })
+            .collect();
+        Self { fields, focused: 0 }
+    }
+
+    pub fn fields(&self) -> &[FormField] {
+        &self.fields
+    }
+
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    pub fn input(&self, index: usize) -> &str {
+        self.fields
+            .get(index)
+            .map(|f| f.readline.input())
+            .unwrap_or("")
+    }
+
+    pub fn set_input(&mut self, index: usize, input: &str) {
+        if let Some(field) = self.fields.get_mut(index) {
+            field.readline.set_input(input);
+        }
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            field.readline.paste(text);
+        }
+    }
+
+    pub fn complete(&mut self, candidates: &[String]) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            field.readline.complete(candidates);
+        }
+    }
+
+    pub fn on_key(&mut self, key: Key) {
+        if self.fields.is_empty() {
+            return;
+        }
+
+        match key {
+            Key::Tab | Key::Down => self.focused = (self.focused + 1) % self.fields.len(),
+            Key::Up => {
+                self.focused = (self.focused + self.fields.len() - 1) % self.fields.len();
+            }
+            _ => self.fields[self.focused].readline.on_key(key),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum EntrySort {
+    #[default]
+    Status,
+    Path,
+    Directory,
+}
+impl EntrySort {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "status" => Some(Self::Status),
+            "path" => Some(Self::Path),
+            "directory" => Some(Self::Directory),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Status => Self::Path,
+            Self::Path => Self::Directory,
+            Self::Directory => Self::Status,
+        }
+    }
+
+    pub fn sort(&self, entries: &mut [SelectableRevisionEntry]) {
+        fn split_directory(name: &str) -> (&str, &str) {
+            match name.rfind('/') {
+                Some(i) => (&name[..i], &name[i + 1..]),
+                None => ("", name),
+            }
+        }
+
+        match self {
+            Self::Status => entries.sort_by(|a, b| a.status.cmp(&b.status)),
+            Self::Path => entries.sort_by(|a, b| a.name.cmp(&b.name)),
+            Self::Directory => {
+                entries.sort_by(|a, b| split_directory(&a.name).cmp(&split_directory(&b.name)))
+            }
+        }
+    }
+}
+
+pub fn sort_entries(
+    sort: EntrySort,
+    entries: &mut [SelectableRevisionEntry],
+    select: &mut SelectMenu,
+) {
+    let hovered = entries.get(select.cursor()).map(|e| e.name.clone());
+    sort.sort(entries);
+    if let Some(i) = hovered.and_then(|name| entries.iter().position(|e| e.name == name)) {
+        select.set_cursor(i);
+    }
+}
+
 pub enum SelectMenuAction {
     None,
     Toggle(usize),
@@ -146,6 +784,8 @@ pub enum SelectMenuAction {
 pub struct SelectMenu {
     cursor: usize,
     scroll: usize,
+    drawn_first_row: Cell<usize>,
+    drawn_hovered_rows: Cell<usize>,
 }
 impl SelectMenu {
     pub fn cursor(&self) -> usize {
@@ -164,6 +804,11 @@ impl SelectMenu {
         self.cursor = cursor;
     }
 
+    pub fn set_drawn_layout(&self, first_row: usize, hovered_rows: usize) {
+        self.drawn_first_row.set(first_row);
+        self.drawn_hovered_rows.set(hovered_rows);
+    }
+
     pub fn on_remove_entry(&mut self, index: usize) {
         if index <= self.cursor {
             self.cursor = self.cursor.saturating_sub(1);
@@ -178,11 +823,49 @@ impl SelectMenu {
     ) -> SelectMenuAction {
         let half_height = available_height / 2;
 
+        // same bindings as in `Output::on_key`
+        match key {
+            Key::Ctrl('e') | Key::ScrollDown => {
+                let lines = match key {
+                    Key::ScrollDown => MOUSE_SCROLL_LINES,
+                    _ => 1,
+                };
+                let max_scroll = entries_len.saturating_sub(available_height);
+                self.scroll = (self.scroll + lines).min(max_scroll);
+                self.cursor = self.cursor.max(self.scroll);
+            }
+            Key::Ctrl('y') | Key::ScrollUp => {
+                let lines = match key {
+                    Key::ScrollUp => MOUSE_SCROLL_LINES,
+                    _ => 1,
+                };
+                self.scroll = self.scroll.saturating_sub(lines);
+                self.cursor = self
+                    .cursor
+                    .min((self.scroll + available_height).saturating_sub(1));
+            }
+            Key::Click(_, row) => {
+                let first_row = self.drawn_first_row.get();
+                if let Some(offset) = (row as usize).checked_sub(first_row) {
+                    let mut index = self.scroll + offset;
+                    // entries below the hovered one are pushed down by its extra rows
+                    if self.cursor >= self.scroll && index > self.cursor {
+                        let extra_rows = self.drawn_hovered_rows.get().saturating_sub(1);
+                        index = index.saturating_sub(extra_rows).max(self.cursor);
+                    }
+                    if index < entries_len {
+                        self.cursor = index;
+                    }
+                }
+            }
+            _ => (),
+        }
+
         self.cursor = match key {
             Key::Down | Key::Ctrl('n') | Key::Char('j') => self.cursor + 1,
             Key::Up | Key::Ctrl('p') | Key::Char('k') => self.cursor.saturating_sub(1),
             Key::Ctrl('h') | Key::Home => 0,
-            Key::Ctrl('e') | Key::End => usize::MAX,
+            Key::End => usize::MAX,
             Key::Ctrl('d') | Key::PageDown => self.cursor + half_height,
             Key::Ctrl('u') | Key::PageUp => self.cursor.saturating_sub(half_height),
             _ => self.cursor,
@@ -203,3 +886,175 @@ impl SelectMenu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn readline(input: &str) -> ReadLine {
+        let mut readline = ReadLine::default();
+        readline.set_input(input);
+        readline
+    }
+
+    fn output(line_count: usize) -> Output {
+        let mut output = Output::default();
+        let text: Vec<_> = (0..line_count).map(|i| i.to_string()).collect();
+        output.set(text.join("\n"));
+        output
+    }
+
+    #[test]
+    fn output_scrolls_line_by_line() {
+        const HEIGHT: usize = 5;
+        let mut output = output(20);
+        assert_eq!(output.line_count(), 20);
+
+        output.on_key(HEIGHT, Key::Ctrl('e'));
+        assert_eq!(output.scroll(HEIGHT), 1);
+        output.on_key(HEIGHT, Key::Ctrl('e'));
+        assert_eq!(output.scroll(HEIGHT), 2);
+        output.on_key(HEIGHT, Key::Ctrl('y'));
+        assert_eq!(output.scroll(HEIGHT), 1);
+        output.on_key(HEIGHT, Key::Ctrl('y'));
+        output.on_key(HEIGHT, Key::Ctrl('y'));
+        assert_eq!(output.scroll(HEIGHT), 0);
+    }
+
+    #[test]
+    fn output_scrolls_half_page() {
+        const HEIGHT: usize = 6;
+        let mut output = output(20);
+
+        output.on_key(HEIGHT, Key::Ctrl('d'));
+        assert_eq!(output.scroll(HEIGHT), 3);
+        output.on_key(HEIGHT, Key::Ctrl('e'));
+        assert_eq!(output.scroll(HEIGHT), 4);
+        for _ in 0..10 {
+            output.on_key(HEIGHT, Key::Ctrl('d'));
+        }
+        assert_eq!(output.scroll(HEIGHT), 20 - HEIGHT);
+        output.on_key(HEIGHT, Key::Ctrl('e'));
+        assert_eq!(output.scroll(HEIGHT), 20 - HEIGHT);
+    }
+
+    #[test]
+    fn output_scroll_keeps_cursor_in_view() {
+        const HEIGHT: usize = 5;
+        let mut output = output(20);
+        output.on_key(HEIGHT, Key::Char('v'));
+        assert_eq!(output.cursor(), Some(0));
+
+        output.on_key(HEIGHT, Key::Ctrl('e'));
+        assert_eq!(output.scroll(HEIGHT), 1);
+        assert_eq!(output.cursor(), Some(1));
+
+        output.on_key(HEIGHT, Key::Char('j'));
+        output.on_key(HEIGHT, Key::Char('j'));
+        output.on_key(HEIGHT, Key::Ctrl('y'));
+        assert_eq!(output.scroll(HEIGHT), 0);
+        assert_eq!(output.cursor(), Some(3));
+
+        output.on_key(HEIGHT, Key::Ctrl('d'));
+        assert_eq!(output.cursor(), Some(5));
+        assert_eq!(output.scroll(HEIGHT), 1);
+    }
+
+    #[test]
+    fn output_scroll_on_short_text() {
+        const HEIGHT: usize = 5;
+        let mut output = output(3);
+        output.on_key(HEIGHT, Key::Ctrl('e'));
+        assert_eq!(output.scroll(HEIGHT), 0);
+        output.on_key(HEIGHT, Key::Ctrl('d'));
+        assert_eq!(output.scroll(HEIGHT), 0);
+    }
+
+    #[test]
+    fn output_pans_by_display_columns() {
+        let mut output = Output::default();
+        output.set("漢".repeat(20));
+        output.on_key(5, Key::ShiftRight);
+        assert_eq!(output.h_scroll(), 0);
+
+        output.set_wrap(false);
+        output.on_key(5, Key::Right);
+        assert_eq!(output.h_scroll(), 0);
+        output.on_key(5, Key::ShiftRight);
+        assert_eq!(output.h_scroll(), 8);
+        for _ in 0..10 {
+            output.on_key(5, Key::ShiftRight);
+        }
+        assert_eq!(output.h_scroll(), 40 - 8);
+        output.on_key(5, Key::ShiftLeft);
+        assert_eq!(output.h_scroll(), 40 - 16);
+    }
+
+    #[test]
+    fn backspace_removes_whole_flag() {
+        let mut readline = readline("a🇧🇷🇧🇷");
+        readline.on_key(Key::Backspace);
+        assert_eq!(readline.input(), "a🇧🇷");
+        readline.on_key(Key::Backspace);
+        assert_eq!(readline.input(), "a");
+    }
+
+    #[test]
+    fn backspace_removes_combining_mark_with_base() {
+        let mut readline = readline("cafe\u{301}");
+        readline.on_key(Key::Backspace);
+        assert_eq!(readline.input(), "caf");
+    }
+
+    #[test]
+    fn backspace_removes_zwj_sequence() {
+        let mut readline = readline("x👩\u{200d}💻");
+        readline.on_key(Key::Backspace);
+        assert_eq!(readline.input(), "x");
+    }
+
+    #[test]
+    fn backspace_removes_non_latin_combining_marks_with_base() {
+        // devanagari virama
+        let mut devanagari = readline("क्ष");
+        devanagari.on_key(Key::Backspace);
+        assert_eq!(devanagari.input(), "क्");
+        devanagari.on_key(Key::Backspace);
+        assert_eq!(devanagari.input(), "");
+
+        // hebrew points, arabic harakat and a thai vowel sign
+        for text in ["שָׁ", "بَ", "กิ"] {
+            let mut readline = readline(&format!("a{}", text));
+            readline.on_key(Key::Backspace);
+            assert_eq!(readline.input(), "a");
+        }
+    }
+
+    #[test]
+    fn ctrl_w_keeps_combining_marks_in_word() {
+        let mut readline = readline("abc cafe\u{301}");
+        readline.on_key(Key::Ctrl('w'));
+        assert_eq!(readline.input(), "abc ");
+    }
+
+    #[test]
+    fn ctrl_w_removes_flags_as_one_class() {
+        let mut readline = readline("abc🇧🇷🇧🇷");
+        readline.on_key(Key::Ctrl('w'));
+        assert_eq!(readline.input(), "abc");
+    }
+
+    #[test]
+    fn grapheme_boundaries() {
+        assert_eq!(previous_grapheme_boundary(""), 0);
+        assert_eq!(previous_grapheme_boundary("e\u{301}"), 0);
+        assert_eq!(previous_grapheme_boundary("ae\u{301}"), 1);
+        assert_eq!(previous_grapheme_boundary("🇧🇷"), 0);
+        // an odd run of regional indicators leaves the first one unpaired
+        assert_eq!(previous_grapheme_boundary("🇧🇷🇧"), "🇧🇷".len());
+        assert!(is_grapheme_extend('\u{301}'));
+        assert!(is_grapheme_extend('\u{94d}'));
+        assert!(!is_grapheme_extend('e'));
+        assert!(!is_grapheme_extend('\u{915}'));
+    }
+}
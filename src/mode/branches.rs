@@ -1,44 +1,60 @@
 use std::thread;
 
 use crate::{
-    backend::{Backend, BackendResult, BranchEntry},
-    mode::{ModeContext, ModeKind, ModeResponse, ModeStatus, Output, ReadLine, SelectMenu},
+    backend::{Backend, BackendResult, BranchDivergence, BranchEntry, Operation},
+    mode::{
+        is_valid_ref_name, Form, ModeContext, ModeKind, ModeResponse, ModeStatus, Output, ReadLine,
+        SelectMenu,
+    },
     platform::Key,
-    ui::{Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
+    ui::{Color, Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
 };
 
 pub enum Response {
     Refresh(BackendResult<Vec<BranchEntry>>),
     Checkout,
+    AlreadyExists,
+    Candidates(Vec<String>),
     Merge,
+    Rebase,
+    Divergence(BackendResult<BranchDivergence>),
 }
 
 enum WaitOperation {
     Refresh,
     New,
     Delete,
+    Rename,
     Merge,
+    Rebase,
 }
 
+#[derive(Default)]
 enum State {
+    #[default]
     Idle,
     Waiting(WaitOperation),
     NewNameInput,
-}
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
+    RenameInput(String),
+    RebaseInput,
+    ConfirmOverwrite,
+    MergeBaseInput,
+    ViewDivergence,
 }
 
 impl SelectEntryDraw for BranchEntry {
-    fn draw(&self, drawer: &mut Drawer, _: bool, _: bool) -> usize {
-        let status = if self.checked_out {
-            " (checked out)"
+    fn draw(&self, drawer: &mut Drawer, hovered: bool, _: bool) -> usize {
+        if self.checked_out {
+            let foreground = drawer.theme.foreground;
+            let color = if hovered {
+                foreground
+            } else {
+                Color::DarkGreen
+            };
+            drawer.fmt(format_args!("{}* {}{}", color, self.name, foreground));
         } else {
-            ""
-        };
-        drawer.fmt(format_args!("{}{}", self.name, status));
+            drawer.fmt(format_args!("  {}", self.name));
+        }
         1
     }
 }
@@ -50,6 +66,11 @@ pub struct Mode {
     output: Output,
     select: SelectMenu,
     readline: ReadLine,
+    candidates: Vec<String>,
+    form: Form,
+    merge_base: Option<String>,
+    // branch to hover once the list is refreshed
+    hover_name: Option<String>,
 }
 impl Mode {
     pub fn on_enter(&mut self, ctx: &ModeContext) {
@@ -65,22 +86,35 @@ impl Mode {
     }
 
     pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
-        let pending_input = matches!(self.state, State::NewNameInput);
+        let pending_input = matches!(
+            self.state,
+            State::NewNameInput
+                | State::RenameInput(_)
+                | State::RebaseInput
+                | State::ConfirmOverwrite
+                | State::MergeBaseInput
+        );
         let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
 
-        match self.state {
+        match &self.state {
             State::Idle | State::Waiting(_) => {
                 if self.output.text().is_empty() {
                     self.select
                         .on_key(self.entries.len(), available_height, key);
+                } else if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
                 } else {
                     self.output.on_key(available_height, key);
                 }
 
+                let key = ctx.key_bindings.translate("branches", key);
                 match key {
+                    Key::Enter if !self.entries.is_empty() => self.output.set(String::new()),
                     Key::Char('g') => {
                         let index = self.select.cursor();
-                        if let Some(entry) = self.entries.get(index) {
+                        if let Some(entry) = self.entries.get(index).filter(|e| !e.checked_out) {
                             let name = entry.name.clone();
                             let ctx = ctx.clone();
                             thread::spawn(move || {
@@ -104,6 +138,22 @@ impl Mode {
                         self.output.set(String::new());
                         self.readline.clear();
                     }
+                    Key::Char('r') => {
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            self.state = State::RenameInput(entry.name.clone());
+                            self.output.set(String::new());
+                            self.readline.set_input(&entry.name);
+                        }
+                    }
+                    Key::Char('R') => {
+                        self.state = State::RebaseInput;
+                        self.output.set(String::new());
+                        match self.entries.get(self.select.cursor()) {
+                            Some(entry) => self.readline.set_input(&entry.name),
+                            None => self.readline.clear(),
+                        }
+                        request_candidates(ctx);
+                    }
                     Key::Char('D') => {
                         let index = self.select.cursor();
                         if let Some(entry) = self.entries.get(index) {
@@ -115,6 +165,35 @@ impl Mode {
                             request(ctx, move |b| b.delete_branch(&name));
                         }
                     }
+                    Key::Char('c') => {
+                        let index = self.select.cursor();
+                        if let Some(entry) = self.entries.get(index) {
+                            self.state = State::ViewDivergence;
+                            self.output.set(String::new());
+                            self.merge_base = None;
+
+                            let base = entry.name.clone();
+                            let ctx = ctx.clone();
+                            thread::spawn(move || {
+                                let result = ctx.backend.branch_divergence(&base);
+                                ctx.event_sender.send_response(ModeResponse::Branches(
+                                    Response::Divergence(result),
+                                ));
+                            });
+                        }
+                    }
+                    Key::Char('M') => {
+                        self.state = State::MergeBaseInput;
+                        self.output.set(String::new());
+                        self.form = Form::new(&["first", "second"]);
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            self.form.set_input(0, &entry.name);
+                        }
+                        if let Some(entry) = self.entries.iter().find(|e| e.checked_out) {
+                            self.form.set_input(1, &entry.name);
+                        }
+                        request_candidates(ctx);
+                    }
                     Key::Char('m') => {
                         let index = self.select.cursor();
                         if let Some(entry) = self.entries.get(index) {
@@ -142,22 +221,141 @@ impl Mode {
             }
             State::NewNameInput => {
                 self.readline.on_key(key);
-                if key.is_submit() {
+                if key.is_submit() && is_valid_ref_name(self.readline.input()) {
                     self.state = State::Waiting(WaitOperation::New);
 
                     let name = self.readline.input().to_string();
-                    request(ctx, move |b| b.new_branch(&name));
+                    let ctx = ctx.clone();
+                    thread::spawn(move || match ctx.backend.branch_exists(&name) {
+                        Ok(true) => ctx
+                            .event_sender
+                            .send_response(ModeResponse::Branches(Response::AlreadyExists)),
+                        Ok(false) => request(&ctx, move |b| b.new_branch(&name, None, false)),
+                        Err(error) => ctx
+                            .event_sender
+                            .send_response(ModeResponse::Branches(Response::Refresh(Err(error)))),
+                    });
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::RenameInput(old_name) => {
+                self.readline.on_key(key);
+                if key.is_submit() && is_valid_ref_name(self.readline.input()) {
+                    let old_name = old_name.clone();
+                    self.state = State::Waiting(WaitOperation::Rename);
+
+                    let new_name = self.readline.input().to_string();
+                    self.hover_name = Some(new_name.clone());
+                    request(ctx, move |b| b.rename_branch(&old_name, &new_name));
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::RebaseInput => {
+                match key {
+                    Key::Tab => self.readline.complete(&self.candidates),
+                    _ => self.readline.on_key(key),
+                }
+                if key.is_submit() && !self.readline.input().is_empty() {
+                    self.state = State::Waiting(WaitOperation::Rebase);
+
+                    let onto = self.readline.input().to_string();
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        let result = ctx.backend.rebase(&onto);
+                        let operation = ctx.backend.in_progress_operation().unwrap_or(None);
+                        let in_progress = matches!(operation, Some(Operation::Rebase));
+                        ctx.event_sender.send_operation_change(operation);
+                        match result {
+                            Ok(()) => {
+                                ctx.event_sender.send_mode_change(ModeKind::Log);
+                                ctx.event_sender
+                                    .send_response(ModeResponse::Branches(Response::Rebase));
+                                ctx.event_sender.send_mode_refresh(ModeKind::Log);
+                            }
+                            Err(mut error) => {
+                                if in_progress {
+                                    error.push_str(
+                                        "\n\ncontinue or abort the rebase from status mode",
+                                    );
+                                }
+                                ctx.event_sender.send_response(ModeResponse::Branches(
+                                    Response::Refresh(Err(error)),
+                                ));
+                            }
+                        }
+                    });
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::MergeBaseInput => {
+                match key {
+                    Key::Tab => self.form.complete(&self.candidates),
+                    _ => self.form.on_key(key),
+                }
+                if key.is_submit()
+                    && !self.form.input(0).is_empty()
+                    && !self.form.input(1).is_empty()
+                {
+                    self.state = State::ViewDivergence;
+                    self.output.set(String::new());
+                    self.merge_base = None;
+
+                    let head = self.form.input(0).to_string();
+                    let base = self.form.input(1).to_string();
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        let result = ctx.backend.divergence(&head, &base);
+                        ctx.event_sender
+                            .send_response(ModeResponse::Branches(Response::Divergence(result)));
+                    });
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::ViewDivergence => {
+                if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                }
+                self.output.on_key(available_height, key);
+                if let (Key::Char('d'), Some(merge_base)) = (key, &self.merge_base) {
+                    ctx.event_sender
+                        .send_mode_change(ModeKind::RevisionDetails(merge_base.clone()));
                 }
             }
+            State::ConfirmOverwrite => match key {
+                Key::Char('y') => {
+                    self.state = State::Waiting(WaitOperation::New);
+
+                    let name = self.readline.input().to_string();
+                    request(ctx, move |b| b.new_branch(&name, None, true));
+                }
+                Key::Char('n') => self.on_enter(ctx),
+                _ if key.is_cancel() => self.on_enter(ctx),
+                _ => (),
+            },
         }
 
         ModeStatus { pending_input }
     }
 
+    pub fn on_paste(&mut self, text: &str) {
+        match self.state {
+            State::NewNameInput | State::RenameInput(_) | State::RebaseInput => {
+                self.readline.paste(text)
+            }
+            State::MergeBaseInput => self.form.paste(text),
+            _ => (),
+        }
+    }
+
     pub fn on_response(&mut self, response: Response) {
         match response {
             Response::Refresh(result) => {
-                self.entries = Vec::new();
                 self.output.set(String::new());
 
                 if let State::Waiting(_) = self.state {
@@ -170,20 +368,67 @@ impl Mode {
                     }
                 }
 
-                if let Some(i) = self.entries.iter().position(|e| e.checked_out) {
+                let hovered = match self.hover_name.take() {
+                    Some(name) => self.entries.iter().position(|e| e.name == name),
+                    None => None,
+                };
+                if let Some(i) = hovered.or_else(|| self.entries.iter().position(|e| e.checked_out))
+                {
                     self.select.set_cursor(i);
                 } else {
                     self.select.saturate_cursor(self.entries.len());
                 }
             }
-            Response::Checkout | Response::Merge => self.state = State::Idle,
+            Response::Checkout | Response::Merge | Response::Rebase => self.state = State::Idle,
+            Response::Divergence(result) => {
+                if let State::ViewDivergence = self.state {
+                    match result {
+                        Ok(divergence) => {
+                            self.output.set(divergence.to_string());
+                            self.merge_base = Some(divergence.merge_base);
+                        }
+                        Err(error) => self.output.set(error),
+                    }
+                }
+            }
+            Response::Candidates(candidates) => self.candidates = candidates,
+            Response::AlreadyExists => {
+                if let State::Waiting(_) = self.state {
+                    self.state = State::ConfirmOverwrite;
+                }
+            }
         }
     }
 
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
     pub fn is_waiting_response(&self) -> bool {
         match self.state {
-            State::Idle | State::NewNameInput => false,
+            State::Idle
+            | State::NewNameInput
+            | State::RenameInput(_)
+            | State::RebaseInput
+            | State::ConfirmOverwrite
+            | State::MergeBaseInput => false,
             State::Waiting(_) => true,
+            State::ViewDivergence => self.output.text().is_empty(),
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        match self.state {
+            State::Idle | State::Waiting(_) if self.output.text().is_empty() => self
+                .entries
+                .get(self.select.cursor())
+                .map(|entry| entry.name.clone()),
+            _ => Some(self.output.text().into()),
         }
     }
 
@@ -192,17 +437,33 @@ impl Mode {
             State::Idle | State::Waiting(WaitOperation::Refresh) => "branches",
             State::Waiting(WaitOperation::New) => "new branch",
             State::Waiting(WaitOperation::Delete) => "delete branch",
+            State::Waiting(WaitOperation::Rename) | State::RenameInput(_) => "rename branch",
             State::Waiting(WaitOperation::Merge) => "merge branch",
+            State::Waiting(WaitOperation::Rebase) | State::RebaseInput => "rebase",
             State::NewNameInput => "new branch name",
+            State::ConfirmOverwrite => "overwrite branch",
+            State::MergeBaseInput => "merge base",
+            State::ViewDivergence => "compare",
         };
         let (left_help, right_help) = match self.state {
-            State::Idle | State::Waiting(_) => {
-                ("[g]checkout [n]new [D]delete [m]merge", "[arrows]move")
-            }
-            State::NewNameInput => (
+            State::Idle | State::Waiting(_) => (
+                "[g]checkout [n]new [r]rename [D]delete [m]merge [R]rebase onto [c]compare [M]merge base",
+                "[arrows]move",
+            ),
+            State::NewNameInput | State::RenameInput(_) => (
                 "",
                 "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
             ),
+            State::RebaseInput => (
+                "[tab]complete",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::ConfirmOverwrite => ("[y]overwrite", "[n]cancel"),
+            State::MergeBaseInput => (
+                "[tab]complete [up/down]change field",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::ViewDivergence => ("[d]merge base details", "[arrows]move"),
         };
         (name, left_help, right_help)
     }
@@ -214,13 +475,50 @@ impl Mode {
                     drawer.select_menu(&self.select, 0, false, self.entries.iter());
                 } else {
                     drawer.output(&self.output);
+                    if !self.entries.is_empty() {
+                        drawer.back_hint();
+                    }
                 }
             }
-            State::NewNameInput => drawer.readline(&self.readline, "type in the branch name..."),
+            State::NewNameInput => drawer.validated_readline(
+                &self.readline,
+                "type in the branch name...",
+                self.readline.input().is_empty() || is_valid_ref_name(self.readline.input()),
+            ),
+            State::RenameInput(_) => drawer.validated_readline(
+                &self.readline,
+                "type in the new branch name...",
+                self.readline.input().is_empty() || is_valid_ref_name(self.readline.input()),
+            ),
+            State::RebaseInput => drawer.readline(
+                &self.readline,
+                "type in the branch or revision to rebase onto...",
+            ),
+            State::ConfirmOverwrite => drawer.fmt(format_args!(
+                "branch '{}' already exists. overwrite it?",
+                self.readline.input()
+            )),
+            State::MergeBaseInput => drawer.form(
+                &self.form,
+                &["type in a revision...", "type in another revision..."],
+            ),
+            State::ViewDivergence => {
+                drawer.output(&self.output);
+            }
         }
     }
 }
 
+// revisions the rebase and merge base prompts complete against
+fn request_candidates(ctx: &ModeContext) {
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        let candidates = ctx.backend.ref_candidates().unwrap_or_default();
+        ctx.event_sender
+            .send_response(ModeResponse::Branches(Response::Candidates(candidates)));
+    });
+}
+
 fn request<F>(ctx: &ModeContext, f: F)
 where
     F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<()>,
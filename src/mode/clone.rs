@@ -0,0 +1,231 @@
+use std::{env, thread};
+
+use crate::{
+    backend::{self, BackendResult},
+    mode::{
+        path_candidates, Form, ModeContext, ModeKind, ModeResponse, ModeStatus, Output, SelectMenu,
+    },
+    platform::Key,
+    ui::{Drawer, RESERVED_LINES_COUNT},
+};
+
+const URL_FIELD: usize = 0;
+const DIRECTORY_FIELD: usize = 1;
+
+pub enum Response {
+    Progress(String),
+    Done(BackendResult<()>),
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Waiting,
+    CloneInput,
+    InitSelect,
+}
+
+#[derive(Default)]
+pub struct Mode {
+    state: State,
+    output: Output,
+    form: Form,
+    programs: Vec<&'static str>,
+    select: SelectMenu,
+}
+impl Mode {
+    pub fn on_enter(&mut self, _ctx: &ModeContext) {
+        if let State::Waiting = self.state {
+            return;
+        }
+        self.state = State::CloneInput;
+
+        self.output.set(String::new());
+        self.form = Form::new(&["url", "directory"]);
+    }
+
+    pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
+        let pending_input = matches!(self.state, State::CloneInput | State::InitSelect);
+        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+
+        match self.state {
+            State::Idle | State::Waiting => {
+                if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                }
+                self.output.on_key(available_height, key);
+                match (&self.state, key) {
+                    (State::Idle, Key::Char('n')) => self.on_enter(ctx),
+                    (State::Idle, Key::Char('i')) => {
+                        self.state = State::InitSelect;
+                        self.programs = backend::INIT_PROGRAMS
+                            .iter()
+                            .copied()
+                            .filter(|p| backend::is_program_available(p))
+                            .collect();
+                        self.select.set_cursor(0);
+                    }
+                    _ => (),
+                }
+            }
+            State::InitSelect => {
+                self.select
+                    .on_key(self.programs.len(), available_height, key);
+                if key.is_submit() {
+                    let program = self.programs.get(self.select.cursor());
+                    if let Some(backend) = program.and_then(|p| backend::backend_from_program(p)) {
+                        self.state = State::Waiting;
+                        self.output.set(String::new());
+
+                        let ctx = ctx.clone();
+                        thread::spawn(move || match backend.init(".") {
+                            Ok(()) => {
+                                ctx.event_sender.send_backend_change(backend);
+                                ctx.event_sender
+                                    .send_response(ModeResponse::Clone(Response::Done(Ok(()))));
+                                ctx.event_sender.send_mode_change(ModeKind::Status);
+                            }
+                            Err(error) => ctx
+                                .event_sender
+                                .send_response(ModeResponse::Clone(Response::Done(Err(error)))),
+                        });
+                    }
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+            }
+            State::CloneInput => {
+                if let (Key::Tab, DIRECTORY_FIELD) = (key, self.form.focused()) {
+                    let candidates = path_candidates(self.form.input(DIRECTORY_FIELD));
+                    self.form.complete(&candidates);
+                } else {
+                    self.form.on_key(key);
+                }
+                if key.is_submit() && !self.form.input(URL_FIELD).is_empty() {
+                    self.state = State::Waiting;
+                    self.output.set(String::new());
+
+                    let url = self.form.input(URL_FIELD).to_string();
+                    let directory = match self.form.input(DIRECTORY_FIELD) {
+                        "" => directory_from_url(&url).to_string(),
+                        directory => directory.to_string(),
+                    };
+
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        let event_sender = ctx.event_sender.clone();
+                        let result = ctx
+                            .backend
+                            .clone_repository(&url, &directory, &mut |line| {
+                                event_sender.send_response(ModeResponse::Clone(Response::Progress(
+                                    line.into(),
+                                )))
+                            })
+                            .and_then(|_| {
+                                env::set_current_dir(&directory).map_err(|error| {
+                                    format!("could not open '{}': {}", directory, error)
+                                })
+                            });
+
+                        let success = result.is_ok();
+                        ctx.event_sender
+                            .send_response(ModeResponse::Clone(Response::Done(result)));
+                        if success {
+                            ctx.event_sender.send_mode_change(ModeKind::Status);
+                        }
+                    });
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+            }
+        }
+
+        ModeStatus { pending_input }
+    }
+
+    pub fn on_paste(&mut self, text: &str) {
+        if let State::CloneInput = self.state {
+            self.form.paste(text);
+        }
+    }
+
+    pub fn on_response(&mut self, response: Response) {
+        match response {
+            Response::Progress(line) => {
+                if let State::Waiting = self.state {
+                    self.output.set(line);
+                }
+            }
+            Response::Done(result) => {
+                self.state = State::Idle;
+                match result {
+                    Ok(()) => self.output.set(String::new()),
+                    Err(error) => self.output.set(error),
+                }
+            }
+        }
+    }
+
+    pub fn is_following_output(&self) -> bool {
+        self.output.is_following()
+    }
+
+    pub fn is_waiting_response(&self) -> bool {
+        match self.state {
+            State::Idle | State::CloneInput | State::InitSelect => false,
+            State::Waiting => true,
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        Some(self.output.text().into())
+    }
+
+    pub fn header(&self) -> (&str, &str, &str) {
+        match self.state {
+            State::Idle => ("no repository", "[n]clone [i]init", "[arrows]move"),
+            State::Waiting => ("working", "", ""),
+            State::CloneInput => (
+                "clone",
+                "[tab]next field/complete path [up/down]change field",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::InitSelect => ("init", "", "[arrows]move [enter]submit [esc]cancel"),
+        }
+    }
+
+    pub fn draw(&self, drawer: &mut Drawer) {
+        match self.state {
+            State::Idle | State::Waiting => {
+                if self.output.text().is_empty() {
+                    drawer
+                        .str("no repository found. press [n] to clone one or [i] to init one here");
+                } else {
+                    drawer.output(&self.output);
+                }
+            }
+            State::CloneInput => drawer.form(
+                &self.form,
+                &[
+                    "type in the repository url...",
+                    directory_from_url(self.form.input(URL_FIELD)),
+                ],
+            ),
+            State::InitSelect => {
+                drawer.select_menu(&self.select, 0, false, self.programs.iter());
+            }
+        }
+    }
+}
+
+fn directory_from_url(url: &str) -> &str {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(&['/', '\\', ':'][..])
+        .next()
+        .unwrap_or(url);
+    name.strip_suffix(".git").unwrap_or(name)
+}
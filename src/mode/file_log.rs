@@ -0,0 +1,303 @@
+use std::thread;
+
+use crate::{
+    backend::{BackendResult, FileLogEntry, FileStatus, RevisionEntry},
+    date,
+    mode::{log::EntryView, ModeContext, ModeResponse, ModeStatus, Output, OutputKind, SelectMenu},
+    platform::Key,
+    ui::{Color, Drawer, RESERVED_LINES_COUNT},
+};
+
+pub enum Response {
+    Refresh(String, BackendResult<Vec<FileLogEntry>>),
+    Diff(String),
+}
+
+enum WaitOperation {
+    Refresh,
+    LoadMore,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Waiting(WaitOperation),
+    ViewDiff,
+}
+
+pub struct Mode {
+    state: State,
+    path: String,
+    entries: Vec<FileLogEntry>,
+    output: Output,
+    select: SelectMenu,
+    show_full_hovered_message: bool,
+    absolute_dates: bool,
+    follow: bool,
+    requested_count: usize,
+}
+impl Default for Mode {
+    fn default() -> Self {
+        Self {
+            state: State::default(),
+            path: String::new(),
+            entries: Vec::new(),
+            output: Output::default(),
+            select: SelectMenu::default(),
+            show_full_hovered_message: false,
+            absolute_dates: false,
+            follow: true,
+            requested_count: 0,
+        }
+    }
+}
+impl Mode {
+    pub fn on_enter(&mut self, ctx: &ModeContext, path: &str) {
+        if self.path != path {
+            self.path = path.into();
+            self.entries.clear();
+            self.select.set_cursor(0);
+        }
+
+        if let State::Waiting(_) = self.state {
+            return;
+        }
+        self.state = State::Waiting(WaitOperation::Refresh);
+
+        self.output.set(String::new());
+        self.show_full_hovered_message = false;
+
+        self.request(ctx, load_count(ctx).max(self.entries.len()));
+    }
+
+    fn request(&mut self, ctx: &ModeContext, len: usize) {
+        self.requested_count = len;
+
+        let path = self.path.clone();
+        let follow = self.follow;
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = ctx.backend.file_log(&path, len, follow);
+            ctx.event_sender
+                .send_response(ModeResponse::FileLog(Response::Refresh(path, result)));
+        });
+    }
+
+    fn format_dates(&mut self) {
+        let now = date::now();
+        for FileLogEntry { entry, .. } in &mut self.entries {
+            entry.date = if self.absolute_dates {
+                date::format_absolute(entry.timestamp)
+            } else {
+                date::format_relative(entry.timestamp, now)
+            };
+        }
+    }
+
+    pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
+        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+
+        if matches!(self.state, State::ViewDiff) || !self.output.text().is_empty() {
+            if self.output.on_search_key(available_height, key) {
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            self.output.on_key(available_height, key);
+            if let Key::Enter = key {
+                if !self.entries.is_empty() {
+                    if let State::ViewDiff = self.state {
+                        self.state = State::Idle;
+                    }
+                    self.output.set(String::new());
+                }
+            }
+            return ModeStatus {
+                pending_input: false,
+            };
+        }
+
+        let previous_cursor = self.select.cursor();
+        self.select
+            .on_key(self.entries.len(), available_height, key);
+
+        let cursor = self.select.cursor();
+        let has_more = self.entries.len() >= self.requested_count;
+        if cursor != previous_cursor
+            && cursor + available_height / 2 + 1 >= self.entries.len()
+            && has_more
+        {
+            if let State::Idle = self.state {
+                self.state = State::Waiting(WaitOperation::LoadMore);
+                self.request(ctx, self.entries.len() + load_count(ctx));
+            }
+        }
+
+        let key = ctx.key_bindings.translate("file_log", key);
+        match key {
+            Key::Tab => self.show_full_hovered_message = !self.show_full_hovered_message,
+            Key::Char('r') => {
+                self.absolute_dates = !self.absolute_dates;
+                self.format_dates();
+            }
+            Key::Char('F') => {
+                if let State::Idle = self.state {
+                    self.follow = !self.follow;
+                    self.state = State::Waiting(WaitOperation::Refresh);
+                    self.request(ctx, load_count(ctx));
+                }
+            }
+            Key::Enter | Key::Char('d') => {
+                if let Some(FileLogEntry { entry, path }) = self.entries.get(self.select.cursor()) {
+                    self.state = State::ViewDiff;
+                    self.output.set(String::new());
+
+                    let revision = entry.hash.clone();
+                    let entries = [RevisionEntry {
+                        name: path.clone(),
+                        status: FileStatus::Modified,
+                    }];
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        let output = match ctx.backend.diff(Some(&revision), &entries) {
+                            Ok(output) => output,
+                            Err(error) => error,
+                        };
+                        ctx.event_sender
+                            .send_response(ModeResponse::FileLog(Response::Diff(output)));
+                    });
+                }
+            }
+            _ => (),
+        }
+
+        ModeStatus {
+            pending_input: false,
+        }
+    }
+
+    pub fn on_response(&mut self, response: Response) {
+        match response {
+            Response::Refresh(path, result) => {
+                if path != self.path {
+                    return;
+                }
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Idle;
+                }
+                match result {
+                    Ok(entries) => {
+                        self.entries = entries;
+                        if self.entries.is_empty() {
+                            self.output.set(format!("no history for {}\n", self.path));
+                        }
+                    }
+                    Err(error) => self.output.set(error),
+                }
+
+                self.format_dates();
+                self.select.saturate_cursor(self.entries.len());
+            }
+            Response::Diff(mut output) => {
+                if let State::ViewDiff = self.state {
+                    if output.is_empty() {
+                        output.push('\n');
+                    }
+                    self.output.set(output);
+                    self.output.set_kind(OutputKind::Diff);
+                }
+            }
+        }
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
+    pub fn is_waiting_response(&self) -> bool {
+        match self.state {
+            State::Idle => false,
+            State::Waiting(_) => true,
+            State::ViewDiff => self.output.text().is_empty(),
+        }
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|e| e.entry.hash.clone())
+        }
+    }
+
+    pub fn header(&self) -> (&str, &str, &str) {
+        let name = match self.state {
+            State::Idle | State::Waiting(WaitOperation::Refresh) => "file log",
+            State::Waiting(WaitOperation::LoadMore) => "load more",
+            State::ViewDiff => "diff",
+        };
+        let (left_help, right_help) = match self.state {
+            State::Idle | State::Waiting(_) => (
+                "[d]diff [F]follow renames",
+                "[arrows]move [tab]full message [r]relative/absolute dates",
+            ),
+            State::ViewDiff => (
+                "",
+                "[arrows]move [v]cursor line [z]center [W]wrap [shift+arrows]pan",
+            ),
+        };
+        (name, left_help, right_help)
+    }
+
+    pub fn draw(&self, drawer: &mut Drawer) {
+        if !self.output.text().is_empty() {
+            drawer.output(&self.output);
+            if let State::Idle = self.state {
+                if !self.entries.is_empty() {
+                    drawer.back_hint();
+                }
+            }
+            return;
+        }
+
+        let foreground = drawer.theme.foreground;
+        let follow = if self.follow {
+            " (following renames)"
+        } else {
+            ""
+        };
+        drawer.fmt(format_args!(
+            "{}{}{}{}",
+            self.path,
+            Color::DarkGray,
+            follow,
+            foreground
+        ));
+        drawer.next_line();
+        drawer.select_menu(
+            &self.select,
+            1,
+            self.show_full_hovered_message,
+            self.entries.iter().map(|e| EntryView {
+                entry: &e.entry,
+                compact: false,
+            }),
+        );
+    }
+}
+
+fn load_count(ctx: &ModeContext) -> usize {
+    (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT)
+}
@@ -1,66 +1,136 @@
-use std::thread;
+use std::{thread, time::Duration};
 
 use crate::{
-    backend::{Backend, BackendResult, LogEntry},
-    mode::{ModeContext, ModeKind, ModeResponse, ModeStatus, Output, SelectMenu},
+    application::EventSender,
+    backend::{
+        Backend, BackendResult, LogEntry, LogFilter, LogOrder, Operation, Phase, PushOptions,
+        ResetMode,
+    },
+    date,
+    mode::{
+        self, is_valid_ref_name, Form, ModeContext, ModeKind, ModeResponse, ModeStatus, Output,
+        OutputKind, ReadLine, SelectMenu,
+    },
     platform::Key,
     ui::{Color, Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
 };
 
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    pub count: u32,
+    pub delay: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            delay: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct LogQuery {
+    order: LogOrder,
+    // draws the branch and merge structure next to the entries when set
+    graph: bool,
+    filter: LogFilter,
+}
+impl Default for LogQuery {
+    fn default() -> Self {
+        Self {
+            order: LogOrder::default(),
+            graph: true,
+            filter: LogFilter::default(),
+        }
+    }
+}
+
 pub enum Response {
     Refresh(BackendResult<Vec<LogEntry>>),
+    LoadMore(BackendResult<Vec<LogEntry>>),
+    Progress(String),
+    ContainingRefs(String),
+    Show(String),
+    Diagnostics(String),
+    PushOptions(BackendResult<(PushOptions, Vec<String>)>),
 }
 
 enum WaitOperation {
     Refresh,
+    LoadMore,
     Checkout,
     Merge,
     Fetch,
     Pull,
     Push,
+    PushOptions,
+    Sync,
+    Revert,
+    Reset,
+    CherryPick,
+    NewBranch,
 }
 
+#[derive(Default)]
 enum State {
+    #[default]
     Idle,
     Waiting(WaitOperation),
-}
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
+    ViewContainingRefs,
+    ViewShow,
+    RevertPrompt(String),
+    ResetPrompt(String),
+    ConfirmHardReset(String),
+    NewBranchInput(String),
+    GrepInput,
+    DateRangeInput,
+    PushRemoteSelect(PushOptions),
+    PushBranchInput(PushOptions),
+    ConfirmPush(PushOptions),
 }
 
-impl SelectEntryDraw for LogEntry {
+pub struct EntryView<'a> {
+    pub entry: &'a LogEntry,
+    pub compact: bool,
+}
+impl SelectEntryDraw for EntryView<'_> {
     fn draw(&self, drawer: &mut Drawer, hovered: bool, full: bool) -> usize {
-        fn color(color: Color, hovered: bool) -> Color {
-            if hovered {
-                Color::White
-            } else {
-                color
-            }
-        }
+        let foreground = drawer.theme.foreground;
+        let color = |color, hovered| if hovered { foreground } else { color };
+
+        let entry = self.entry;
+        let expanded = !self.compact || hovered;
 
-        let author = match self.author.char_indices().nth(12) {
-            Some((i, _)) => &self.author[..i],
-            None => &self.author,
+        let author = match entry.author.char_indices().nth(12) {
+            Some((i, _)) => &entry.author[..i],
+            None => &entry.author,
         };
 
-        let mut total_chars = self.graph.chars().count()
-            + 1
-            + self.hash.chars().count()
-            + 1
-            + self.date.chars().count()
-            + 1
-            + author.chars().count()
-            + 1;
-
-        if !self.refs.is_empty() {
-            total_chars += self.refs.chars().count() + 3;
+        let refs_too_long_suffix = drawer.glyphs.ellipsis;
+
+        let mut total_chars = entry.graph.chars().count() + 1 + entry.hash.chars().count() + 1;
+        let mut refs_suffix = "";
+        let mut refs = &entry.refs[..];
+        if expanded {
+            total_chars += entry.date.chars().count() + 1 + author.chars().count() + 1;
+            if let Some(phase) = entry.phase {
+                total_chars += phase.as_str().len() + 1;
+            }
+            if !refs.is_empty() {
+                let available_width = (drawer.viewport_size.0 as usize)
+                    .saturating_sub(total_chars + 3 + refs_too_long_suffix.chars().count());
+                if let Some((i, _)) = refs.char_indices().nth(available_width) {
+                    refs = &refs[..i];
+                    refs_suffix = refs_too_long_suffix;
+                }
+                total_chars += refs.chars().count() + refs_suffix.chars().count() + 3;
+            }
         }
 
         let (line_count, message) = if full {
             let mut line_count = 0;
-            for line in self.message.lines() {
+            for line in entry.message.lines() {
                 let mut x = 0;
                 for _ in line.chars() {
                     if x >= drawer.viewport_size.0 as _ {
@@ -71,10 +141,10 @@ impl SelectEntryDraw for LogEntry {
 
                 line_count += 1;
             }
-            (line_count, &self.message[..])
+            (line_count, &entry.message[..])
         } else {
             let available_width = (drawer.viewport_size.0 as usize).saturating_sub(total_chars);
-            let message = match self
+            let message = match entry
                 .message
                 .lines()
                 .next()
@@ -82,34 +152,51 @@ impl SelectEntryDraw for LogEntry {
                 .char_indices()
                 .nth(available_width)
             {
-                Some((i, _)) => &self.message[..i],
-                None => &self.message,
+                Some((i, _)) => &entry.message[..i],
+                None => &entry.message,
             };
             (0, message)
         };
 
-        let (refs_begin, refs_end) = match &self.refs[..] {
-            "" => ("", ""),
-            _ => ("(", ") "),
-        };
-
+        drawer.fmt(format_args!("{}", foreground));
+        drawer.graph(&entry.graph);
         drawer.fmt(format_args!(
-            "{}{} {}{} {}{} {}{} {}{}{}{}{}",
-            color(Color::White, hovered),
-            &self.graph,
+            " {}{} ",
             color(Color::DarkYellow, hovered),
-            &self.hash,
-            color(Color::DarkBlue, hovered),
-            &self.date,
-            color(Color::DarkGreen, hovered),
-            author,
-            color(Color::DarkRed, hovered),
-            refs_begin,
-            &self.refs,
-            refs_end,
-            color(Color::White, hovered),
+            &entry.hash,
         ));
 
+        if expanded {
+            let (refs_begin, refs_end) = match refs {
+                "" => ("", ""),
+                _ => ("(", ") "),
+            };
+
+            let (phase_color, phase) = match entry.phase {
+                Some(Phase::Public) => (Color::DarkGray, "public "),
+                Some(Phase::Draft) => (Color::DarkMagenta, "draft "),
+                Some(Phase::Secret) => (Color::DarkRed, "secret "),
+                None => (foreground, ""),
+            };
+
+            drawer.fmt(format_args!(
+                "{}{} {}{} {}{}{}{}{}{}{}",
+                color(Color::DarkBlue, hovered),
+                &entry.date,
+                color(Color::DarkGreen, hovered),
+                author,
+                color(phase_color, hovered),
+                phase,
+                color(Color::DarkRed, hovered),
+                refs_begin,
+                refs,
+                refs_suffix,
+                refs_end,
+            ));
+        }
+
+        drawer.fmt(format_args!("{}", foreground));
+
         if full {
             drawer.next_line();
         }
@@ -134,8 +221,129 @@ pub struct Mode {
     output: Output,
     select: SelectMenu,
     show_full_hovered_message: bool,
+    absolute_dates: bool,
+    compact: bool,
+    default_count: Option<usize>,
+    has_more: bool,
+    query: LogQuery,
+    retry: RetryPolicy,
+    readline: ReadLine,
+    form: Form,
+    remotes: Vec<String>,
+    remote_select: SelectMenu,
 }
 impl Mode {
+    pub fn new(default_count: Option<usize>, retry: RetryPolicy) -> Self {
+        Self {
+            default_count,
+            retry,
+            ..Default::default()
+        }
+    }
+
+    fn load_count(&self, ctx: &ModeContext) -> usize {
+        match self.default_count {
+            Some(count) => count,
+            None => (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT),
+        }
+    }
+
+    fn refresh_count(&self, ctx: &ModeContext) -> usize {
+        self.load_count(ctx).max(self.entries.len())
+    }
+
+    fn load_more(&mut self, ctx: &ModeContext) {
+        if !self.has_more {
+            return;
+        }
+        self.state = State::Waiting(WaitOperation::LoadMore);
+
+        let start = self.entries.len();
+        let len = self.load_count(ctx);
+        let query = self.query.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = ctx
+                .backend
+                .log(start, len, query.order, query.graph, &query.filter);
+            ctx.event_sender
+                .send_response(ModeResponse::Log(Response::LoadMore(result)));
+        });
+    }
+
+    fn reset(&mut self, ctx: &ModeContext, revision: String, mode: ResetMode) {
+        self.state = State::Waiting(WaitOperation::Reset);
+        request(ctx, self.refresh_count(ctx), self.query.clone(), move |b| {
+            b.reset(&revision, mode)
+        });
+    }
+
+    fn cherry_pick(&mut self, ctx: &ModeContext, revisions: Vec<String>) {
+        self.state = State::Waiting(WaitOperation::CherryPick);
+
+        let len = self.refresh_count(ctx);
+        let query = self.query.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            use std::ops::Deref;
+
+            let result = ctx.backend.cherry_pick(&revisions);
+            let has_conflicts = result.is_err() && mode::has_conflicts(ctx.backend.deref());
+
+            let result = result.and_then(|_| {
+                ctx.backend
+                    .log(0, len, query.order, query.graph, &query.filter)
+            });
+            let operation = ctx.backend.in_progress_operation().unwrap_or(None);
+            ctx.event_sender.send_operation_change(operation);
+            ctx.event_sender
+                .send_response(ModeResponse::Log(Response::Refresh(result)));
+            if has_conflicts {
+                ctx.event_sender.send_mode_change(ModeKind::Status);
+            }
+        });
+    }
+
+    fn revert(&mut self, ctx: &ModeContext, revision: String, no_commit: bool) {
+        self.state = State::Waiting(WaitOperation::Revert);
+
+        let len = self.refresh_count(ctx);
+        let query = self.query.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            use std::ops::Deref;
+
+            let result = ctx.backend.revert_commit(&revision, no_commit);
+            let has_conflicts = result.is_err() && mode::has_conflicts(ctx.backend.deref());
+            let show_status = (result.is_ok() && no_commit) || has_conflicts;
+
+            let result = result.and_then(|_| {
+                ctx.backend
+                    .log(0, len, query.order, query.graph, &query.filter)
+            });
+            ctx.event_sender
+                .send_response(ModeResponse::Log(Response::Refresh(result)));
+            if show_status {
+                ctx.event_sender.send_mode_change(ModeKind::Status);
+            }
+        });
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn format_dates(&mut self) {
+        let now = date::now();
+        for entry in &mut self.entries {
+            entry.date = if self.absolute_dates {
+                date::format_absolute(entry.timestamp)
+            } else {
+                date::format_relative(entry.timestamp, now)
+            };
+        }
+    }
+
     pub fn on_enter(&mut self, ctx: &ModeContext) {
         if let State::Waiting(_) = self.state {
             return;
@@ -145,14 +353,184 @@ impl Mode {
         self.output.set(String::new());
         self.show_full_hovered_message = false;
 
-        request(ctx, |_| Ok(()));
+        request(ctx, self.load_count(ctx), self.query.clone(), |_| Ok(()));
     }
 
     pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
         let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+        match &self.state {
+            State::ViewContainingRefs | State::ViewShow => {
+                if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                }
+                self.output.on_key(available_height, key);
+                return ModeStatus {
+                    pending_input: false,
+                };
+            }
+            State::RevertPrompt(revision) => {
+                let revision = revision.clone();
+                if key.is_submit() {
+                    self.revert(ctx, revision, false);
+                } else if let Key::Char('n') = key {
+                    self.revert(ctx, revision, true);
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::ResetPrompt(revision) => {
+                let revision = revision.clone();
+                match key {
+                    Key::Char('s') => self.reset(ctx, revision, ResetMode::Soft),
+                    Key::Char('m') => self.reset(ctx, revision, ResetMode::Mixed),
+                    Key::Char('h') => self.state = State::ConfirmHardReset(revision),
+                    _ if key.is_cancel() => self.state = State::Idle,
+                    _ => (),
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::ConfirmHardReset(revision) => {
+                let revision = revision.clone();
+                match key {
+                    Key::Char('y') => self.reset(ctx, revision, ResetMode::Hard),
+                    Key::Char('n') => self.state = State::Idle,
+                    _ if key.is_cancel() => self.state = State::Idle,
+                    _ => (),
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::NewBranchInput(revision) => {
+                self.readline.on_key(key);
+                let name = self.readline.input();
+                if key.is_submit() && !name.is_empty() && is_valid_ref_name(name) {
+                    let name = name.to_string();
+                    let revision = revision.clone();
+                    self.state = State::Waiting(WaitOperation::NewBranch);
+                    request(ctx, self.refresh_count(ctx), self.query.clone(), move |b| {
+                        b.new_branch(&name, Some(&revision), false)
+                    });
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::GrepInput => {
+                self.readline.on_key(key);
+                if key.is_submit() {
+                    self.query.filter.message = self.readline.input().trim().into();
+                    self.state = State::Waiting(WaitOperation::Refresh);
+                    self.select.set_cursor(0);
+                    request(ctx, self.load_count(ctx), self.query.clone(), |_| Ok(()));
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::DateRangeInput => {
+                self.form.on_key(key);
+                if key.is_submit() {
+                    self.query.filter.since = self.form.input(0).trim().into();
+                    self.query.filter.until = self.form.input(1).trim().into();
+                    self.state = State::Waiting(WaitOperation::Refresh);
+                    self.select.set_cursor(0);
+                    request(ctx, self.load_count(ctx), self.query.clone(), |_| Ok(()));
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::PushRemoteSelect(options) => {
+                self.remote_select
+                    .on_key(self.remotes.len(), available_height, key);
+                if key.is_submit() {
+                    if let Some(remote) = self.remotes.get(self.remote_select.cursor()) {
+                        let mut options = options.clone();
+                        options.remote = remote.clone();
+                        self.readline.set_input(&options.branch);
+                        self.state = State::PushBranchInput(options);
+                    }
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::PushBranchInput(options) => {
+                self.readline.on_key(key);
+                let branch = self.readline.input();
+                if key.is_submit() && !branch.is_empty() && is_valid_ref_name(branch) {
+                    let mut options = options.clone();
+                    options.branch = branch.into();
+                    self.state = State::ConfirmPush(options);
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::ConfirmPush(options) => {
+                let mut options = options.clone();
+                match key {
+                    Key::Char('f') => {
+                        options.force_with_lease = !options.force_with_lease;
+                        self.state = State::ConfirmPush(options);
+                    }
+                    Key::Char('u') => {
+                        options.set_upstream = !options.set_upstream;
+                        self.state = State::ConfirmPush(options);
+                    }
+                    _ if key.is_submit() => {
+                        self.state = State::Waiting(WaitOperation::Push);
+                        request_with_progress(
+                            ctx,
+                            self.refresh_count(ctx),
+                            self.query.clone(),
+                            self.retry,
+                            move |b, progress| b.push_with_options(&options, progress),
+                        );
+                    }
+                    Key::Char('n') => self.state = State::Idle,
+                    _ if key.is_cancel() => self.state = State::Idle,
+                    _ => (),
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            _ => (),
+        }
+
+        let previous_cursor = self.select.cursor();
         self.select
             .on_key(self.entries.len(), available_height, key);
 
+        // start loading the next page while there's still half a screen of entries left
+        // so scrolling through long histories doesn't stall at the bottom
+        let cursor = self.select.cursor();
+        if cursor != previous_cursor && cursor + available_height / 2 + 1 >= self.entries.len() {
+            if let State::Idle = self.state {
+                self.load_more(ctx);
+            }
+        }
+
+        let key = ctx.key_bindings.translate("log", key);
         if let Key::Char('d') = key {
             let index = self.select.cursor();
             if let Some(entry) = self.entries.get(index) {
@@ -161,14 +539,22 @@ impl Mode {
             }
         } else if let Key::Tab = key {
             self.show_full_hovered_message = !self.show_full_hovered_message;
+        } else if let Key::Char('r') = key {
+            self.absolute_dates = !self.absolute_dates;
+            self.format_dates();
+        } else if let Key::Char('e') = key {
+            self.compact = !self.compact;
         } else if let State::Idle = self.state {
             match key {
+                Key::Enter if !self.entries.is_empty() => self.output.set(String::new()),
                 Key::Char('g') => {
                     let index = self.select.cursor();
                     if let Some(entry) = self.entries.get(index) {
                         self.state = State::Waiting(WaitOperation::Checkout);
                         let revision = entry.hash.clone();
-                        request(ctx, move |b| b.checkout(&revision));
+                        request(ctx, self.refresh_count(ctx), self.query.clone(), move |b| {
+                            b.checkout(&revision)
+                        });
                     }
                 }
                 Key::Char('m') => {
@@ -176,20 +562,147 @@ impl Mode {
                     if let Some(entry) = self.entries.get(index) {
                         self.state = State::Waiting(WaitOperation::Merge);
                         let revision = entry.hash.clone();
-                        request(ctx, move |b| b.merge(&revision));
+                        request(ctx, self.refresh_count(ctx), self.query.clone(), move |b| {
+                            b.merge(&revision)
+                        });
+                    }
+                }
+                Key::Char('n') => self.load_more(ctx),
+                Key::Char('o') => {
+                    self.state = State::Waiting(WaitOperation::Refresh);
+                    self.query.order = self.query.order.next();
+                    request(ctx, self.refresh_count(ctx), self.query.clone(), |_| Ok(()));
+                }
+                Key::Char('/') => {
+                    self.readline.set_input(&self.query.filter.message);
+                    self.state = State::GrepInput;
+                }
+                Key::Char('T') => {
+                    self.form = Form::new(&["since", "until"]);
+                    self.form.set_input(0, &self.query.filter.since);
+                    self.form.set_input(1, &self.query.filter.until);
+                    self.state = State::DateRangeInput;
+                }
+                Key::Char('G') => {
+                    self.state = State::Waiting(WaitOperation::Refresh);
+                    self.query.graph = !self.query.graph;
+                    request(ctx, self.refresh_count(ctx), self.query.clone(), |_| Ok(()));
+                }
+                Key::Char('V') => {
+                    let index = self.select.cursor();
+                    if let Some(entry) = self.entries.get(index) {
+                        self.state = State::RevertPrompt(entry.hash.clone());
+                    }
+                }
+                Key::Char('C') => {
+                    let index = self.select.cursor();
+                    if let Some(entry) = self.entries.get(index) {
+                        let revisions = vec![entry.hash.clone()];
+                        self.cherry_pick(ctx, revisions);
+                    }
+                }
+                Key::Char('X') => {
+                    let index = self.select.cursor();
+                    if let Some(entry) = self.entries.get(index) {
+                        self.state = State::ResetPrompt(entry.hash.clone());
+                    }
+                }
+                Key::Char('B') => {
+                    let index = self.select.cursor();
+                    if let Some(entry) = self.entries.get(index) {
+                        self.readline.clear();
+                        self.state = State::NewBranchInput(entry.hash.clone());
+                    }
+                }
+                Key::Char('i') => {
+                    let index = self.select.cursor();
+                    if let Some(entry) = self.entries.get(index) {
+                        self.state = State::ViewContainingRefs;
+                        self.output.set(String::new());
+
+                        let ctx = ctx.clone();
+                        let revision = entry.hash.clone();
+                        thread::spawn(move || {
+                            let output = match ctx.backend.containing_refs(&revision) {
+                                Ok(refs) => refs.to_string(),
+                                Err(error) => error,
+                            };
+                            ctx.event_sender
+                                .send_response(ModeResponse::Log(Response::ContainingRefs(output)));
+                        });
+                    }
+                }
+                Key::Char('v') => {
+                    let index = self.select.cursor();
+                    if let Some(entry) = self.entries.get(index) {
+                        self.state = State::ViewShow;
+                        self.output.set(String::new());
+
+                        let ctx = ctx.clone();
+                        let revision = entry.hash.clone();
+                        thread::spawn(move || {
+                            let output = match ctx.backend.show(&revision) {
+                                Ok(output) => output,
+                                Err(error) => error,
+                            };
+                            ctx.event_sender
+                                .send_response(ModeResponse::Log(Response::Show(output)));
+                        });
                     }
                 }
                 Key::Char('f') => {
                     self.state = State::Waiting(WaitOperation::Fetch);
-                    request(ctx, Backend::fetch);
+                    request_with_progress(
+                        ctx,
+                        self.refresh_count(ctx),
+                        self.query.clone(),
+                        self.retry,
+                        Backend::fetch,
+                    );
                 }
                 Key::Char('p') => {
                     self.state = State::Waiting(WaitOperation::Pull);
-                    request(ctx, Backend::pull);
+                    request_with_progress(
+                        ctx,
+                        self.refresh_count(ctx),
+                        self.query.clone(),
+                        self.retry,
+                        Backend::pull,
+                    );
                 }
                 Key::Char('P') => {
                     self.state = State::Waiting(WaitOperation::Push);
-                    request(ctx, Backend::push);
+                    request_with_progress(
+                        ctx,
+                        self.refresh_count(ctx),
+                        self.query.clone(),
+                        self.retry,
+                        Backend::push,
+                    );
+                }
+                Key::Char('U') => {
+                    self.state = State::Waiting(WaitOperation::PushOptions);
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        let result = ctx.backend.push_options().and_then(|options| {
+                            let remotes = ctx.backend.remote_list()?;
+                            let remotes = remotes.into_iter().map(|r| r.name).collect();
+                            Ok((options, remotes))
+                        });
+                        ctx.event_sender
+                            .send_response(ModeResponse::Log(Response::PushOptions(result)));
+                    });
+                }
+                Key::Char('y') => {
+                    self.state = State::Waiting(WaitOperation::Sync);
+                    let retry = self.retry;
+                    let event_sender = ctx.event_sender.clone();
+                    request_with_diagnostics(
+                        ctx,
+                        self.refresh_count(ctx),
+                        self.query.clone(),
+                        move |b| sync(b, retry, &event_sender),
+                    );
                 }
                 _ => (),
             }
@@ -200,10 +713,19 @@ impl Mode {
         }
     }
 
+    pub fn on_paste(&mut self, text: &str) {
+        match self.state {
+            State::NewBranchInput(_) | State::GrepInput | State::PushBranchInput(_) => {
+                self.readline.paste(text)
+            }
+            State::DateRangeInput => self.form.paste(text),
+            _ => (),
+        }
+    }
+
     pub fn on_response(&mut self, response: Response) {
         match response {
             Response::Refresh(result) => {
-                self.entries = Vec::new();
                 self.output.set(String::new());
 
                 if let State::Waiting(_) = self.state {
@@ -216,57 +738,441 @@ impl Mode {
                     }
                 }
 
+                self.has_more = !self.entries.is_empty();
+                self.format_dates();
+
                 self.select.saturate_cursor(self.entries.len());
             }
+            Response::LoadMore(result) => {
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Idle;
+                }
+                match result {
+                    Ok(entries) => {
+                        self.has_more = !entries.is_empty();
+                        self.entries.extend(entries);
+                    }
+                    Err(error) => self.output.set(error),
+                }
+
+                self.format_dates();
+            }
+            Response::Progress(line) => {
+                if let State::Waiting(_) = self.state {
+                    self.output.set(progress_text(&line));
+                }
+            }
+            Response::Diagnostics(output) => {
+                if let State::Idle = self.state {
+                    self.output.set(output);
+                }
+            }
+            Response::ContainingRefs(output) => {
+                if let State::ViewContainingRefs = self.state {
+                    self.output.set(output);
+                }
+            }
+            Response::Show(output) => {
+                if let State::ViewShow = self.state {
+                    self.output.set(output);
+                    self.output.set_kind(OutputKind::Diff);
+                }
+            }
+            Response::PushOptions(result) => {
+                if let State::Waiting(WaitOperation::PushOptions) = self.state {
+                    match result {
+                        Ok((options, remotes)) => {
+                            let cursor = remotes.iter().position(|r| *r == options.remote);
+                            self.remote_select.set_cursor(cursor.unwrap_or(0));
+                            self.remotes = remotes;
+                            self.state = State::PushRemoteSelect(options);
+                        }
+                        Err(error) => {
+                            self.state = State::Idle;
+                            self.output.set(error);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_following_output(&self) -> bool {
+        self.output.is_following()
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
         }
+        waiting
     }
 
     pub fn is_waiting_response(&self) -> bool {
         match self.state {
-            State::Idle => false,
+            State::Idle
+            | State::RevertPrompt(_)
+            | State::ResetPrompt(_)
+            | State::ConfirmHardReset(_)
+            | State::NewBranchInput(_)
+            | State::GrepInput
+            | State::DateRangeInput
+            | State::PushRemoteSelect(_)
+            | State::PushBranchInput(_)
+            | State::ConfirmPush(_) => false,
             State::Waiting(_) => true,
+            State::ViewContainingRefs | State::ViewShow => self.output.text().is_empty(),
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|entry| entry.hash.clone())
         }
     }
 
     pub fn header(&self) -> (&str, &str, &str) {
         let name = match self.state {
-            State::Idle | State::Waiting(WaitOperation::Refresh) => "log",
+            State::Idle | State::Waiting(WaitOperation::Refresh) => match self.query.order {
+                LogOrder::Default => "log",
+                LogOrder::Date => "log (date order)",
+                LogOrder::Topological => "log (topo order)",
+            },
+            State::Waiting(WaitOperation::LoadMore) => "load more",
             State::Waiting(WaitOperation::Checkout) => "checkout",
             State::Waiting(WaitOperation::Merge) => "merge",
             State::Waiting(WaitOperation::Fetch) => "fetch",
             State::Waiting(WaitOperation::Pull) => "pull",
-            State::Waiting(WaitOperation::Push) => "push",
+            State::Waiting(WaitOperation::Push | WaitOperation::PushOptions)
+            | State::PushRemoteSelect(_)
+            | State::PushBranchInput(_)
+            | State::ConfirmPush(_) => "push",
+            State::Waiting(WaitOperation::Sync) => "sync",
+            State::Waiting(WaitOperation::Revert) | State::RevertPrompt(_) => "revert commit",
+            State::Waiting(WaitOperation::Reset) | State::ResetPrompt(_) => "reset",
+            State::ConfirmHardReset(_) => "hard reset",
+            State::Waiting(WaitOperation::CherryPick) => "cherry-pick",
+            State::Waiting(WaitOperation::NewBranch) | State::NewBranchInput(_) => "new branch",
+            State::ViewContainingRefs => "containing refs",
+            State::ViewShow => "show",
+            State::GrepInput => "grep",
+            State::DateRangeInput => "date range",
+        };
+        let (left_help, right_help) = match self.state {
+            State::Idle | State::Waiting(_) => (
+                "[g]checkout [d]details [v]show [i]containing refs [n]load more [V]revert commit [C]cherry-pick [X]reset [B]new branch here [f]fetch [p]pull [P]push [U]push to [y]sync",
+                "[arrows]move [r]relative/absolute dates [e]compact/expanded [o]order [G]graph [/]grep [T]date range",
+            ),
+            State::ViewContainingRefs => ("", "[arrows]move"),
+            State::ViewShow => ("", "[arrows]move [v]cursor line [z]center [W]wrap [shift+arrows]pan"),
+            State::RevertPrompt(_) => (
+                "[enter]revert and commit [n]revert without committing",
+                "[esc]cancel",
+            ),
+            State::ResetPrompt(_) => ("[s]soft [m]mixed [h]hard", "[esc]cancel"),
+            State::ConfirmHardReset(_) => (
+                "[y]reset and discard all uncommitted changes",
+                "[n]cancel",
+            ),
+            State::NewBranchInput(_) => ("[enter]create", "[esc]cancel"),
+            State::GrepInput => (
+                "[enter]filter by message (leave empty to clear)",
+                "[esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::DateRangeInput => (
+                "[tab]next field [enter]filter by date (leave empty to clear)",
+                "[esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::PushRemoteSelect(_) => ("[enter]select remote", "[arrows]move [esc]cancel"),
+            State::PushBranchInput(_) => (
+                "",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::ConfirmPush(_) => (
+                "[enter]push [f]force with lease [u]set upstream",
+                "[n]cancel",
+            ),
         };
-        let left_help = "[g]checkout [d]details [f]fetch [p]pull [P]push";
-        let right_help = "[arrows]move";
         (name, left_help, right_help)
     }
 
+    // draws the active filters above the entries and returns how many lines it took
+    fn draw_filter(&self, drawer: &mut Drawer) -> u16 {
+        let filter = &self.query.filter;
+        if filter.is_empty() {
+            return 0;
+        }
+
+        let foreground = drawer.theme.foreground;
+        let fields = [
+            ("grep", &filter.message),
+            ("since", &filter.since),
+            ("until", &filter.until),
+        ];
+        for (label, value) in fields.iter().filter(|(_, v)| !v.is_empty()) {
+            drawer.fmt(format_args!(
+                "{}{}: {}{}  ",
+                Color::DarkGray,
+                label,
+                foreground,
+                value
+            ));
+        }
+        drawer.next_line();
+        1
+    }
+
     pub fn draw(&self, drawer: &mut Drawer) {
-        if self.output.text().is_empty() {
-            drawer.select_menu(
-                &self.select,
+        let input = self.readline.input();
+        match &self.state {
+            State::NewBranchInput(_) => drawer.validated_readline(
+                &self.readline,
+                "type in the branch name...",
+                input.is_empty() || is_valid_ref_name(input),
+            ),
+            State::GrepInput => drawer.readline(&self.readline, "type in a message pattern..."),
+            State::DateRangeInput => drawer.form(
+                &self.form,
+                &[
+                    "type in a date like '2 weeks ago' or '2024-01-31'...",
+                    "type in a date or leave it empty...",
+                ],
+            ),
+            State::PushBranchInput(_) => drawer.validated_readline(
+                &self.readline,
+                "type in the remote branch name...",
+                input.is_empty() || is_valid_ref_name(input),
+            ),
+            State::PushRemoteSelect(_) => drawer.select_menu(
+                &self.remote_select,
                 0,
-                self.show_full_hovered_message,
-                self.entries.iter(),
-            );
-        } else {
-            drawer.output(&self.output);
+                false,
+                self.remotes.iter().map(String::as_str),
+            ),
+            State::ConfirmPush(options) => {
+                let yes_no = |enabled| if enabled { "yes" } else { "no" };
+                drawer.fmt(format_args!(
+                    "push HEAD to '{}' on '{}'?",
+                    options.branch, options.remote
+                ));
+                drawer.next_line();
+                drawer.fmt(format_args!(
+                    "force with lease: {}",
+                    yes_no(options.force_with_lease)
+                ));
+                drawer.next_line();
+                drawer.fmt(format_args!(
+                    "set upstream: {}",
+                    yes_no(options.set_upstream)
+                ));
+            }
+            _ if self.output.text().is_empty() => {
+                let header_height = self.draw_filter(drawer);
+                let compact = self.compact;
+                drawer.select_menu(
+                    &self.select,
+                    header_height,
+                    self.show_full_hovered_message,
+                    self.entries
+                        .iter()
+                        .map(|entry| EntryView { entry, compact }),
+                );
+            }
+            _ => {
+                drawer.output(&self.output);
+                if let State::Idle = self.state {
+                    if !self.entries.is_empty() {
+                        drawer.back_hint();
+                    }
+                }
+            }
         }
     }
 }
 
-fn request<F>(ctx: &ModeContext, f: F)
+fn request<F>(ctx: &ModeContext, len: usize, query: LogQuery, f: F)
 where
     F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<()>,
+{
+    request_with_diagnostics(ctx, len, query, move |b| f(b).map(|()| String::new()));
+}
+
+// like `request` but `f` may return warnings to show once the log is refreshed
+fn request_with_diagnostics<F>(ctx: &ModeContext, len: usize, query: LogQuery, f: F)
+where
+    F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<String>,
 {
     let ctx = ctx.clone();
     thread::spawn(move || {
         use std::ops::Deref;
 
-        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
-        let result = f(ctx.backend.deref()).and_then(|_| ctx.backend.log(0, available_height));
+        let mut diagnostics = String::new();
+        let result = f(ctx.backend.deref()).and_then(|output| {
+            diagnostics = output;
+            ctx.backend
+                .log(0, len, query.order, query.graph, &query.filter)
+        });
+        if let Ok(header) = ctx.backend.branch_header() {
+            ctx.event_sender.send_title_change(header, None);
+        }
+        let operation = ctx.backend.in_progress_operation().unwrap_or(None);
+        ctx.event_sender.send_operation_change(operation);
         ctx.event_sender
             .send_response(ModeResponse::Log(Response::Refresh(result)));
+        if !diagnostics.is_empty() {
+            ctx.event_sender
+                .send_response(ModeResponse::Log(Response::Diagnostics(diagnostics)));
+        }
+    });
+}
+
+fn request_with_progress<F>(
+    ctx: &ModeContext,
+    len: usize,
+    query: LogQuery,
+    retry: RetryPolicy,
+    f: F,
+) where
+    F: 'static + Send + Sync + Fn(&dyn Backend, &mut dyn FnMut(&str)) -> BackendResult<String>,
+{
+    let event_sender = ctx.event_sender.clone();
+    request_with_diagnostics(ctx, len, query, move |b| {
+        retry_with_progress(
+            b,
+            retry,
+            &mut |line| event_sender.send_response(ModeResponse::Log(Response::Progress(line))),
+            f,
+        )
     });
 }
+
+fn sync(
+    backend: &dyn Backend,
+    retry: RetryPolicy,
+    event_sender: &EventSender,
+) -> BackendResult<String> {
+    type Stage = fn(&dyn Backend, &mut dyn FnMut(&str)) -> BackendResult<String>;
+    const STAGES: &[(&str, Stage)] = &[
+        ("fetch", Backend::fetch),
+        ("pull", Backend::pull),
+        ("push", Backend::push),
+    ];
+
+    let mut diagnostics = String::new();
+    for (i, &(name, f)) in STAGES.iter().enumerate() {
+        let result = retry_with_progress(
+            backend,
+            retry,
+            &mut |line| {
+                let line = format!("{} ({} of {})\n{}", name, i + 1, STAGES.len(), line);
+                event_sender.send_response(ModeResponse::Log(Response::Progress(line)));
+            },
+            f,
+        );
+
+        match result {
+            Ok(output) => diagnostics.push_str(&output),
+            Err(error) => {
+                if let Ok(Some(Operation::Merge | Operation::Rebase)) =
+                    backend.in_progress_operation()
+                {
+                    event_sender.send_mode_change(ModeKind::Status);
+                }
+                return Err(format!("sync stopped at {}:\n{}", name, error));
+            }
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+pub fn retry_with_progress<F>(
+    backend: &dyn Backend,
+    retry: RetryPolicy,
+    progress: &mut dyn FnMut(String),
+    f: F,
+) -> BackendResult<String>
+where
+    F: Fn(&dyn Backend, &mut dyn FnMut(&str)) -> BackendResult<String>,
+{
+    let mut attempt = 0;
+    loop {
+        let prefix = match attempt {
+            0 => String::new(),
+            _ => format!("retry {} of {}\n", attempt, retry.count),
+        };
+        let result = f(backend, &mut |line| progress(format!("{}{}", prefix, line)));
+
+        match result {
+            Err(error) if attempt < retry.count && is_transient_network_error(&error) => {
+                let delay = retry.delay * (1 << attempt.min(6));
+                attempt += 1;
+                progress(format!(
+                    "{}\nretrying in {}s ({} of {})",
+                    error.trim_end(),
+                    delay.as_secs(),
+                    attempt,
+                    retry.count
+                ));
+                thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_transient_network_error(error: &str) -> bool {
+    const PERMANENT_PATTERNS: &[&str] = &[
+        "authentication failed",
+        "permission denied",
+        "could not read username",
+        "repository not found",
+        "403",
+    ];
+    const TRANSIENT_PATTERNS: &[&str] = &[
+        "connection reset",
+        "connection timed out",
+        "operation timed out",
+        "could not resolve host",
+        "temporary failure",
+        "network is unreachable",
+        "the remote end hung up unexpectedly",
+        "early eof",
+        "rpc failed",
+    ];
+
+    let error = error.to_lowercase();
+    !PERMANENT_PATTERNS.iter().any(|p| error.contains(p))
+        && TRANSIENT_PATTERNS.iter().any(|p| error.contains(p))
+}
+
+pub fn progress_text(line: &str) -> String {
+    const BAR_WIDTH: usize = 40;
+
+    let percent = line.find('%').and_then(|end| {
+        let start = line[..end]
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        line[start..end].parse::<usize>().ok()
+    });
+
+    match percent {
+        Some(percent) => {
+            let filled = BAR_WIDTH * percent.min(100) / 100;
+            format!(
+                "{}\n[{}{}]",
+                line,
+                "#".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled)
+            )
+        }
+        None => line.into(),
+    }
+}
@@ -0,0 +1,243 @@
+use std::thread;
+
+use crate::{
+    backend::{Backend, BackendResult, ReflogEntry, ResetMode},
+    mode::{ModeContext, ModeKind, ModeResponse, ModeStatus, Output, SelectMenu},
+    platform::Key,
+    ui::{Color, Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
+};
+
+pub enum Response {
+    Refresh(BackendResult<Vec<ReflogEntry>>),
+}
+
+enum WaitOperation {
+    Refresh,
+    Reset,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Waiting(WaitOperation),
+    ResetPrompt(String),
+    ConfirmHardReset(String),
+}
+
+impl SelectEntryDraw for ReflogEntry {
+    fn draw(&self, drawer: &mut Drawer, hovered: bool, _: bool) -> usize {
+        let foreground = drawer.theme.foreground;
+        let selector_color = if hovered { foreground } else { Color::DarkRed };
+        let hash_color = if hovered {
+            foreground
+        } else {
+            Color::DarkYellow
+        };
+        let old_hash = if self.old_hash.is_empty() {
+            "-"
+        } else {
+            &self.old_hash
+        };
+        drawer.fmt(format_args!(
+            "{}{} {}{}..{} {}{}",
+            selector_color,
+            self.selector,
+            hash_color,
+            old_hash,
+            self.new_hash,
+            foreground,
+            self.action,
+        ));
+        1
+    }
+}
+
+#[derive(Default)]
+pub struct Mode {
+    state: State,
+    entries: Vec<ReflogEntry>,
+    output: Output,
+    select: SelectMenu,
+}
+impl Mode {
+    pub fn on_enter(&mut self, ctx: &ModeContext) {
+        if let State::Waiting(_) = self.state {
+            return;
+        }
+        self.state = State::Waiting(WaitOperation::Refresh);
+
+        self.output.set(String::new());
+
+        request(ctx, |_| Ok(()));
+    }
+
+    pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
+        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+
+        match &self.state {
+            State::ResetPrompt(revision) => {
+                let revision = revision.clone();
+                match key {
+                    Key::Char('s') => self.reset(ctx, revision, ResetMode::Soft),
+                    Key::Char('m') => self.reset(ctx, revision, ResetMode::Mixed),
+                    Key::Char('h') => self.state = State::ConfirmHardReset(revision),
+                    _ if key.is_cancel() => self.state = State::Idle,
+                    _ => (),
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::ConfirmHardReset(revision) => {
+                let revision = revision.clone();
+                match key {
+                    Key::Char('y') => self.reset(ctx, revision, ResetMode::Hard),
+                    Key::Char('n') => self.state = State::Idle,
+                    _ if key.is_cancel() => self.state = State::Idle,
+                    _ => (),
+                }
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            State::Idle | State::Waiting(_) => (),
+        }
+
+        if !self.output.text().is_empty() {
+            if self.output.on_search_key(available_height, key) {
+                return ModeStatus {
+                    pending_input: true,
+                };
+            }
+            self.output.on_key(available_height, key);
+            if let (State::Idle, Key::Enter) = (&self.state, key) {
+                if !self.entries.is_empty() {
+                    self.output.set(String::new());
+                }
+            }
+            return ModeStatus {
+                pending_input: false,
+            };
+        }
+
+        self.select
+            .on_key(self.entries.len(), available_height, key);
+
+        let key = ctx.key_bindings.translate("reflog", key);
+        if let State::Idle = self.state {
+            let index = self.select.cursor();
+            if let Some(entry) = self.entries.get(index) {
+                match key {
+                    Key::Enter | Key::Char('d') => ctx
+                        .event_sender
+                        .send_mode_change(ModeKind::RevisionDetails(entry.new_hash.clone())),
+                    Key::Char('X') => self.state = State::ResetPrompt(entry.new_hash.clone()),
+                    _ => (),
+                }
+            }
+        }
+
+        ModeStatus {
+            pending_input: false,
+        }
+    }
+
+    pub fn on_response(&mut self, response: Response) {
+        match response {
+            Response::Refresh(result) => {
+                self.output.set(String::new());
+
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Idle;
+                }
+                match result {
+                    Ok(entries) => {
+                        self.entries = entries;
+                        if self.entries.is_empty() {
+                            self.output.set("empty reflog\n".into());
+                        }
+                    }
+                    Err(error) => self.output.set(error),
+                }
+
+                self.select.saturate_cursor(self.entries.len());
+            }
+        }
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
+    pub fn is_waiting_response(&self) -> bool {
+        match self.state {
+            State::Idle | State::ResetPrompt(_) | State::ConfirmHardReset(_) => false,
+            State::Waiting(_) => true,
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|entry| entry.new_hash.clone())
+        }
+    }
+
+    pub fn header(&self) -> (&str, &str, &str) {
+        let name = match self.state {
+            State::Idle | State::Waiting(WaitOperation::Refresh) => "reflog",
+            State::Waiting(WaitOperation::Reset) | State::ResetPrompt(_) => "reset",
+            State::ConfirmHardReset(_) => "hard reset",
+        };
+        let (left_help, right_help) = match self.state {
+            State::Idle | State::Waiting(_) => ("[d]details [X]reset", "[arrows]move"),
+            State::ResetPrompt(_) => ("[s]soft [m]mixed [h]hard", "[esc]cancel"),
+            State::ConfirmHardReset(_) => {
+                ("[y]reset and discard all uncommitted changes", "[n]cancel")
+            }
+        };
+        (name, left_help, right_help)
+    }
+
+    pub fn draw(&self, drawer: &mut Drawer) {
+        if self.output.text().is_empty() {
+            drawer.select_menu(&self.select, 0, false, self.entries.iter());
+        } else {
+            drawer.output(&self.output);
+            if let State::Idle = self.state {
+                if !self.entries.is_empty() {
+                    drawer.back_hint();
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self, ctx: &ModeContext, revision: String, mode: ResetMode) {
+        self.state = State::Waiting(WaitOperation::Reset);
+        request(ctx, move |b| b.reset(&revision, mode));
+    }
+}
+
+fn request<F>(ctx: &ModeContext, f: F)
+where
+    F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<()>,
+{
+    let ctx = ctx.clone();
+    let len = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+    thread::spawn(move || {
+        use std::ops::Deref;
+
+        let result = f(ctx.backend.deref()).and_then(|_| ctx.backend.reflog(len));
+        ctx.event_sender
+            .send_response(ModeResponse::Reflog(Response::Refresh(result)));
+    });
+}
@@ -0,0 +1,278 @@
+use std::thread;
+
+use crate::{
+    backend::{Backend, BackendResult, RemoteEntry},
+    mode::{
+        is_valid_ref_name, Form, ModeContext, ModeResponse, ModeStatus, Output, ReadLine,
+        SelectMenu,
+    },
+    platform::Key,
+    ui::{Color, Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
+};
+
+const NAME_FIELD: usize = 0;
+const URL_FIELD: usize = 1;
+
+pub enum Response {
+    Refresh(BackendResult<Vec<RemoteEntry>>),
+}
+
+enum WaitOperation {
+    Refresh,
+    Add,
+    Remove,
+    Rename,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Waiting(WaitOperation),
+    AddInput,
+    RenameInput(String),
+}
+
+impl SelectEntryDraw for RemoteEntry {
+    fn draw(&self, drawer: &mut Drawer, hovered: bool, _: bool) -> usize {
+        let foreground = drawer.theme.foreground;
+        let name_color = if hovered {
+            foreground
+        } else {
+            Color::DarkGreen
+        };
+        if self.fetch_url == self.push_url {
+            drawer.fmt(format_args!(
+                "{}{} {}{}",
+                name_color, self.name, foreground, self.fetch_url
+            ));
+        } else {
+            drawer.fmt(format_args!(
+                "{}{} {}{} (fetch) {} (push)",
+                name_color, self.name, foreground, self.fetch_url, self.push_url
+            ));
+        }
+        1
+    }
+}
+
+#[derive(Default)]
+pub struct Mode {
+    state: State,
+    entries: Vec<RemoteEntry>,
+    output: Output,
+    select: SelectMenu,
+    readline: ReadLine,
+    form: Form,
+}
+impl Mode {
+    pub fn on_enter(&mut self, ctx: &ModeContext) {
+        if let State::Waiting(_) = self.state {
+            return;
+        }
+        self.state = State::Waiting(WaitOperation::Refresh);
+
+        self.output.set(String::new());
+        self.readline.clear();
+
+        request(ctx, |_| Ok(()));
+    }
+
+    pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
+        let pending_input = matches!(self.state, State::AddInput | State::RenameInput(_));
+        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+
+        match &self.state {
+            State::Idle | State::Waiting(_) => {
+                if self.output.text().is_empty() {
+                    self.select
+                        .on_key(self.entries.len(), available_height, key);
+                } else if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                } else {
+                    self.output.on_key(available_height, key);
+                }
+
+                let key = ctx.key_bindings.translate("remotes", key);
+                if let State::Idle = self.state {
+                    match key {
+                        Key::Enter if !self.entries.is_empty() => self.output.set(String::new()),
+                        Key::Char('n') => {
+                            self.state = State::AddInput;
+                            self.output.set(String::new());
+                            self.form = Form::new(&["name", "url"]);
+                        }
+                        Key::Char('r') => {
+                            if let Some(entry) = self.entries.get(self.select.cursor()) {
+                                self.state = State::RenameInput(entry.name.clone());
+                                self.output.set(String::new());
+                                self.readline.set_input(&entry.name);
+                            }
+                        }
+                        Key::Char('D') => {
+                            let index = self.select.cursor();
+                            if let Some(entry) = self.entries.get(index) {
+                                self.state = State::Waiting(WaitOperation::Remove);
+
+                                let name = entry.name.clone();
+                                self.entries.remove(index);
+                                self.select.on_remove_entry(index);
+                                request(ctx, move |b| b.remote_remove(&name));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            State::AddInput => {
+                self.form.on_key(key);
+                if key.is_submit()
+                    && is_valid_ref_name(self.form.input(NAME_FIELD))
+                    && !self.form.input(URL_FIELD).trim().is_empty()
+                {
+                    self.state = State::Waiting(WaitOperation::Add);
+
+                    let name = self.form.input(NAME_FIELD).to_string();
+                    let url = self.form.input(URL_FIELD).trim().to_string();
+                    request(ctx, move |b| b.remote_add(&name, &url));
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::RenameInput(old_name) => {
+                self.readline.on_key(key);
+                if key.is_submit() && is_valid_ref_name(self.readline.input()) {
+                    let old_name = old_name.clone();
+                    self.state = State::Waiting(WaitOperation::Rename);
+
+                    let new_name = self.readline.input().to_string();
+                    request(ctx, move |b| b.remote_rename(&old_name, &new_name));
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+        }
+
+        ModeStatus { pending_input }
+    }
+
+    pub fn on_paste(&mut self, text: &str) {
+        match self.state {
+            State::AddInput => self.form.paste(text),
+            State::RenameInput(_) => self.readline.paste(text),
+            _ => (),
+        }
+    }
+
+    pub fn on_response(&mut self, response: Response) {
+        match response {
+            Response::Refresh(result) => {
+                self.output.set(String::new());
+
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Idle;
+                }
+                if let State::Idle = self.state {
+                    match result {
+                        Ok(entries) => {
+                            self.entries = entries;
+                            if self.entries.is_empty() {
+                                self.output.set("no remotes\n".into());
+                            }
+                        }
+                        Err(error) => self.output.set(error),
+                    }
+                }
+
+                self.select.saturate_cursor(self.entries.len());
+            }
+        }
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
+    pub fn is_waiting_response(&self) -> bool {
+        match self.state {
+            State::Idle | State::AddInput | State::RenameInput(_) => false,
+            State::Waiting(_) => true,
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|entry| entry.fetch_url.clone())
+        }
+    }
+
+    pub fn header(&self) -> (&str, &str, &str) {
+        let name = match self.state {
+            State::Idle | State::Waiting(WaitOperation::Refresh) => "remotes",
+            State::Waiting(WaitOperation::Add) | State::AddInput => "new remote",
+            State::Waiting(WaitOperation::Remove) => "remove remote",
+            State::Waiting(WaitOperation::Rename) | State::RenameInput(_) => "rename remote",
+        };
+        let (left_help, right_help) = match self.state {
+            State::Idle | State::Waiting(_) => ("[n]new [r]rename [D]remove", "[arrows]move"),
+            State::AddInput => (
+                "[tab]next field",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::RenameInput(_) => (
+                "",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+        };
+        (name, left_help, right_help)
+    }
+
+    pub fn draw(&self, drawer: &mut Drawer) {
+        match self.state {
+            State::Idle | State::Waiting(_) => {
+                if self.output.text().is_empty() {
+                    drawer.select_menu(&self.select, 0, false, self.entries.iter());
+                } else {
+                    drawer.output(&self.output);
+                    if !self.entries.is_empty() {
+                        drawer.back_hint();
+                    }
+                }
+            }
+            State::AddInput => drawer.form(
+                &self.form,
+                &["type in the remote name...", "type in the remote url..."],
+            ),
+            State::RenameInput(_) => drawer.validated_readline(
+                &self.readline,
+                "type in the new remote name...",
+                self.readline.input().is_empty() || is_valid_ref_name(self.readline.input()),
+            ),
+        }
+    }
+}
+
+fn request<F>(ctx: &ModeContext, f: F)
+where
+    F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<()>,
+{
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        use std::ops::Deref;
+
+        let result = f(ctx.backend.deref()).and_then(|_| ctx.backend.remote_list());
+        ctx.event_sender
+            .send_response(ModeResponse::Remotes(Response::Refresh(result)));
+    });
+}
@@ -1,26 +1,30 @@
 use std::thread;
 
 use crate::{
-    backend::{RevisionEntry, RevisionInfo, SelectableRevisionEntry},
-    mode::{ModeContext, ModeResponse, ModeStatus, Output, SelectMenu, SelectMenuAction},
+    backend::{RevisionEntry, RevisionInfo, SelectableRevisionEntry, SignatureStatus},
+    mode::{
+        self, sort_entries, EntrySort, ModeContext, ModeKind, ModeResponse, ModeStatus, Output,
+        OutputKind, SelectMenu, SelectMenuAction,
+    },
     platform::Key,
-    ui::{Drawer, RESERVED_LINES_COUNT},
+    ui::{Color, Drawer, RESERVED_LINES_COUNT},
 };
 
 pub enum Response {
     Info(RevisionInfo),
     Diff(String),
+    ContainingRefs(String),
+    Revert,
 }
 
+#[derive(Default)]
 enum State {
+    #[default]
     Idle,
     Waiting,
     ViewDiff,
-}
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
+    ViewContainingRefs,
+    RevertPrompt,
 }
 
 #[derive(Default)]
@@ -29,9 +33,18 @@ pub struct Mode {
     entries: Vec<SelectableRevisionEntry>,
     output: Output,
     select: SelectMenu,
+    signature: Option<SignatureStatus>,
     show_full_message: bool,
+    sort: EntrySort,
 }
 impl Mode {
+    pub fn new(sort: EntrySort) -> Self {
+        Self {
+            sort,
+            ..Default::default()
+        }
+    }
+
     fn get_selected_entries(&self) -> Vec<RevisionEntry> {
         let entries: Vec<_> = self
             .entries
@@ -53,27 +66,27 @@ impl Mode {
 
         self.output.set(String::new());
         self.select.saturate_cursor(0);
+        self.signature = None;
         self.show_full_message = false;
 
         let ctx = ctx.clone();
         let revision = revision.to_string();
         thread::spawn(move || {
-            let mut info = match ctx.backend.revision_details(&revision) {
+            let info = match ctx.backend.revision_details(&revision) {
                 Ok(info) => info,
                 Err(error) => RevisionInfo {
                     message: error,
+                    signature: None,
                     entries: Vec::new(),
                 },
             };
-            info.entries
-                .sort_unstable_by(|a, b| a.status.cmp(&b.status));
-
             ctx.event_sender
                 .send_response(ModeResponse::RevisionDetails(Response::Info(info)));
         });
     }
 
     pub fn on_key(&mut self, ctx: &ModeContext, revision: &str, key: Key) -> ModeStatus {
+        let pending_input = matches!(self.state, State::RevertPrompt);
         let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
 
         match self.state {
@@ -94,41 +107,112 @@ impl Mode {
                     }
                 }
 
+                let key = ctx.key_bindings.translate("revision_details", key);
                 match key {
                     Key::Tab => {
                         self.show_full_message = !self.show_full_message;
                     }
-                    Key::Char('d') => {
-                        if !self.entries.is_empty() {
-                            self.state = State::ViewDiff;
-                            self.output.set(String::new());
-
-                            let entries = self.get_selected_entries();
-
-                            let ctx = ctx.clone();
-                            let revision = revision.to_string();
-                            thread::spawn(move || {
-                                let output = match ctx.backend.diff(Some(&revision), &entries) {
-                                    Ok(output) => output,
-                                    Err(error) => error,
-                                };
-                                ctx.event_sender
-                                    .send_response(ModeResponse::RevisionDetails(Response::Diff(
-                                        output,
-                                    )));
-                            });
+                    Key::Char('d') if !self.entries.is_empty() => {
+                        self.state = State::ViewDiff;
+                        self.output.set(String::new());
+
+                        let entries = self.get_selected_entries();
+
+                        let ctx = ctx.clone();
+                        let revision = revision.to_string();
+                        thread::spawn(move || {
+                            let output = match ctx.backend.diff(Some(&revision), &entries) {
+                                Ok(output) => output,
+                                Err(error) => error,
+                            };
+                            ctx.event_sender
+                                .send_response(ModeResponse::RevisionDetails(Response::Diff(
+                                    output,
+                                )));
+                        });
+                    }
+                    Key::Char('V') => self.state = State::RevertPrompt,
+                    Key::Char('f') => {
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            ctx.event_sender
+                                .send_mode_change(ModeKind::FileLog(entry.name.clone()));
                         }
                     }
+                    Key::Char('o') => {
+                        self.sort = self.sort.next();
+                        sort_entries(self.sort, &mut self.entries, &mut self.select);
+                    }
+                    Key::Char('i') => {
+                        self.state = State::ViewContainingRefs;
+                        self.output.set(String::new());
+
+                        let ctx = ctx.clone();
+                        let revision = revision.to_string();
+                        thread::spawn(move || {
+                            let output = match ctx.backend.containing_refs(&revision) {
+                                Ok(refs) => refs.to_string(),
+                                Err(error) => error,
+                            };
+                            ctx.event_sender
+                                .send_response(ModeResponse::RevisionDetails(
+                                    Response::ContainingRefs(output),
+                                ));
+                        });
+                    }
                     _ => (),
                 }
             }
-            State::ViewDiff => self.output.on_key(available_height, key),
-            _ => (),
+            State::ViewDiff | State::ViewContainingRefs => {
+                if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                }
+                self.output.on_key(available_height, key)
+            }
+            State::RevertPrompt => {
+                if key.is_submit() {
+                    self.revert(ctx, revision, false);
+                } else if let Key::Char('n') = key {
+                    self.revert(ctx, revision, true);
+                } else if key.is_cancel() {
+                    self.state = State::Idle;
+                }
+            }
+            State::Waiting => (),
         }
 
-        ModeStatus {
-            pending_input: false,
-        }
+        ModeStatus { pending_input }
+    }
+
+    fn revert(&mut self, ctx: &ModeContext, revision: &str, no_commit: bool) {
+        self.state = State::Waiting;
+
+        let ctx = ctx.clone();
+        let revision = revision.to_string();
+        thread::spawn(move || {
+            use std::ops::Deref;
+
+            let next_mode = match ctx.backend.revert_commit(&revision, no_commit) {
+                Ok(()) if no_commit => ModeKind::Status,
+                Ok(()) => ModeKind::Log,
+                Err(_) if mode::has_conflicts(ctx.backend.deref()) => ModeKind::Status,
+                Err(error) => {
+                    let info = RevisionInfo {
+                        message: error,
+                        signature: None,
+                        entries: Vec::new(),
+                    };
+                    ctx.event_sender
+                        .send_response(ModeResponse::RevisionDetails(Response::Info(info)));
+                    return;
+                }
+            };
+
+            ctx.event_sender
+                .send_response(ModeResponse::RevisionDetails(Response::Revert));
+            ctx.event_sender.send_mode_change(next_mode);
+        });
     }
 
     pub fn on_response(&mut self, response: Response) {
@@ -141,33 +225,66 @@ impl Mode {
                     self.output.set(info.message);
                 }
 
+                self.signature = info.signature;
                 self.entries = info.entries.into_iter().map(Into::into).collect();
+                self.sort.sort(&mut self.entries);
                 self.select.saturate_cursor(self.entries.len());
             }
             Response::Diff(output) => {
                 if let State::ViewDiff = self.state {
                     self.output.set(output);
+                    self.output.set_kind(OutputKind::Diff);
+                }
+            }
+            Response::ContainingRefs(output) => {
+                if let State::ViewContainingRefs = self.state {
+                    self.output.set(output);
                 }
             }
+            Response::Revert => self.state = State::Idle,
         }
     }
 
     pub fn is_waiting_response(&self) -> bool {
         match self.state {
-            State::Idle => false,
+            State::Idle | State::RevertPrompt => false,
             State::Waiting => true,
-            State::ViewDiff => self.output.text().is_empty(),
+            State::ViewDiff | State::ViewContainingRefs => self.output.text().is_empty(),
+        }
+    }
+
+    pub fn selected_count(&self) -> usize {
+        self.entries.iter().filter(|e| e.selected).count()
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        match self.state {
+            State::ViewDiff | State::ViewContainingRefs => Some(self.output.text().into()),
+            _ => self
+                .entries
+                .get(self.select.cursor())
+                .map(|entry| entry.name.clone()),
         }
     }
 
     pub fn header(&self) -> (&str, &str, &str) {
         match self.state {
             State::Idle | State::Waiting => (
-                "revision details",
-                "[d]diff",
-                "[arrows]move [space]toggle [a]toggle all",
+                "details",
+                "[d]diff [f]file log [i]containing refs [V]revert commit",
+                "[arrows]move [space]toggle [a]toggle all [o]sort",
+            ),
+            State::ViewDiff => (
+                "diff",
+                "",
+                "[arrows]move [v]cursor line [z]center [W]wrap [shift+arrows]pan",
+            ),
+            State::ViewContainingRefs => ("containing refs", "", "[arrows]move"),
+            State::RevertPrompt => (
+                "revert commit",
+                "[enter]revert and commit [n]revert without committing",
+                "[esc]cancel",
             ),
-            State::ViewDiff => ("diff", "", "[arrows]move"),
         }
     }
 
@@ -190,6 +307,21 @@ impl Mode {
         };
 
         if let State::Idle = self.state {
+            if let Some(signature) = self.signature {
+                let color = match signature {
+                    SignatureStatus::Good => Color::DarkGreen,
+                    SignatureStatus::Bad => Color::DarkRed,
+                    SignatureStatus::Unverified => Color::DarkYellow,
+                    SignatureStatus::Unsigned => Color::DarkGray,
+                };
+                let foreground = drawer.theme.foreground;
+                drawer.fmt(format_args!(
+                    "{}[{}]{}",
+                    color,
+                    signature.as_str(),
+                    foreground
+                ));
+            }
             drawer.next_line();
             drawer.select_menu(
                 &self.select,
@@ -0,0 +1,250 @@
+use std::thread;
+
+use crate::{
+    backend::{Backend, BackendResult},
+    mode::{ModeContext, ModeKind, ModeResponse, ModeStatus, Output, ReadLine, SelectMenu},
+    platform::Key,
+    ui::{Drawer, RESERVED_LINES_COUNT},
+};
+
+pub enum Response {
+    Refresh(BackendResult<Option<Vec<String>>>),
+}
+
+enum WaitOperation {
+    Refresh,
+    Add,
+    Remove,
+    Enable,
+    Disable,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Waiting(WaitOperation),
+    NewPatternInput,
+    ConfirmEnable,
+}
+
+#[derive(Default)]
+pub struct Mode {
+    state: State,
+    enabled: bool,
+    patterns: Vec<String>,
+    output: Output,
+    select: SelectMenu,
+    readline: ReadLine,
+}
+impl Mode {
+    pub fn on_enter(&mut self, ctx: &ModeContext) {
+        if let State::Waiting(_) = self.state {
+            return;
+        }
+        self.state = State::Waiting(WaitOperation::Refresh);
+
+        self.output.set(String::new());
+        self.readline.clear();
+
+        request(ctx, |_| Ok(()));
+    }
+
+    pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
+        let pending_input = matches!(self.state, State::NewPatternInput | State::ConfirmEnable);
+        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+
+        match self.state {
+            State::Idle | State::Waiting(_) => {
+                if self.output.text().is_empty() {
+                    self.select
+                        .on_key(self.patterns.len(), available_height, key);
+                } else if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                } else {
+                    self.output.on_key(available_height, key);
+                }
+
+                let key = ctx.key_bindings.translate("sparse", key);
+                if let State::Idle = self.state {
+                    match key {
+                        Key::Char('n') => {
+                            self.state = State::NewPatternInput;
+                            self.output.set(String::new());
+                            self.readline.clear();
+                        }
+                        Key::Char('D') => {
+                            let index = self.select.cursor();
+                            if self.enabled && index < self.patterns.len() {
+                                self.state = State::Waiting(WaitOperation::Remove);
+                                let mut patterns = self.patterns.clone();
+                                patterns.remove(index);
+                                request(ctx, move |b| b.sparse_checkout_set(&patterns));
+                            }
+                        }
+                        Key::Char('E') => {
+                            if self.enabled {
+                                self.state = State::Waiting(WaitOperation::Disable);
+                                request(ctx, |b| b.sparse_checkout_disable());
+                            } else {
+                                self.state = State::ConfirmEnable;
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            State::NewPatternInput => {
+                self.readline.on_key(key);
+                if key.is_submit() {
+                    if !self.readline.input().is_empty() {
+                        self.state = State::Waiting(WaitOperation::Add);
+                        let mut patterns = self.patterns.clone();
+                        patterns.push(self.readline.input().into());
+                        request(ctx, move |b| b.sparse_checkout_set(&patterns));
+                    }
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::ConfirmEnable => match key {
+                Key::Char('y') => {
+                    self.state = State::Waiting(WaitOperation::Enable);
+                    request(ctx, |b| b.sparse_checkout_set(&[]));
+                }
+                Key::Char('n') => self.state = State::Idle,
+                _ if key.is_cancel() => self.state = State::Idle,
+                _ => (),
+            },
+        }
+
+        ModeStatus { pending_input }
+    }
+
+    pub fn on_paste(&mut self, text: &str) {
+        if let State::NewPatternInput = self.state {
+            self.readline.paste(text);
+        }
+    }
+
+    pub fn on_response(&mut self, response: Response) {
+        match response {
+            Response::Refresh(result) => {
+                self.patterns = Vec::new();
+                self.output.set(String::new());
+
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Idle;
+                }
+                if let State::Idle = self.state {
+                    match result {
+                        Ok(Some(patterns)) => {
+                            self.enabled = true;
+                            self.patterns = patterns;
+                        }
+                        Ok(None) => {
+                            self.enabled = false;
+                            self.output.set("sparse checkout is disabled\n".into());
+                        }
+                        Err(error) => self.output.set(error),
+                    }
+                }
+
+                self.select.saturate_cursor(self.patterns.len());
+            }
+        }
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
+    pub fn is_waiting_response(&self) -> bool {
+        match self.state {
+            State::Idle | State::NewPatternInput | State::ConfirmEnable => false,
+            State::Waiting(_) => true,
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.patterns.get(self.select.cursor()).cloned()
+        }
+    }
+
+    pub fn header(&self) -> (&str, &str, &str) {
+        let name = match self.state {
+            State::Idle | State::Waiting(WaitOperation::Refresh) => "sparse checkout",
+            State::Waiting(WaitOperation::Add) | State::NewPatternInput => "new pattern",
+            State::Waiting(WaitOperation::Remove) => "remove pattern",
+            State::Waiting(WaitOperation::Enable) | State::ConfirmEnable => {
+                "enable sparse checkout"
+            }
+            State::Waiting(WaitOperation::Disable) => "disable sparse checkout",
+        };
+        let (left_help, right_help) = match self.state {
+            State::Idle | State::Waiting(_) if self.enabled => {
+                ("[n]new pattern [D]remove [E]toggle enabled", "[arrows]move")
+            }
+            State::Idle | State::Waiting(_) => (
+                "[n]new pattern [E]toggle enabled (starts from root files only)",
+                "[arrows]move",
+            ),
+            State::ConfirmEnable => ("[y]enable", "[n]cancel"),
+            State::NewPatternInput => (
+                "",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+        };
+        (name, left_help, right_help)
+    }
+
+    pub fn draw(&self, drawer: &mut Drawer) {
+        match self.state {
+            State::Idle | State::Waiting(_) => {
+                if self.output.text().is_empty() {
+                    drawer.select_menu(
+                        &self.select,
+                        0,
+                        false,
+                        self.patterns.iter().map(String::as_str),
+                    );
+                } else {
+                    drawer.output(&self.output);
+                }
+            }
+            State::NewPatternInput => drawer.readline(&self.readline, "type in the pattern..."),
+            State::ConfirmEnable => drawer.str(
+                "enabling keeps only the files at the repository root until patterns are added. enable sparse checkout?",
+            ),
+        }
+    }
+}
+
+fn request<F>(ctx: &ModeContext, f: F)
+where
+    F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<()>,
+{
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        use std::ops::Deref;
+
+        let result = f(ctx.backend.deref());
+        let changed = result.is_ok();
+        let result = result.and_then(|_| ctx.backend.sparse_checkout_list());
+        ctx.event_sender
+            .send_response(ModeResponse::Sparse(Response::Refresh(result)));
+        if changed {
+            ctx.event_sender.send_mode_refresh(ModeKind::Status);
+        }
+    });
+}
@@ -0,0 +1,244 @@
+use std::thread;
+
+use crate::{
+    backend::{Backend, BackendResult, StashEntry},
+    mode::{ModeContext, ModeResponse, ModeStatus, Output, ReadLine, SelectMenu},
+    platform::Key,
+    ui::{Color, Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
+};
+
+pub enum Response {
+    Refresh(BackendResult<Vec<StashEntry>>),
+}
+
+enum WaitOperation {
+    Refresh,
+    Push,
+    Apply,
+    Pop,
+    Drop,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Idle,
+    Waiting(WaitOperation),
+    MessageInput,
+}
+
+impl SelectEntryDraw for StashEntry {
+    fn draw(&self, drawer: &mut Drawer, hovered: bool, _: bool) -> usize {
+        let foreground = drawer.theme.foreground;
+        let name_color = if hovered {
+            foreground
+        } else {
+            Color::DarkYellow
+        };
+        drawer.fmt(format_args!(
+            "{}{} {}{}",
+            name_color, self.name, foreground, self.message
+        ));
+        1
+    }
+}
+
+#[derive(Default)]
+pub struct Mode {
+    state: State,
+    entries: Vec<StashEntry>,
+    output: Output,
+    select: SelectMenu,
+    readline: ReadLine,
+}
+impl Mode {
+    pub fn on_enter(&mut self, ctx: &ModeContext) {
+        if let State::Waiting(_) = self.state {
+            return;
+        }
+        self.state = State::Waiting(WaitOperation::Refresh);
+
+        self.output.set(String::new());
+        self.readline.clear();
+
+        request(ctx, |_| Ok(()));
+    }
+
+    pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
+        let pending_input = matches!(self.state, State::MessageInput);
+        let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+
+        match self.state {
+            State::Idle | State::Waiting(_) => {
+                if self.output.text().is_empty() {
+                    self.select
+                        .on_key(self.entries.len(), available_height, key);
+                } else if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                } else {
+                    self.output.on_key(available_height, key);
+                }
+
+                let key = ctx.key_bindings.translate("stash", key);
+                if let State::Idle = self.state {
+                    match key {
+                        Key::Enter if !self.entries.is_empty() => self.output.set(String::new()),
+                        Key::Char('n') => {
+                            self.state = State::MessageInput;
+                            self.output.set(String::new());
+                            self.readline.clear();
+                        }
+                        Key::Char('a') => {
+                            let index = self.select.cursor();
+                            if let Some(entry) = self.entries.get(index) {
+                                self.state = State::Waiting(WaitOperation::Apply);
+                                let name = entry.name.clone();
+                                request(ctx, move |b| b.stash_apply(&name));
+                            }
+                        }
+                        Key::Char('p') => {
+                            let index = self.select.cursor();
+                            if let Some(entry) = self.entries.get(index) {
+                                self.state = State::Waiting(WaitOperation::Pop);
+                                let name = entry.name.clone();
+                                request(ctx, move |b| b.stash_pop(&name));
+                            }
+                        }
+                        Key::Char('D') => {
+                            let index = self.select.cursor();
+                            if let Some(entry) = self.entries.get(index) {
+                                self.state = State::Waiting(WaitOperation::Drop);
+                                let name = entry.name.clone();
+                                self.entries.remove(index);
+                                self.select.on_remove_entry(index);
+                                request(ctx, move |b| b.stash_drop(&name));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            State::MessageInput => {
+                self.readline.on_key(key);
+                if key.is_submit() {
+                    self.state = State::Waiting(WaitOperation::Push);
+                    let message = self.readline.input().to_string();
+                    request(ctx, move |b| b.stash_push(&message, &[]));
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+        }
+
+        ModeStatus { pending_input }
+    }
+
+    pub fn on_paste(&mut self, text: &str) {
+        if let State::MessageInput = self.state {
+            self.readline.paste(text);
+        }
+    }
+
+    pub fn on_response(&mut self, response: Response) {
+        match response {
+            Response::Refresh(result) => {
+                self.output.set(String::new());
+
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Idle;
+                }
+                if let State::Idle = self.state {
+                    match result {
+                        Ok(entries) => {
+                            self.entries = entries;
+                            if self.entries.is_empty() {
+                                self.output.set("no stashes\n".into());
+                            }
+                        }
+                        Err(error) => self.output.set(error),
+                    }
+                }
+
+                self.select.saturate_cursor(self.entries.len());
+            }
+        }
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
+    pub fn is_waiting_response(&self) -> bool {
+        match self.state {
+            State::Idle | State::MessageInput => false,
+            State::Waiting(_) => true,
+        }
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|entry| entry.name.clone())
+        }
+    }
+
+    pub fn header(&self) -> (&str, &str, &str) {
+        let name = match self.state {
+            State::Idle | State::Waiting(WaitOperation::Refresh) => "stash",
+            State::Waiting(WaitOperation::Push) | State::MessageInput => "new stash",
+            State::Waiting(WaitOperation::Apply) => "apply stash",
+            State::Waiting(WaitOperation::Pop) => "pop stash",
+            State::Waiting(WaitOperation::Drop) => "drop stash",
+        };
+        let (left_help, right_help) = match self.state {
+            State::Idle | State::Waiting(_) => ("[n]new [a]apply [p]pop [D]drop", "[arrows]move"),
+            State::MessageInput => (
+                "",
+                "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+        };
+        (name, left_help, right_help)
+    }
+
+    pub fn draw(&self, drawer: &mut Drawer) {
+        match self.state {
+            State::Idle | State::Waiting(_) => {
+                if self.output.text().is_empty() {
+                    drawer.select_menu(&self.select, 0, false, self.entries.iter());
+                } else {
+                    drawer.output(&self.output);
+                    if !self.entries.is_empty() {
+                        drawer.back_hint();
+                    }
+                }
+            }
+            State::MessageInput => {
+                drawer.readline(&self.readline, "type in the stash message (optional)...")
+            }
+        }
+    }
+}
+
+fn request<F>(ctx: &ModeContext, f: F)
+where
+    F: 'static + Send + Sync + FnOnce(&dyn Backend) -> BackendResult<()>,
+{
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        use std::ops::Deref;
+
+        let result = f(ctx.backend.deref()).and_then(|_| ctx.backend.stash_list());
+        ctx.event_sender
+            .send_response(ModeResponse::Stash(Response::Refresh(result)));
+    });
+}
@@ -1,38 +1,159 @@
-use std::thread;
+use std::{collections::HashSet, thread};
 
 use crate::{
-    backend::{Backend, BackendResult, RevisionEntry, SelectableRevisionEntry, StatusInfo},
+    backend::{
+        Backend, BackendResult, FileStatus, Operation, RerereStatus, RevisionEntry,
+        SelectableRevisionEntry, StatusInfo,
+    },
+    diff::{FileDiff, Hunk},
     mode::{
-        ModeContext, ModeKind, ModeResponse, ModeStatus, Output, ReadLine, SelectMenu,
-        SelectMenuAction,
+        log::{self, RetryPolicy},
+        sort_entries, EntrySort, ModeContext, ModeKind, ModeResponse, ModeStatus, Output,
+        OutputKind, ReadLine, SelectMenu, SelectMenuAction,
     },
     platform::Key,
-    ui::{Color, Drawer, RESERVED_LINES_COUNT},
+    ui::{Color, Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
 };
 
 pub enum Response {
     Refresh(StatusInfo),
+    Rerere(RerereStatus),
+    Operation(Option<Operation>),
     Commit,
+    LastCommitMessage(String),
+    Push,
+    Progress(String),
+    NeedsUpstream(String),
     Diff(String),
+    Hunks(BackendResult<FileDiff>),
 }
 
 enum WaitOperation {
     Refresh,
     Commit,
+    Amend,
+    Push,
     Discard,
     ResolveTakingLocal,
     ResolveTakingOther,
+    EnableRerere,
+    Continue,
+    Abort,
+    Skip,
+    Stash,
 }
 
+#[derive(Default)]
 enum State {
+    #[default]
     Idle,
     Waiting(WaitOperation),
     CommitMessageInput,
+    StashMessageInput,
+    ConfirmSetUpstream,
     ViewDiff,
+    ViewHunks,
+}
+
+#[derive(Clone, Copy, Default)]
+enum CommitKind {
+    #[default]
+    Selected,
+    All,
+    AndPush,
+    Amend,
+    Hunks,
+}
+
+struct EntryView<'a> {
+    entry: &'a SelectableRevisionEntry,
+    rerere_resolved: bool,
+    mode_only: bool,
+}
+impl SelectEntryDraw for EntryView<'_> {
+    fn draw(&self, drawer: &mut Drawer, hovered: bool, full: bool) -> usize {
+        let (suffix, color) = if self.rerere_resolved {
+            (" (rerere)", Color::DarkGreen)
+        } else if self.mode_only {
+            (" (mode change)", Color::DarkBlue)
+        } else {
+            return self.entry.draw(drawer, hovered, full);
+        };
+
+        let width = drawer.viewport_size.0;
+        drawer.viewport_size.0 = width.saturating_sub(suffix.len() as _);
+        let line_count = self.entry.draw(drawer, hovered, full);
+        drawer.viewport_size.0 = width;
+
+        let foreground = drawer.theme.foreground;
+        drawer.fmt(format_args!("{}{}{}", color, suffix, foreground));
+        line_count
+    }
+}
+
+struct HunkEntry {
+    hunk: Hunk,
+    selected: bool,
 }
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
+impl SelectEntryDraw for HunkEntry {
+    fn draw(&self, drawer: &mut Drawer, _: bool, full: bool) -> usize {
+        let width = drawer.viewport_size.0 as usize;
+        let theme = drawer.theme;
+
+        let (additions, deletions) = self.hunk.line_counts();
+        let selected_text = if self.selected { '+' } else { ' ' };
+        let counts = format!(" +{} -{}", additions, deletions);
+        let header = self.hunk.header();
+        let header = match header
+            .char_indices()
+            .nth(width.saturating_sub(2 + counts.len() + 1))
+        {
+            Some((i, _)) => &header[..i],
+            None => header,
+        };
+        drawer.fmt(format_args!(
+            "{} {}{}{}{}",
+            selected_text, header, theme.diff_add, counts, theme.foreground
+        ));
+
+        if !full {
+            return 1;
+        }
+
+        // keep the hovered hunk from pushing every other one off the screen
+        let max_body_lines = (drawer.viewport_size.1 as usize / 2).max(1);
+        let mut line_count = 1;
+        let mut body = self.hunk.body();
+        for line in body.by_ref().take(max_body_lines) {
+            drawer.next_line();
+            let color = if line.starts_with('+') {
+                theme.diff_add
+            } else if line.starts_with('-') {
+                theme.diff_remove
+            } else {
+                theme.foreground
+            };
+            let line = match line.char_indices().nth(width.saturating_sub(1)) {
+                Some((i, _)) => &line[..i],
+                None => line,
+            };
+            drawer.fmt(format_args!("{}{}{}", color, line, theme.foreground));
+            line_count += 1;
+        }
+
+        let remaining = body.count();
+        if remaining > 0 {
+            drawer.next_line();
+            drawer.fmt(format_args!(
+                "{}{} more line(s){}",
+                Color::DarkGray,
+                remaining,
+                theme.foreground
+            ));
+            line_count += 1;
+        }
+
+        line_count
     }
 }
 
@@ -43,8 +164,25 @@ pub struct Mode {
     output: Output,
     select: SelectMenu,
     readline: ReadLine,
+    rerere: RerereStatus,
+    mode_changes: Vec<String>,
+    operation: Option<Operation>,
+    sort: EntrySort,
+    commit_kind: CommitKind,
+    retry: RetryPolicy,
+    hunk_file: String,
+    hunks: Vec<HunkEntry>,
+    hunk_select: SelectMenu,
 }
 impl Mode {
+    pub fn new(sort: EntrySort, retry: RetryPolicy) -> Self {
+        Self {
+            sort,
+            retry,
+            ..Default::default()
+        }
+    }
+
     fn get_selected_entries(&self) -> Vec<RevisionEntry> {
         let entries: Vec<_> = self
             .entries
@@ -72,6 +210,13 @@ impl Mode {
         }
     }
 
+    fn enter_commit_message_input(&mut self, kind: CommitKind) {
+        self.state = State::CommitMessageInput;
+        self.commit_kind = kind;
+        self.output.set(String::new());
+        self.readline.clear();
+    }
+
     pub fn on_enter(&mut self, ctx: &ModeContext) {
         if let State::Waiting(_) = self.state {
             return;
@@ -85,12 +230,20 @@ impl Mode {
     }
 
     pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
-        let pending_input = matches!(self.state, State::CommitMessageInput);
+        let pending_input = matches!(
+            self.state,
+            State::CommitMessageInput | State::StashMessageInput | State::ConfirmSetUpstream
+        );
         let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
 
         match self.state {
             State::Idle | State::Waiting(_) => {
                 if self.output.line_count() > 1 {
+                    if self.output.on_search_key(available_height, key) {
+                        return ModeStatus {
+                            pending_input: true,
+                        };
+                    }
                     self.output.on_key(available_height, key);
                 } else {
                     match self
@@ -110,96 +263,285 @@ impl Mode {
                     }
                 }
 
+                let key = ctx.key_bindings.translate("status", key);
                 match key {
-                    Key::Char('c') => {
-                        if !self.entries.is_empty() {
-                            self.state = State::CommitMessageInput;
-                            self.output.set(String::new());
-                            self.readline.clear();
-                        }
+                    Key::Char('c') if !self.entries.is_empty() => {
+                        self.enter_commit_message_input(CommitKind::Selected);
                     }
-                    Key::Char('R') => {
-                        if matches!(self.state, State::Idle) && !self.entries.is_empty() {
-                            self.state = State::Waiting(WaitOperation::Discard);
-                            let entries = self.get_selected_entries();
-                            self.remove_selected_entries();
+                    Key::Char('x') if !self.entries.is_empty() => {
+                        self.enter_commit_message_input(CommitKind::All);
+                    }
+                    Key::Char('P') if !self.entries.is_empty() => {
+                        self.enter_commit_message_input(CommitKind::AndPush);
+                    }
+                    Key::Char('z')
+                        if matches!(self.state, State::Idle) && !self.entries.is_empty() =>
+                    {
+                        self.state = State::StashMessageInput;
+                        self.output.set(String::new());
+                        self.readline.clear();
+                    }
+                    Key::Char('m') => {
+                        if let State::Idle = self.state {
+                            self.enter_commit_message_input(CommitKind::Amend);
 
-                            request(ctx, move |b| b.discard(&entries));
+                            let ctx = ctx.clone();
+                            thread::spawn(move || {
+                                if let Ok(message) = ctx.backend.last_commit_message() {
+                                    ctx.event_sender.send_response(ModeResponse::Status(
+                                        Response::LastCommitMessage(message),
+                                    ));
+                                }
+                            });
                         }
                     }
-                    Key::Char('O') => {
-                        if matches!(self.state, State::Idle) && !self.entries.is_empty() {
-                            self.state = State::Waiting(WaitOperation::ResolveTakingLocal);
-                            let entries = self.get_selected_entries();
+                    Key::Char('R')
+                        if matches!(self.state, State::Idle) && !self.entries.is_empty() =>
+                    {
+                        self.state = State::Waiting(WaitOperation::Discard);
+                        let entries = self.get_selected_entries();
+                        self.remove_selected_entries();
 
-                            request(ctx, move |b| b.resolve_taking_ours(&entries));
-                        }
+                        request(ctx, move |b| b.discard(&entries));
                     }
-                    Key::Char('T') => {
-                        if matches!(self.state, State::Idle) && !self.entries.is_empty() {
-                            self.state = State::Waiting(WaitOperation::ResolveTakingOther);
-                            let entries = self.get_selected_entries();
+                    Key::Char('O')
+                        if matches!(self.state, State::Idle) && !self.entries.is_empty() =>
+                    {
+                        self.state = State::Waiting(WaitOperation::ResolveTakingLocal);
+                        let entries = self.get_selected_entries();
 
-                            request(ctx, move |b| b.resolve_taking_theirs(&entries));
+                        request(ctx, move |b| b.resolve_taking_ours(&entries));
+                    }
+                    Key::Char('T')
+                        if matches!(self.state, State::Idle) && !self.entries.is_empty() =>
+                    {
+                        self.state = State::Waiting(WaitOperation::ResolveTakingOther);
+                        let entries = self.get_selected_entries();
+
+                        request(ctx, move |b| b.resolve_taking_theirs(&entries));
+                    }
+                    Key::Char('C') => {
+                        if let State::Idle = self.state {
+                            self.state = State::Waiting(WaitOperation::Continue);
+                            request(ctx, |b| match b.in_progress_operation()? {
+                                Some(operation) => b.continue_operation(operation),
+                                None => Err("there is no operation in progress".into()),
+                            });
+                        }
+                    }
+                    Key::Char('A') => {
+                        if let State::Idle = self.state {
+                            match self.operation {
+                                Some(Operation::Rebase) => {
+                                    self.state = State::Waiting(WaitOperation::Abort);
+                                    request(ctx, |b| b.rebase_abort());
+                                }
+                                Some(Operation::CherryPick) => {
+                                    self.state = State::Waiting(WaitOperation::Abort);
+                                    request(ctx, |b| b.cherry_pick_abort());
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                    Key::Char('K') => {
+                        if let (State::Idle, Some(Operation::Rebase)) =
+                            (&self.state, self.operation)
+                        {
+                            self.state = State::Waiting(WaitOperation::Skip);
+                            request(ctx, |b| b.rebase_skip());
                         }
                     }
-                    Key::Char('d') => {
-                        if !self.entries.is_empty() {
-                            self.state = State::ViewDiff;
+                    Key::Char('o') => {
+                        self.sort = self.sort.next();
+                        sort_entries(self.sort, &mut self.entries, &mut self.select);
+                    }
+                    Key::Char('E') => {
+                        if let (State::Idle, RerereStatus::Disabled) = (&self.state, &self.rerere) {
+                            self.state = State::Waiting(WaitOperation::EnableRerere);
+                            request(ctx, |b| b.config_set("rerere.enabled", "true"));
+                        }
+                    }
+                    Key::Char('f') => {
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            ctx.event_sender
+                                .send_mode_change(ModeKind::FileLog(entry.name.clone()));
+                        }
+                    }
+                    Key::Char('H') => {
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            self.state = State::ViewHunks;
                             self.output.set(String::new());
+                            self.hunk_file = entry.name.clone();
+                            self.hunks.clear();
+                            self.hunk_select.set_cursor(0);
 
-                            let entries = self.get_selected_entries();
+                            let entries = [RevisionEntry {
+                                name: entry.name.clone(),
+                                status: entry.status.clone(),
+                            }];
 
                             let ctx = ctx.clone();
                             thread::spawn(move || {
-                                let output = match ctx.backend.diff(None, &entries) {
-                                    Ok(output) => output,
-                                    Err(error) => error,
-                                };
+                                let result = ctx
+                                    .backend
+                                    .diff(None, &entries)
+                                    .map(|diff| FileDiff::parse(&diff));
                                 ctx.event_sender
-                                    .send_response(ModeResponse::Status(Response::Diff(output)));
+                                    .send_response(ModeResponse::Status(Response::Hunks(result)));
                             });
                         }
                     }
+                    Key::Char('d') if !self.entries.is_empty() => {
+                        self.state = State::ViewDiff;
+                        self.output.set(String::new());
+
+                        let entries = self.get_selected_entries();
+
+                        let ctx = ctx.clone();
+                        thread::spawn(move || {
+                            let output = match ctx.backend.diff(None, &entries) {
+                                Ok(output) => output,
+                                Err(error) => error,
+                            };
+                            ctx.event_sender
+                                .send_response(ModeResponse::Status(Response::Diff(output)));
+                        });
+                    }
                     _ => (),
                 }
             }
             State::CommitMessageInput => {
                 self.readline.on_key(key);
                 if key.is_submit() {
-                    self.state = State::Waiting(WaitOperation::Commit);
+                    let kind = self.commit_kind;
+                    self.state = match kind {
+                        CommitKind::Amend => State::Waiting(WaitOperation::Amend),
+                        _ => State::Waiting(WaitOperation::Commit),
+                    };
 
                     let message = self.readline.input().to_string();
-                    let entries = self.get_selected_entries();
-                    self.remove_selected_entries();
+                    if let CommitKind::Hunks = kind {
+                        let file = std::mem::take(&mut self.hunk_file);
+                        let hunk_indices: Vec<_> = self
+                            .hunks
+                            .drain(..)
+                            .enumerate()
+                            .filter(|(_, h)| h.selected)
+                            .map(|(i, _)| i)
+                            .collect();
 
+                        let retry = self.retry;
+                        let ctx = ctx.clone();
+                        thread::spawn(move || {
+                            let result = ctx.backend.commit_hunks(&message, &file, &hunk_indices);
+                            on_commit(&ctx, kind, retry, result);
+                        });
+                        return ModeStatus { pending_input };
+                    }
+
+                    let entries = if let CommitKind::All = kind {
+                        self.entries.clear();
+                        self.select.set_cursor(0);
+                        Vec::new()
+                    } else {
+                        let entries = self.get_selected_entries();
+                        self.remove_selected_entries();
+                        entries
+                    };
+
+                    let retry = self.retry;
                     let ctx = ctx.clone();
                     thread::spawn(move || {
-                        ctx.event_sender.send_mode_change(ModeKind::Log);
-                        match ctx.backend.commit(&message, &entries) {
-                            Ok(()) => {
-                                ctx.event_sender
-                                    .send_response(ModeResponse::Status(Response::Commit));
-                                ctx.event_sender.send_mode_refresh(ModeKind::Log);
-                            }
-                            Err(error) => ctx.event_sender.send_response(ModeResponse::Status(
-                                Response::Refresh(StatusInfo {
-                                    header: error,
-                                    entries: Vec::new(),
-                                }),
-                            )),
-                        }
+                        let result = match kind {
+                            CommitKind::Amend => ctx.backend.commit_amend(&message, &entries),
+                            _ => ctx.backend.commit(&message, &entries),
+                        };
+                        on_commit(&ctx, kind, retry, result);
                     });
                 } else if key.is_cancel() {
                     self.on_enter(ctx);
                 }
             }
-            _ => self.output.on_key(available_height, key),
+            State::StashMessageInput => {
+                self.readline.on_key(key);
+                if key.is_submit() {
+                    self.state = State::Waiting(WaitOperation::Stash);
+                    let message = self.readline.input().to_string();
+                    let entries = self.get_selected_entries();
+                    self.remove_selected_entries();
+
+                    request(ctx, move |b| b.stash_push(&message, &entries));
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::ConfirmSetUpstream => match key {
+                Key::Char('y') => {
+                    self.state = State::Waiting(WaitOperation::Push);
+                    self.output.set(String::new());
+
+                    let retry = self.retry;
+                    let ctx = ctx.clone();
+                    thread::spawn(move || push(&ctx, retry, Backend::push_set_upstream));
+                }
+                Key::Char('n') => self.on_enter(ctx),
+                _ if key.is_cancel() => self.on_enter(ctx),
+                _ => (),
+            },
+            State::ViewHunks => {
+                if self.hunks.is_empty() {
+                    if self.output.on_search_key(available_height, key) {
+                        return ModeStatus {
+                            pending_input: true,
+                        };
+                    }
+                    self.output.on_key(available_height, key);
+                } else {
+                    match self
+                        .hunk_select
+                        .on_key(self.hunks.len(), available_height, key)
+                    {
+                        SelectMenuAction::None => (),
+                        SelectMenuAction::Toggle(i) => {
+                            self.hunks[i].selected = !self.hunks[i].selected
+                        }
+                        SelectMenuAction::ToggleAll => {
+                            let all_selected = self.hunks.iter().all(|h| h.selected);
+                            for hunk in &mut self.hunks {
+                                hunk.selected = !all_selected;
+                            }
+                        }
+                    }
+                }
+
+                match ctx.key_bindings.translate("status", key) {
+                    Key::Char('c') if self.hunks.iter().any(|h| h.selected) => {
+                        self.enter_commit_message_input(CommitKind::Hunks);
+                    }
+                    Key::Enter => self.on_enter(ctx),
+                    _ => (),
+                }
+            }
+            _ => {
+                if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
+                }
+                self.output.on_key(available_height, key);
+            }
         }
 
         ModeStatus { pending_input }
     }
 
+    pub fn on_paste(&mut self, text: &str) {
+        if let State::CommitMessageInput | State::StashMessageInput = self.state {
+            self.readline.paste(text);
+        }
+    }
+
     pub fn on_response(&mut self, response: Response) {
         match response {
             Response::Refresh(info) => {
@@ -210,49 +552,175 @@ impl Mode {
                     self.output.set(info.header);
                 }
 
+                let selected_names: HashSet<_> = self
+                    .entries
+                    .drain(..)
+                    .filter(|e| e.selected)
+                    .map(|e| e.name)
+                    .collect();
                 self.entries = info.entries.into_iter().map(Into::into).collect();
+                self.mode_changes = info.mode_changes;
+                for entry in &mut self.entries {
+                    entry.selected = selected_names.contains(&entry.name);
+                }
+                self.sort.sort(&mut self.entries);
                 self.select.saturate_cursor(self.entries.len());
             }
+            Response::Rerere(rerere) => self.rerere = rerere,
+            Response::Operation(operation) => self.operation = operation,
             Response::Commit => self.state = State::Idle,
+            Response::LastCommitMessage(message) => {
+                if let (State::CommitMessageInput, CommitKind::Amend) =
+                    (&self.state, self.commit_kind)
+                {
+                    if self.readline.input().is_empty() {
+                        self.readline.set_input(message.trim_end());
+                    }
+                }
+            }
+            Response::Push => {
+                if let State::Waiting(_) = self.state {
+                    self.state = State::Waiting(WaitOperation::Push);
+                    self.output.set(String::new());
+                }
+            }
+            Response::Progress(line) => {
+                if let State::Waiting(WaitOperation::Push) = self.state {
+                    self.output.set(log::progress_text(&line));
+                }
+            }
+            Response::NeedsUpstream(error) => {
+                if let State::Waiting(WaitOperation::Push) = self.state {
+                    self.state = State::ConfirmSetUpstream;
+                    self.output.set(error);
+                }
+            }
             Response::Diff(mut output) => {
                 if let State::ViewDiff = self.state {
                     if output.is_empty() {
                         output.push('\n');
                     }
                     self.output.set(output);
+                    self.output.set_kind(OutputKind::Diff);
+                }
+            }
+            Response::Hunks(result) => {
+                if let State::ViewHunks = self.state {
+                    match result {
+                        Ok(file_diff) if !file_diff.hunks.is_empty() => {
+                            self.hunks = file_diff
+                                .hunks
+                                .into_iter()
+                                .map(|hunk| HunkEntry {
+                                    hunk,
+                                    selected: false,
+                                })
+                                .collect();
+                        }
+                        Ok(_) => self.output.set(format!(
+                            "{} has no hunks to stage. untracked and binary files can only be committed whole\n",
+                            self.hunk_file
+                        )),
+                        Err(error) => self.output.set(error),
+                    }
                 }
             }
         }
     }
 
+    pub fn is_following_output(&self) -> bool {
+        self.output.is_following()
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
+        }
+        waiting
+    }
+
     pub fn is_waiting_response(&self) -> bool {
         match self.state {
-            State::Idle | State::CommitMessageInput => false,
+            State::Idle
+            | State::CommitMessageInput
+            | State::StashMessageInput
+            | State::ConfirmSetUpstream => false,
             State::Waiting(_) => true,
             State::ViewDiff => self.output.text().is_empty(),
+            State::ViewHunks => self.hunks.is_empty() && self.output.text().is_empty(),
+        }
+    }
+
+    pub fn selected_count(&self) -> usize {
+        self.entries.iter().filter(|e| e.selected).count()
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if let State::ViewHunks = self.state {
+            if let Some(entry) = self.hunks.get(self.hunk_select.cursor()) {
+                return Some(entry.hunk.text().into());
+            }
+        }
+        if self.output.line_count() > 1 {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|entry| entry.name.clone())
         }
     }
 
     pub fn header(&self) -> (&str, &str, &str) {
         let name = match self.state {
             State::Idle | State::Waiting(WaitOperation::Refresh) => "status",
-            State::CommitMessageInput => "commit message",
+            State::CommitMessageInput => match self.commit_kind {
+                CommitKind::Selected => "commit message",
+                CommitKind::All => "commit all message",
+                CommitKind::AndPush => "commit and push message",
+                CommitKind::Amend => "amend message",
+                CommitKind::Hunks => "commit hunks message",
+            },
             State::Waiting(WaitOperation::Commit) => "commit",
+            State::Waiting(WaitOperation::Amend) => "amend commit",
+            State::Waiting(WaitOperation::Push) => "push",
+            State::ConfirmSetUpstream => "set upstream",
             State::Waiting(WaitOperation::Discard) => "discard",
             State::Waiting(WaitOperation::ResolveTakingLocal) => "resolve taking local",
             State::Waiting(WaitOperation::ResolveTakingOther) => "resolve taking other",
+            State::Waiting(WaitOperation::EnableRerere) => "enable rerere",
+            State::Waiting(WaitOperation::Continue) => "continue",
+            State::Waiting(WaitOperation::Abort) => "abort",
+            State::Waiting(WaitOperation::Skip) => "skip",
+            State::StashMessageInput => "stash message",
+            State::Waiting(WaitOperation::Stash) => "stash",
             State::ViewDiff => "diff",
+            State::ViewHunks => "hunks",
         };
-        let (left_help, right_help) = match self.state {
-            State::Idle | State::Waiting(_) => (
-                "[c]commit [R]revert [d]diff [L]take local [O]take other",
-                "[arrows]move [space]toggle [a]toggle all",
+        let (left_help, right_help) = match (&self.state, self.operation) {
+            (State::Idle | State::Waiting(_), Some(Operation::Rebase)) => (
+                "[c]commit [x]commit all [P]commit and push [m]amend [R]revert changes [d]diff [H]hunks [z]stash [f]file log [O]take local [T]take other [C]continue [A]abort [K]skip",
+                "[arrows]move [space]toggle [a]toggle all [o]sort",
+            ),
+            (State::Idle | State::Waiting(_), Some(Operation::CherryPick)) => (
+                "[c]commit [x]commit all [P]commit and push [m]amend [R]revert changes [d]diff [H]hunks [z]stash [f]file log [O]take local [T]take other [C]continue [A]abort",
+                "[arrows]move [space]toggle [a]toggle all [o]sort",
+            ),
+            (State::Idle | State::Waiting(_), _) => (
+                "[c]commit [x]commit all [P]commit and push [m]amend [R]revert changes [d]diff [H]hunks [z]stash [f]file log [O]take local [T]take other [C]continue",
+                "[arrows]move [space]toggle [a]toggle all [o]sort",
             ),
-            State::CommitMessageInput => (
+            (State::CommitMessageInput | State::StashMessageInput, _) => (
                 "",
                 "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
             ),
-            State::ViewDiff => ("", "[arrows]move"),
+            (State::ConfirmSetUpstream, _) => ("[y]set upstream and push", "[n]cancel"),
+            (State::ViewDiff, _) => ("", "[arrows]move [v]cursor line [z]center [W]wrap [shift+arrows]pan"),
+            (State::ViewHunks, _) => (
+                "[c]commit selected hunks [enter]back",
+                "[arrows]move [space]toggle [a]toggle all",
+            ),
         };
         (name, left_help, right_help)
     }
@@ -274,8 +742,41 @@ impl Mode {
 
                     drawer.str(output);
                     drawer.next_line();
+                    let foreground = drawer.theme.foreground;
+                    match &self.rerere {
+                        RerereStatus::Unavailable => (),
+                        RerereStatus::Disabled => drawer.fmt(format_args!(
+                            "{}rerere is disabled. press [E] to enable it{}",
+                            Color::DarkGray,
+                            foreground
+                        )),
+                        RerereStatus::Enabled { resolved } => {
+                            if !resolved.is_empty() {
+                                drawer.fmt(format_args!(
+                                    "{}rerere replayed {} recorded resolution(s){}",
+                                    Color::DarkGreen,
+                                    resolved.len(),
+                                    foreground
+                                ));
+                            }
+                        }
+                    }
                     drawer.next_line();
-                    drawer.select_menu(&self.select, 2, false, self.entries.iter());
+
+                    let resolved: &[String] = match &self.rerere {
+                        RerereStatus::Enabled { resolved } => resolved,
+                        _ => &[],
+                    };
+                    drawer.select_menu(
+                        &self.select,
+                        2,
+                        false,
+                        self.entries.iter().map(|entry| EntryView {
+                            entry,
+                            rerere_resolved: resolved.contains(&entry.name),
+                            mode_only: self.mode_changes.contains(&entry.name),
+                        }),
+                    );
 
                     if self.entries.is_empty() {
                         let empty_message = match self.state {
@@ -289,9 +790,26 @@ impl Mode {
             State::CommitMessageInput => {
                 drawer.readline(&self.readline, "type in the commit message...")
             }
+            State::StashMessageInput => {
+                drawer.readline(&self.readline, "type in the stash message...")
+            }
+            State::ConfirmSetUpstream => {
+                drawer.output(&self.output);
+                drawer.next_line();
+                drawer.str("the current branch has no upstream. set it and push?");
+            }
             State::ViewDiff => {
                 drawer.output(&self.output);
             }
+            State::ViewHunks => {
+                if self.hunks.is_empty() {
+                    drawer.output(&self.output);
+                } else {
+                    drawer.str(&self.hunk_file);
+                    drawer.next_line();
+                    drawer.select_menu(&self.hunk_select, 1, true, self.hunks.iter());
+                }
+            }
         }
     }
 }
@@ -304,17 +822,123 @@ where
     thread::spawn(move || {
         use std::ops::Deref;
 
-        let mut info = match f(ctx.backend.deref()).and_then(|_| ctx.backend.status()) {
-            Ok(info) => info,
+        let info = match f(ctx.backend.deref()).and_then(|_| ctx.backend.status()) {
+            Ok(info) => {
+                ctx.event_sender
+                    .send_title_change(info.header.clone(), Some(!info.entries.is_empty()));
+                info
+            }
             Err(error) => StatusInfo {
                 header: error,
                 entries: Vec::new(),
+                mode_changes: Vec::new(),
             },
         };
-        info.entries
-            .sort_unstable_by(|a, b| a.status.cmp(&b.status));
 
+        let has_conflicts = info
+            .entries
+            .iter()
+            .any(|e| matches!(e.status, FileStatus::Unmerged));
+        let rerere = if has_conflicts {
+            ctx.backend.rerere_status().unwrap_or_default()
+        } else {
+            RerereStatus::Unavailable
+        };
+
+        let operation = ctx.backend.in_progress_operation().unwrap_or(None);
+        ctx.event_sender.send_operation_change(operation);
+        ctx.event_sender
+            .send_response(ModeResponse::Status(Response::Operation(operation)));
+        ctx.event_sender
+            .send_response(ModeResponse::Status(Response::Rerere(rerere)));
         ctx.event_sender
             .send_response(ModeResponse::Status(Response::Refresh(info)));
     });
 }
+
+fn on_commit(ctx: &ModeContext, kind: CommitKind, retry: RetryPolicy, result: BackendResult<()>) {
+    match result {
+        Ok(()) => {
+            if let CommitKind::AndPush = kind {
+                ctx.event_sender
+                    .send_response(ModeResponse::Status(Response::Push));
+                push(ctx, retry, Backend::push);
+            } else {
+                ctx.event_sender.send_mode_change(ModeKind::Log);
+                ctx.event_sender
+                    .send_response(ModeResponse::Status(Response::Commit));
+                ctx.event_sender.send_mode_refresh(ModeKind::Log);
+            }
+        }
+        Err(error) => refresh_with_header(ctx, commit_error_message(error)),
+    }
+}
+
+fn push<F>(ctx: &ModeContext, retry: RetryPolicy, f: F)
+where
+    F: Fn(&dyn Backend, &mut dyn FnMut(&str)) -> BackendResult<String>,
+{
+    use std::ops::Deref;
+
+    let event_sender = ctx.event_sender.clone();
+    let result = log::retry_with_progress(
+        ctx.backend.deref(),
+        retry,
+        &mut |line| event_sender.send_response(ModeResponse::Status(Response::Progress(line))),
+        f,
+    );
+
+    match result {
+        // stay here so the warnings are not wiped by the log refresh
+        Ok(diagnostics) if !diagnostics.is_empty() => refresh_with_header(
+            ctx,
+            format!("pushed with warnings:\n{}", diagnostics.trim_end()),
+        ),
+        Ok(_) => {
+            ctx.event_sender.send_mode_change(ModeKind::Log);
+            ctx.event_sender
+                .send_response(ModeResponse::Status(Response::Commit));
+            ctx.event_sender.send_mode_refresh(ModeKind::Log);
+        }
+        Err(error) if needs_upstream(&error) => ctx
+            .event_sender
+            .send_response(ModeResponse::Status(Response::NeedsUpstream(error))),
+        Err(error) => refresh_with_header(
+            ctx,
+            format!("committed, but push failed:\n{}", error.trim_end()),
+        ),
+    }
+}
+
+fn refresh_with_header(ctx: &ModeContext, header: String) {
+    let info = match ctx.backend.status() {
+        Ok(info) => StatusInfo { header, ..info },
+        Err(_) => StatusInfo {
+            header,
+            entries: Vec::new(),
+            mode_changes: Vec::new(),
+        },
+    };
+    ctx.event_sender
+        .send_response(ModeResponse::Status(Response::Refresh(info)));
+}
+
+fn needs_upstream(error: &str) -> bool {
+    const NO_UPSTREAM_PATTERNS: &[&str] = &["has no upstream branch", "--set-upstream"];
+    NO_UPSTREAM_PATTERNS.iter().any(|p| error.contains(p))
+}
+
+fn commit_error_message(error: String) -> String {
+    const NOTHING_TO_COMMIT_PATTERNS: &[&str] = &[
+        "nothing to commit",
+        "nothing added to commit",
+        "no changes added to commit",
+        "nothing changed",
+    ];
+
+    if NOTHING_TO_COMMIT_PATTERNS.iter().any(|p| error.contains(p)) {
+        "nothing to commit!".into()
+    } else {
+        error
+    }
+}
@@ -2,7 +2,10 @@ use std::thread;
 
 use crate::{
     backend::{Backend, BackendResult, TagEntry},
-    mode::{ModeContext, ModeKind, ModeResponse, ModeStatus, Output, ReadLine, SelectMenu},
+    mode::{
+        is_valid_ref_name, ModeContext, ModeKind, ModeResponse, ModeStatus, Output, ReadLine,
+        SelectMenu,
+    },
     platform::Key,
     ui::{Drawer, SelectEntryDraw, RESERVED_LINES_COUNT},
 };
@@ -10,23 +13,26 @@ use crate::{
 pub enum Response {
     Refresh(BackendResult<Vec<TagEntry>>),
     Checkout,
+    AlreadyExists,
 }
 
 enum WaitOperation {
     Refresh,
     New,
     Delete,
+    DeleteRemote,
 }
 
+#[derive(Default)]
 enum State {
+    #[default]
     Idle,
     Waiting(WaitOperation),
     NewNameInput,
-}
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
+    MessageInput,
+    ConfirmOverwrite,
+    ConfirmDelete(String),
+    ConfirmDeleteRemote(String),
 }
 
 impl SelectEntryDraw for TagEntry {
@@ -43,6 +49,7 @@ pub struct Mode {
     output: Output,
     select: SelectMenu,
     readline: ReadLine,
+    message_readline: ReadLine,
 }
 impl Mode {
     pub fn on_enter(&mut self, ctx: &ModeContext) {
@@ -53,24 +60,31 @@ impl Mode {
 
         self.output.set(String::new());
         self.readline.clear();
+        self.message_readline.clear();
 
         request(ctx, |_| Ok(()));
     }
 
     pub fn on_key(&mut self, ctx: &ModeContext, key: Key) -> ModeStatus {
-        let pending_input = matches!(self.state, State::NewNameInput);
+        let pending_input = !matches!(self.state, State::Idle | State::Waiting(_));
         let available_height = (ctx.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
 
-        match self.state {
+        match &self.state {
             State::Idle | State::Waiting(_) => {
                 if self.output.text().is_empty() {
                     self.select
                         .on_key(self.entries.len(), available_height, key);
+                } else if self.output.on_search_key(available_height, key) {
+                    return ModeStatus {
+                        pending_input: true,
+                    };
                 } else {
                     self.output.on_key(available_height, key);
                 }
 
+                let key = ctx.key_bindings.translate("tags", key);
                 match key {
+                    Key::Enter if !self.entries.is_empty() => self.output.set(String::new()),
                     Key::Char('g') => {
                         let index = self.select.cursor();
                         if let Some(entry) = self.entries.get(index) {
@@ -97,14 +111,15 @@ impl Mode {
                         self.readline.clear();
                     }
                     Key::Char('D') => {
-                        let index = self.select.cursor();
-                        if let Some(entry) = self.entries.get(index) {
-                            self.state = State::Waiting(WaitOperation::Delete);
-
-                            let name = entry.name.clone();
-                            self.entries.remove(index);
-                            self.select.on_remove_entry(index);
-                            request(ctx, move |b| b.delete_tag(&name));
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            self.state = State::ConfirmDelete(entry.name.clone());
+                            self.output.set(String::new());
+                        }
+                    }
+                    Key::Char('R') => {
+                        if let Some(entry) = self.entries.get(self.select.cursor()) {
+                            self.state = State::ConfirmDeleteRemote(entry.name.clone());
+                            self.output.set(String::new());
                         }
                     }
                     _ => (),
@@ -112,22 +127,99 @@ impl Mode {
             }
             State::NewNameInput => {
                 self.readline.on_key(key);
+                if key.is_submit() && is_valid_ref_name(self.readline.input()) {
+                    self.state = State::MessageInput;
+                    self.message_readline.clear();
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
+                }
+            }
+            State::MessageInput => {
+                self.message_readline.on_key(key);
                 if key.is_submit() {
                     self.state = State::Waiting(WaitOperation::New);
 
                     let name = self.readline.input().to_string();
-                    request(ctx, move |b| b.new_tag(&name));
+                    let message = self.message();
+                    let ctx = ctx.clone();
+                    thread::spawn(move || match ctx.backend.tag_exists(&name) {
+                        Ok(true) => ctx
+                            .event_sender
+                            .send_response(ModeResponse::Tags(Response::AlreadyExists)),
+                        Ok(false) => {
+                            request(&ctx, move |b| b.new_tag(&name, message.as_deref(), false))
+                        }
+                        Err(error) => ctx
+                            .event_sender
+                            .send_response(ModeResponse::Tags(Response::Refresh(Err(error)))),
+                    });
+                } else if key.is_cancel() {
+                    self.on_enter(ctx);
                 }
             }
+            State::ConfirmOverwrite => match key {
+                Key::Char('y') => {
+                    self.state = State::Waiting(WaitOperation::New);
+
+                    let name = self.readline.input().to_string();
+                    let message = self.message();
+                    request(ctx, move |b| b.new_tag(&name, message.as_deref(), true));
+                }
+                Key::Char('n') => self.on_enter(ctx),
+                _ if key.is_cancel() => self.on_enter(ctx),
+                _ => (),
+            },
+            State::ConfirmDelete(name) => match key {
+                Key::Char('y') => {
+                    let name = name.clone();
+                    self.state = State::Waiting(WaitOperation::Delete);
+
+                    if let Some(index) = self.entries.iter().position(|e| e.name == name) {
+                        self.entries.remove(index);
+                        self.select.on_remove_entry(index);
+                    }
+                    request(ctx, move |b| b.delete_tag(&name));
+                }
+                Key::Char('n') => self.state = State::Idle,
+                _ if key.is_cancel() => self.state = State::Idle,
+                _ => (),
+            },
+            State::ConfirmDeleteRemote(name) => match key {
+                Key::Char('y') => {
+                    let name = name.clone();
+                    self.state = State::Waiting(WaitOperation::DeleteRemote);
+                    request(ctx, move |b| b.delete_remote_tag(&name));
+                }
+                Key::Char('n') => self.state = State::Idle,
+                _ if key.is_cancel() => self.state = State::Idle,
+                _ => (),
+            },
         }
 
         ModeStatus { pending_input }
     }
 
+    // an empty message creates a lightweight tag
+    fn message(&self) -> Option<String> {
+        let message = self.message_readline.input().trim();
+        if message.is_empty() {
+            None
+        } else {
+            Some(message.into())
+        }
+    }
+
+    pub fn on_paste(&mut self, text: &str) {
+        match self.state {
+            State::NewNameInput => self.readline.paste(text),
+            State::MessageInput => self.message_readline.paste(text),
+            _ => (),
+        }
+    }
+
     pub fn on_response(&mut self, response: Response) {
         match response {
             Response::Refresh(result) => {
-                self.entries = Vec::new();
                 self.output.set(String::new());
 
                 if let State::Waiting(_) = self.state {
@@ -143,43 +235,105 @@ impl Mode {
                 self.select.saturate_cursor(self.entries.len());
             }
             Response::Checkout => self.state = State::Idle,
+            Response::AlreadyExists => {
+                if let State::Waiting(_) = self.state {
+                    self.state = State::ConfirmOverwrite;
+                }
+            }
+        }
+    }
+
+    // gives up on a pending refresh, the application drops its late response
+    pub fn cancel_refresh(&mut self) -> bool {
+        let waiting = matches!(self.state, State::Waiting(WaitOperation::Refresh));
+        if waiting {
+            self.state = State::Idle;
         }
+        waiting
     }
 
     pub fn is_waiting_response(&self) -> bool {
         match self.state {
-            State::Idle | State::NewNameInput => false,
+            State::Idle
+            | State::NewNameInput
+            | State::MessageInput
+            | State::ConfirmOverwrite
+            | State::ConfirmDelete(_)
+            | State::ConfirmDeleteRemote(_) => false,
             State::Waiting(_) => true,
         }
     }
 
+    pub fn clipboard_text(&self) -> Option<String> {
+        if !self.output.text().is_empty() {
+            Some(self.output.text().into())
+        } else {
+            self.entries
+                .get(self.select.cursor())
+                .map(|entry| entry.name.clone())
+        }
+    }
+
     pub fn header(&self) -> (&str, &str, &str) {
         let name = match self.state {
             State::Idle | State::Waiting(WaitOperation::Refresh) => "tags",
             State::Waiting(WaitOperation::New) => "new tag",
-            State::Waiting(WaitOperation::Delete) => "delete tag",
+            State::Waiting(WaitOperation::Delete) | State::ConfirmDelete(_) => "delete tag",
+            State::Waiting(WaitOperation::DeleteRemote) | State::ConfirmDeleteRemote(_) => {
+                "delete remote tag"
+            }
             State::NewNameInput => "new tag name",
+            State::MessageInput => "new tag message",
+            State::ConfirmOverwrite => "overwrite tag",
         };
         let (left_help, right_help) = match self.state {
-            State::Idle | State::Waiting(_) => ("[g]checkout [n]new [D]delete", "[arrows]move"),
+            State::Idle | State::Waiting(_) => (
+                "[g]checkout [n]new [D]delete [R]delete remote",
+                "[arrows]move",
+            ),
             State::NewNameInput => (
                 "",
                 "[enter]submit [esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
             ),
+            State::MessageInput => (
+                "[enter]submit (leave empty for a lightweight tag)",
+                "[esc]cancel [ctrl+w]delete word [ctrl+u]delete all",
+            ),
+            State::ConfirmOverwrite => ("[y]overwrite", "[n]cancel"),
+            State::ConfirmDelete(_) | State::ConfirmDeleteRemote(_) => ("[y]delete", "[n]cancel"),
         };
         (name, left_help, right_help)
     }
 
     pub fn draw(&self, drawer: &mut Drawer) {
-        match self.state {
+        match &self.state {
             State::Idle | State::Waiting(_) => {
                 if self.output.text.is_empty() {
                     drawer.select_menu(&self.select, 0, false, self.entries.iter());
                 } else {
                     drawer.output(&self.output);
+                    if !self.entries.is_empty() {
+                        drawer.back_hint();
+                    }
                 }
             }
-            State::NewNameInput => drawer.readline(&self.readline, "type in the tag name..."),
+            State::NewNameInput => drawer.validated_readline(
+                &self.readline,
+                "type in the tag name...",
+                self.readline.input().is_empty() || is_valid_ref_name(self.readline.input()),
+            ),
+            State::MessageInput => drawer.readline(
+                &self.message_readline,
+                "type in the tag message or leave it empty...",
+            ),
+            State::ConfirmOverwrite => drawer.fmt(format_args!(
+                "tag '{}' already exists. overwrite it?",
+                self.readline.input()
+            )),
+            State::ConfirmDelete(name) => drawer.fmt(format_args!("delete tag '{}'?", name)),
+            State::ConfirmDeleteRemote(name) => {
+                drawer.fmt(format_args!("delete tag '{}' from the remote?", name))
+            }
         }
     }
 }
@@ -14,11 +14,12 @@ use winapi::{
         processenv::GetStdHandle,
         winbase::{FILE_TYPE_CHAR, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE},
         wincon::{
-            GetConsoleScreenBufferInfo, ENABLE_PROCESSED_OUTPUT,
+            GetConsoleScreenBufferInfo, ENABLE_MOUSE_INPUT, ENABLE_PROCESSED_OUTPUT,
             ENABLE_VIRTUAL_TERMINAL_PROCESSING, ENABLE_WINDOW_INPUT,
         },
         wincontypes::{
-            KEY_EVENT, LEFT_ALT_PRESSED, LEFT_CTRL_PRESSED, RIGHT_ALT_PRESSED, RIGHT_CTRL_PRESSED,
+            FROM_LEFT_1ST_BUTTON_PRESSED, KEY_EVENT, LEFT_ALT_PRESSED, LEFT_CTRL_PRESSED,
+            MOUSE_EVENT, MOUSE_WHEELED, RIGHT_ALT_PRESSED, RIGHT_CTRL_PRESSED, SHIFT_PRESSED,
             WINDOW_BUFFER_SIZE_EVENT,
         },
         winnt::HANDLE,
@@ -29,12 +30,14 @@ use winapi::{
     },
 };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Key {
     Backspace,
     Enter,
     Left,
     Right,
+    ShiftLeft,
+    ShiftRight,
     Up,
     Down,
     Home,
@@ -46,6 +49,11 @@ pub enum Key {
     Char(char),
     Ctrl(char),
     Esc,
+    ScrollUp,
+    ScrollDown,
+    Click(u16, u16),
+    // a default key whose action was rebound, it triggers nothing
+    Unbound,
 }
 impl Key {
     pub fn is_submit(&self) -> bool {
@@ -66,7 +74,7 @@ pub struct Platform {
 
 #[cfg(unix)]
 impl Platform {
-    pub fn new() -> Option<(Self, PlatformEventReader)> {
+    pub fn new(_mouse: bool) -> Option<(Self, PlatformEventReader)> {
         let is_pipped = unsafe { libc::isatty(libc::STDIN_FILENO) == 0 };
         if is_pipped {
             return None;
@@ -75,7 +83,7 @@ impl Platform {
         let original = unsafe {
             let mut original = std::mem::zeroed();
             libc::tcgetattr(libc::STDIN_FILENO, &mut original);
-            let mut new = original.clone();
+            let mut new = original;
             new.c_iflag &= !(libc::IGNBRK
                 | libc::BRKINT
                 | libc::PARMRK
@@ -99,6 +107,10 @@ impl Platform {
         Some((Self { original }, PlatformEventReader::new(backspace_code)))
     }
 
+    pub fn is_stdout_terminal() -> bool {
+        unsafe { libc::isatty(libc::STDOUT_FILENO) != 0 }
+    }
+
     pub fn terminal_size() -> (u16, u16) {
         let mut size: libc::winsize = unsafe { std::mem::zeroed() };
         let result = unsafe {
@@ -127,6 +139,7 @@ impl Drop for Platform {
 pub struct PlatformEventReader {
     backspace_code: u8,
     buf: Vec<u8>,
+    paste: Option<Vec<u8>>,
     queue_fd: RawFd,
     resize_signal_fd: Option<RawFd>,
 }
@@ -145,10 +158,58 @@ impl PlatformEventReader {
         }
     }
 
-    fn parse_terminal_keys(mut buf: &[u8], backspace_code: u8, keys: &mut Vec<Key>) {
+    // parses the sgr mouse encoding: `button;column;row` followed by `M` on press or `m` on release
+    fn parse_mouse_event(buf: &[u8]) -> Option<(Option<Key>, &[u8])> {
+        let end = buf.iter().position(|&b| b == b'M' || b == b'm')?;
+        let params = std::str::from_utf8(&buf[..end]).ok()?;
+        let mut params = params.split(';').map(|p| p.parse::<u16>().unwrap_or(0));
+        let button = params.next()?;
+        let x = params.next()?.saturating_sub(1);
+        let y = params.next()?.saturating_sub(1);
+        let pressed = buf[end] == b'M';
+
+        let key = match button {
+            64 => Some(Key::ScrollUp),
+            65 => Some(Key::ScrollDown),
+            0 if pressed => Some(Key::Click(x, y)),
+            _ => None,
+        };
+        Some((key, &buf[end + 1..]))
+    }
+
+    fn parse_terminal_keys(
+        mut buf: &[u8],
+        backspace_code: u8,
+        paste: &mut Option<Vec<u8>>,
+        keys: &mut Vec<Key>,
+        pastes: &mut Vec<String>,
+    ) {
+        const PASTE_END: &[u8] = b"\x1b[201~";
+
         loop {
+            if let Some(pasted) = paste {
+                match buf.windows(PASTE_END.len()).position(|w| w == PASTE_END) {
+                    Some(end) => {
+                        pasted.extend_from_slice(&buf[..end]);
+                        pastes.push(String::from_utf8_lossy(pasted).into_owned());
+                        *paste = None;
+                        buf = &buf[end + PASTE_END.len()..];
+                        continue;
+                    }
+                    None => {
+                        pasted.extend_from_slice(buf);
+                        break;
+                    }
+                }
+            }
+
             let (key, rest) = match buf {
                 &[] => break,
+                &[0x1b, b'[', b'2', b'0', b'0', b'~', ref rest @ ..] => {
+                    *paste = Some(Vec::new());
+                    buf = rest;
+                    continue;
+                }
                 &[b, ref rest @ ..] if b == backspace_code => (Key::Backspace, rest),
                 &[0x1b, b'[', b'5', b'~', ref rest @ ..] => (Key::PageUp, rest),
                 &[0x1b, b'[', b'6', b'~', ref rest @ ..] => (Key::PageDown, rest),
@@ -156,6 +217,10 @@ impl PlatformEventReader {
                 &[0x1b, b'[', b'B', ref rest @ ..] => (Key::Down, rest),
                 &[0x1b, b'[', b'C', ref rest @ ..] => (Key::Right, rest),
                 &[0x1b, b'[', b'D', ref rest @ ..] => (Key::Left, rest),
+                &[0x1b, b'[', b'1', b';', b'2', b'C', ref rest @ ..]
+                | &[0x1b, b'[', b'c', ref rest @ ..] => (Key::ShiftRight, rest),
+                &[0x1b, b'[', b'1', b';', b'2', b'D', ref rest @ ..]
+                | &[0x1b, b'[', b'd', ref rest @ ..] => (Key::ShiftLeft, rest),
                 &[0x1b, b'[', b'1', b'~', ref rest @ ..]
                 | &[0x1b, b'[', b'7', b'~', ref rest @ ..]
                 | &[0x1b, b'[', b'H', ref rest @ ..]
@@ -165,6 +230,14 @@ impl PlatformEventReader {
                 | &[0x1b, b'[', b'F', ref rest @ ..]
                 | &[0x1b, b'O', b'F', ref rest @ ..] => (Key::End, rest),
                 &[0x1b, b'[', b'3', b'~', ref rest @ ..] => (Key::Delete, rest),
+                &[0x1b, b'[', b'<', ref rest @ ..] => match Self::parse_mouse_event(rest) {
+                    Some((Some(key), rest)) => (key, rest),
+                    Some((None, rest)) => {
+                        buf = rest;
+                        continue;
+                    }
+                    None => break,
+                },
                 &[0x1b, ref rest @ ..] => (Key::Esc, rest),
                 &[0x8, ref rest @ ..] => (Key::Backspace, rest),
                 &[b'\r', ref rest @ ..] => (Key::Enter, rest),
@@ -265,6 +338,7 @@ impl PlatformEventReader {
         Self {
             backspace_code,
             buf,
+            paste: None,
             queue_fd,
             resize_signal_fd,
         }
@@ -276,7 +350,12 @@ impl PlatformEventReader {
         }
     }
 
-    pub fn read_terminal_events(&mut self, keys: &mut Vec<Key>, resize: &mut Option<(u16, u16)>) {
+    pub fn read_terminal_events(
+        &mut self,
+        keys: &mut Vec<Key>,
+        pastes: &mut Vec<String>,
+        resize: &mut Option<(u16, u16)>,
+    ) {
         fn epoll_wait<'a>(
             epoll_fd: RawFd,
             events: &'a mut [libc::epoll_event],
@@ -303,9 +382,13 @@ impl PlatformEventReader {
             match event_index {
                 0 => match Self::read(libc::STDIN_FILENO, &mut self.buf) {
                     Ok(0) | Err(()) => panic!("could not read from stdin"),
-                    Ok(len) => {
-                        Self::parse_terminal_keys(&self.buf[..len], self.backspace_code, keys)
-                    }
+                    Ok(len) => Self::parse_terminal_keys(
+                        &self.buf[..len],
+                        self.backspace_code,
+                        &mut self.paste,
+                        keys,
+                        pastes,
+                    ),
                 },
                 1 => {
                     if let Some(fd) = self.resize_signal_fd {
@@ -370,6 +453,7 @@ impl PlatformEventReader {
         Self {
             backspace_code,
             buf: Vec::with_capacity(1024),
+            paste: None,
             queue_fd,
             resize_signal_fd: None,
         }
@@ -387,7 +471,12 @@ impl PlatformEventReader {
         Self::modify_kqueue(self.queue_fd, &resize_event);
     }
 
-    pub fn read_terminal_events(&mut self, keys: &mut Vec<Key>, resize: &mut Option<(u16, u16)>) {
+    pub fn read_terminal_events(
+        &mut self,
+        keys: &mut Vec<Key>,
+        pastes: &mut Vec<String>,
+        resize: &mut Option<(u16, u16)>,
+    ) {
         struct TriggeredEvent {
             pub index: usize,
             pub data: isize,
@@ -444,9 +533,13 @@ impl PlatformEventReader {
                     self.buf.resize(data as _, 0);
                     match Self::read(libc::STDIN_FILENO, &mut self.buf) {
                         Ok(0) | Err(()) => panic!("could not read from stdin"),
-                        Ok(len) => {
-                            Self::parse_terminal_keys(&self.buf[..len], self.backspace_code, keys)
-                        }
+                        Ok(len) => Self::parse_terminal_keys(
+                            &self.buf[..len],
+                            self.backspace_code,
+                            &mut self.paste,
+                            keys,
+                            pastes,
+                        ),
                     }
                 }
                 Ok(TriggeredEvent { index: 1, .. }) => *resize = Some(Platform::terminal_size()),
@@ -467,7 +560,7 @@ pub struct Platform {
 
 #[cfg(windows)]
 impl Platform {
-    pub fn new() -> Option<(Self, PlatformEventReader)> {
+    pub fn new(mouse: bool) -> Option<(Self, PlatformEventReader)> {
         let input_handle = Self::get_std_handle(STD_INPUT_HANDLE)?;
         let output_handle = Self::get_std_handle(STD_OUTPUT_HANDLE)?;
 
@@ -476,7 +569,12 @@ impl Platform {
             return None;
         }
 
-        let input_handle_original_mode = Self::swap_console_mode(input_handle, ENABLE_WINDOW_INPUT);
+        let input_mode = if mouse {
+            ENABLE_WINDOW_INPUT | ENABLE_MOUSE_INPUT
+        } else {
+            ENABLE_WINDOW_INPUT
+        };
+        let input_handle_original_mode = Self::swap_console_mode(input_handle, input_mode);
         let output_handle_original_mode = Self::swap_console_mode(
             output_handle,
             ENABLE_PROCESSED_OUTPUT | ENABLE_VIRTUAL_TERMINAL_PROCESSING,
@@ -491,6 +589,13 @@ impl Platform {
         ))
     }
 
+    pub fn is_stdout_terminal() -> bool {
+        match Self::get_std_handle(STD_OUTPUT_HANDLE) {
+            Some(handle) => unsafe { GetFileType(handle) == FILE_TYPE_CHAR },
+            None => false,
+        }
+    }
+
     pub fn terminal_size() -> (u16, u16) {
         let output_handle = match Self::get_std_handle(STD_OUTPUT_HANDLE) {
             Some(handle) => handle,
@@ -551,7 +656,12 @@ pub struct PlatformEventReader;
 impl PlatformEventReader {
     pub fn init(&mut self) {}
 
-    pub fn read_terminal_events(&mut self, keys: &mut Vec<Key>, resize: &mut Option<(u16, u16)>) {
+    pub fn read_terminal_events(
+        &mut self,
+        keys: &mut Vec<Key>,
+        _: &mut Vec<String>,
+        resize: &mut Option<(u16, u16)>,
+    ) {
         let input_handle = match Platform::get_std_handle(STD_INPUT_HANDLE) {
             Some(handle) => handle,
             None => return,
@@ -589,6 +699,8 @@ impl PlatformEventReader {
                     let key = match keycode {
                         VK_BACK => Key::Backspace,
                         VK_RETURN => Key::Enter,
+                        VK_LEFT if control_key_state & SHIFT_PRESSED != 0 => Key::ShiftLeft,
+                        VK_RIGHT if control_key_state & SHIFT_PRESSED != 0 => Key::ShiftRight,
                         VK_LEFT => Key::Left,
                         VK_RIGHT => Key::Right,
                         VK_UP => Key::Up,
@@ -634,6 +746,24 @@ impl PlatformEventReader {
                         keys.push(key);
                     }
                 }
+                MOUSE_EVENT => {
+                    let event = unsafe { event.Event.MouseEvent() };
+                    let key = if event.dwEventFlags & MOUSE_WHEELED != 0 {
+                        if (event.dwButtonState >> 16) as i16 > 0 {
+                            Key::ScrollUp
+                        } else {
+                            Key::ScrollDown
+                        }
+                    } else if event.dwEventFlags == 0
+                        && event.dwButtonState & FROM_LEFT_1ST_BUTTON_PRESSED != 0
+                    {
+                        let position = event.dwMousePosition;
+                        Key::Click(position.X as _, position.Y as _)
+                    } else {
+                        continue;
+                    };
+                    keys.push(key);
+                }
                 WINDOW_BUFFER_SIZE_EVENT => {
                     let size = unsafe { event.Event.WindowBufferSizeEvent().dwSize };
                     *resize = Some((size.X as _, size.Y as _));
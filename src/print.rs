@@ -0,0 +1,158 @@
+use std::fmt::Write;
+
+use crate::{
+    backend::{Backend, BackendResult, FileStatus, LogFilter, LogOrder},
+    date,
+    mode::ModeKind,
+    ui::Color,
+};
+
+const LOG_LEN: usize = 50;
+const RESET_COLOR_CODE: &str = "\x1b[0m";
+
+struct Painter {
+    enabled: bool,
+}
+impl Painter {
+    fn color(&self, color: Color) -> String {
+        if self.enabled {
+            color.to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    fn reset(&self) -> &'static str {
+        if self.enabled {
+            RESET_COLOR_CODE
+        } else {
+            ""
+        }
+    }
+}
+
+pub fn print(backend: &dyn Backend, mode: &ModeKind, colored: bool) -> BackendResult<String> {
+    let painter = Painter { enabled: colored };
+    let mut output = String::new();
+
+    match mode {
+        ModeKind::Status => {
+            let info = backend.status()?;
+            let _ = writeln!(output, "{}", info.header);
+            for entry in &info.entries {
+                let color = match entry.status {
+                    FileStatus::Added | FileStatus::Untracked => Color::DarkGreen,
+                    FileStatus::Deleted | FileStatus::Missing => Color::DarkRed,
+                    FileStatus::Unmerged => Color::DarkMagenta,
+                    _ => Color::DarkYellow,
+                };
+                let _ = writeln!(
+                    output,
+                    "{}[{:>width$}]{} {}",
+                    painter.color(color),
+                    entry.status.as_str(),
+                    painter.reset(),
+                    entry.name,
+                    width = FileStatus::max_len(),
+                );
+            }
+        }
+        ModeKind::Log => {
+            let now = date::now();
+            for entry in backend.log(0, LOG_LEN, LogOrder::Default, true, &LogFilter::default())? {
+                let refs = match &entry.refs[..] {
+                    "" => String::new(),
+                    refs => format!("{}({}) ", painter.color(Color::DarkRed), refs),
+                };
+                let _ = writeln!(
+                    output,
+                    "{} {}{} {}{} {}{} {}{}{}",
+                    entry.graph,
+                    painter.color(Color::DarkYellow),
+                    entry.hash,
+                    painter.color(Color::DarkBlue),
+                    date::format_relative(entry.timestamp, now),
+                    painter.color(Color::DarkGreen),
+                    entry.author,
+                    refs,
+                    painter.reset(),
+                    entry.message.lines().next().unwrap_or(""),
+                );
+            }
+        }
+        ModeKind::Branches => {
+            for entry in backend.branches()? {
+                let marker = if entry.checked_out { '*' } else { ' ' };
+                let _ = writeln!(output, "{} {}", marker, entry.name);
+            }
+        }
+        ModeKind::Tags => {
+            for entry in backend.tags()? {
+                let _ = writeln!(output, "{}", entry.name);
+            }
+        }
+        ModeKind::Stash => {
+            for entry in backend.stash_list()? {
+                let _ = writeln!(
+                    output,
+                    "{}{}{} {}",
+                    painter.color(Color::DarkYellow),
+                    entry.name,
+                    painter.reset(),
+                    entry.message
+                );
+            }
+        }
+        ModeKind::Reflog => {
+            for entry in backend.reflog(LOG_LEN)? {
+                let old_hash = match &entry.old_hash[..] {
+                    "" => "-",
+                    old_hash => old_hash,
+                };
+                let _ = writeln!(
+                    output,
+                    "{}{} {}{}..{}{} {}",
+                    painter.color(Color::DarkRed),
+                    entry.selector,
+                    painter.color(Color::DarkYellow),
+                    old_hash,
+                    entry.new_hash,
+                    painter.reset(),
+                    entry.action,
+                );
+            }
+        }
+        ModeKind::Remotes => {
+            for entry in backend.remote_list()? {
+                let name = format!(
+                    "{}{}{}",
+                    painter.color(Color::DarkGreen),
+                    entry.name,
+                    painter.reset()
+                );
+                if entry.fetch_url == entry.push_url {
+                    let _ = writeln!(output, "{} {}", name, entry.fetch_url);
+                } else {
+                    let _ = writeln!(
+                        output,
+                        "{} {} (fetch) {} (push)",
+                        name, entry.fetch_url, entry.push_url
+                    );
+                }
+            }
+        }
+        ModeKind::Sparse => match backend.sparse_checkout_list()? {
+            Some(patterns) => {
+                for pattern in patterns {
+                    let _ = writeln!(output, "{}", pattern);
+                }
+            }
+            None => return Err("sparse checkout is disabled".into()),
+        },
+        ModeKind::RevisionDetails(_) | ModeKind::FileLog(_) | ModeKind::Clone => {
+            return Err("this mode can not be printed".into());
+        }
+    }
+
+    Ok(output)
+}
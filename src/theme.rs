@@ -0,0 +1,93 @@
+use crate::ui::Color;
+
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub background: Color,
+    pub foreground: Color,
+    pub header_background: Color,
+    pub header_foreground: Color,
+    pub highlight: Color,
+    pub diff_add: Color,
+    pub diff_remove: Color,
+}
+impl Theme {
+    pub const DARK: Self = Self {
+        background: Color::Black,
+        foreground: Color::White,
+        header_background: Color::Black,
+        header_foreground: Color::DarkYellow,
+        highlight: Color::DarkMagenta,
+        diff_add: Color::DarkGreen,
+        diff_remove: Color::DarkRed,
+    };
+
+    pub const LIGHT: Self = Self {
+        background: Color::White,
+        foreground: Color::Black,
+        header_background: Color::Indexed(254),
+        header_foreground: Color::DarkBlue,
+        highlight: Color::Indexed(153),
+        diff_add: Color::DarkGreen,
+        diff_remove: Color::DarkRed,
+    };
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::DARK),
+            "light" => Some(Self::LIGHT),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&mut self, text: &str) -> Vec<(usize, String)> {
+        let mut warnings = Vec::new();
+        let mut colors = Vec::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut splits = line.splitn(2, '=');
+            let key = splits.next().unwrap_or("").trim();
+            let value = splits.next().unwrap_or("").trim().trim_matches('"');
+
+            if key == "theme" {
+                match Self::from_name(value) {
+                    Some(theme) => *self = theme,
+                    None => warnings.push((line_index, format!("unknown theme '{}'", value))),
+                }
+            } else {
+                colors.push((line_index, key, value));
+            }
+        }
+
+        // individual colors override the base theme wherever it appears in the file
+        for (line_index, key, value) in colors {
+            let slot = match key {
+                "background" => &mut self.background,
+                "foreground" => &mut self.foreground,
+                "header_background" => &mut self.header_background,
+                "header_foreground" => &mut self.header_foreground,
+                "highlight" => &mut self.highlight,
+                "diff_add" => &mut self.diff_add,
+                "diff_remove" => &mut self.diff_remove,
+                _ => {
+                    warnings.push((line_index, format!("unknown color '{}'", key)));
+                    continue;
+                }
+            };
+            match Color::from_name(value) {
+                Some(color) => *slot = color,
+                None => warnings.push((line_index, format!("invalid color '{}'", value))),
+            }
+        }
+
+        warnings
+    }
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
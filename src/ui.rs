@@ -1,12 +1,21 @@
 use std::fmt;
 
-use crate::mode::{Output, ReadLine, SelectMenu};
+use crate::{
+    config::KeyBindings,
+    mode::{Form, Output, OutputKind, ReadLine, SelectMenu},
+    theme::Theme,
+    unicode::is_grapheme_extend,
+};
 
 pub const HEADER_LINE_COUNT: usize = 2;
 pub const RESERVED_LINES_COUNT: usize = HEADER_LINE_COUNT + 1;
 
 pub static ENTER_ALTERNATE_BUFFER_CODE: &[u8] = b"\x1b[?1049h";
 pub static EXIT_ALTERNATE_BUFFER_CODE: &[u8] = b"\x1b[?1049l";
+pub static ENABLE_BRACKETED_PASTE_CODE: &[u8] = b"\x1b[?2004h";
+pub static DISABLE_BRACKETED_PASTE_CODE: &[u8] = b"\x1b[?2004l";
+pub static ENABLE_MOUSE_CODE: &[u8] = b"\x1b[?1000h\x1b[?1006h";
+pub static DISABLE_MOUSE_CODE: &[u8] = b"\x1b[?1006l\x1b[?1000l";
 pub static HIDE_CURSOR_CODE: &[u8] = b"\x1b[?25l";
 pub static SHOW_CURSOR_CODE: &[u8] = b"\x1b[?25h";
 pub static RESET_STYLE_CODE: &[u8] = b"\x1b[0;49m";
@@ -25,21 +34,152 @@ pub fn move_cursor_to_zero(buf: &mut Vec<u8>) {
     buf.extend_from_slice(b"\x1b[0;0H");
 }
 
+pub fn move_cursor_to(buf: &mut Vec<u8>, x: usize, y: usize) {
+    use std::io::Write;
+    write!(buf, "\x1b[{};{}H", y + 1, x + 1).unwrap();
+}
+
 pub fn move_cursor_to_next_line(buf: &mut Vec<u8>) {
     buf.extend_from_slice(b"\x1b[1E");
 }
 
 pub fn set_background_color(buf: &mut Vec<u8>, color: Color) {
-    buf.extend_from_slice(b"\x1b[48;5;");
-    buf.extend_from_slice(color.code().as_bytes());
-    buf.push(b'm');
+    use std::io::Write;
+    write!(buf, "\x1b[48;{}m", color.code()).unwrap();
 }
 
-static BEGIN_FOREGROUND_COLOR_CODE: &str = "\x1b[38;5;";
 pub fn set_foreground_color(buf: &mut Vec<u8>, color: Color) {
-    buf.extend_from_slice(BEGIN_FOREGROUND_COLOR_CODE.as_bytes());
-    buf.extend_from_slice(color.code().as_bytes());
-    buf.push(b'm');
+    use std::io::Write;
+    write!(buf, "{}", color).unwrap();
+}
+
+pub fn set_bold(buf: &mut Vec<u8>, bold: bool) {
+    if bold {
+        buf.extend_from_slice(b"\x1b[1m");
+    } else {
+        buf.extend_from_slice(b"\x1b[22m");
+    }
+}
+
+const TAB_WIDTH: usize = 4;
+
+// approximates the east asian width property, wide chars take two terminal
+// columns and combining marks take none
+pub fn char_display_width(c: char) -> usize {
+    if c.is_control() || is_grapheme_extend(c) || matches!(c, '\u{200b}' | '\u{2060}') {
+        return 0;
+    }
+    let wide = matches!(
+        c,
+        '\u{1100}'..='\u{115f}'
+            | '\u{231a}'..='\u{231b}'
+            | '\u{2329}'..='\u{232a}'
+            | '\u{23e9}'..='\u{23ec}'
+            | '\u{25fd}'..='\u{25fe}'
+            | '\u{2614}'..='\u{2615}'
+            | '\u{26a1}'
+            | '\u{26bd}'..='\u{26be}'
+            | '\u{2705}'
+            | '\u{274c}'
+            | '\u{2753}'..='\u{2755}'
+            | '\u{2757}'
+            | '\u{2b50}'
+            | '\u{2e80}'..='\u{303e}'
+            | '\u{3041}'..='\u{33ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{a000}'..='\u{a4cf}'
+            | '\u{a960}'..='\u{a97f}'
+            | '\u{ac00}'..='\u{d7a3}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{fe10}'..='\u{fe19}'
+            | '\u{fe30}'..='\u{fe6f}'
+            | '\u{ff00}'..='\u{ff60}'
+            | '\u{ffe0}'..='\u{ffe6}'
+            | '\u{16fe0}'..='\u{18aff}'
+            | '\u{1b000}'..='\u{1b2ff}'
+            | '\u{1f004}'
+            | '\u{1f0cf}'
+            | '\u{1f18e}'
+            | '\u{1f191}'..='\u{1f19a}'
+            | '\u{1f200}'..='\u{1f251}'
+            | '\u{1f300}'..='\u{1f64f}'
+            | '\u{1f680}'..='\u{1f6ff}'
+            | '\u{1f7e0}'..='\u{1f7eb}'
+            | '\u{1f90c}'..='\u{1f9ff}'
+            | '\u{1fa70}'..='\u{1faff}'
+            | '\u{20000}'..='\u{2fffd}'
+            | '\u{30000}'..='\u{3fffd}'
+    );
+    if wide {
+        2
+    } else {
+        1
+    }
+}
+
+pub fn line_display_width(line: &str) -> usize {
+    line.chars()
+        .map(|c| match c {
+            '\t' => TAB_WIDTH,
+            c => char_display_width(c),
+        })
+        .sum()
+}
+
+fn truncated_line_marker(glyphs: &Glyphs, more_cols: usize) -> String {
+    format!(
+        "{}(line truncated, {} more cols)",
+        glyphs.ellipsis, more_cols
+    )
+}
+
+fn is_diff_header(line: &str) -> bool {
+    // file headers are told apart from added and removed lines by their a/ and b/ prefixes
+    // which are always present since diff.noprefix and diff.mnemonicPrefix are overridden
+    const HEADER_PREFIXES: &[&str] = &[
+        "diff ",
+        "index ",
+        "--- a/",
+        "+++ b/",
+        "--- /dev/null",
+        "+++ /dev/null",
+        "new file mode ",
+        "deleted file mode ",
+        "similarity index ",
+        "rename from ",
+        "rename to ",
+        "Binary files ",
+    ];
+    HEADER_PREFIXES.iter().any(|p| line.starts_with(p))
+}
+
+fn output_line_color(theme: &Theme, line: &str, kind: OutputKind) -> Option<Color> {
+    const ERROR_PREFIXES: &[&str] = &["fatal:", "error:", "abort:"];
+    const WARNING_PREFIXES: &[&str] = &["warning:", "hint:"];
+
+    let message = line.trim_start_matches("remote: ").trim_start();
+    if line.starts_with("old mode ") || line.starts_with("new mode ") {
+        Some(Color::DarkBlue)
+    } else if let OutputKind::Diff = kind {
+        if is_diff_header(line) {
+            Some(theme.foreground)
+        } else if line.starts_with("@@") {
+            Some(Color::DarkCyan)
+        } else if line.starts_with('+') {
+            Some(theme.diff_add)
+        } else if line.starts_with('-') {
+            Some(theme.diff_remove)
+        } else {
+            None
+        }
+    } else if ERROR_PREFIXES.iter().any(|p| message.starts_with(p)) {
+        Some(Color::DarkRed)
+    } else if WARNING_PREFIXES.iter().any(|p| message.starts_with(p)) {
+        Some(Color::DarkYellow)
+    } else {
+        None
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -50,45 +190,197 @@ pub enum Color {
     DarkYellow,
     DarkBlue,
     DarkMagenta,
+    DarkCyan,
     DarkGray,
     White,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
 }
 impl Color {
-    fn code(&self) -> &str {
-        match self {
-            Self::Black => "0",
-            Self::DarkRed => "1",
-            Self::DarkGreen => "2",
-            Self::DarkYellow => "3",
-            Self::DarkBlue => "4",
-            Self::DarkMagenta => "5",
-            Self::DarkGray => "8",
-            Self::White => "15",
+    // accepts a color name, a 256 color palette index or a #rrggbb hex code
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "black" => return Some(Self::Black),
+            "red" => return Some(Self::DarkRed),
+            "green" => return Some(Self::DarkGreen),
+            "yellow" => return Some(Self::DarkYellow),
+            "blue" => return Some(Self::DarkBlue),
+            "magenta" => return Some(Self::DarkMagenta),
+            "cyan" => return Some(Self::DarkCyan),
+            "gray" => return Some(Self::DarkGray),
+            "white" => return Some(Self::White),
+            _ => (),
         }
+
+        if let Some(hex) = name.strip_prefix('#') {
+            if hex.len() != 6 || !hex.is_ascii() {
+                return None;
+            }
+            let component = |i| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Self::Rgb(component(0)?, component(2)?, component(4)?));
+        }
+
+        name.parse().ok().map(Self::Indexed)
+    }
+
+    fn code(&self) -> ColorCode {
+        ColorCode(*self)
     }
 }
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(BEGIN_FOREGROUND_COLOR_CODE)?;
-        f.write_str(self.code())?;
-        f.write_str("m")?;
-        Ok(())
+        write!(f, "\x1b[38;{}m", self.code())
+    }
+}
+
+struct ColorCode(Color);
+impl fmt::Display for ColorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let index = match self.0 {
+            Color::Black => 0,
+            Color::DarkRed => 1,
+            Color::DarkGreen => 2,
+            Color::DarkYellow => 3,
+            Color::DarkBlue => 4,
+            Color::DarkMagenta => 5,
+            Color::DarkCyan => 6,
+            Color::DarkGray => 8,
+            Color::White => 15,
+            Color::Indexed(index) => index,
+            Color::Rgb(r, g, b) => return write!(f, "2;{};{};{}", r, g, b),
+        };
+        write!(f, "5;{}", index)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum SpinnerStyle {
+    #[default]
+    Ascii,
+    Braille,
+    Disabled,
+}
+impl SpinnerStyle {
+    pub const NAMES: &'static [&'static str] = &["ascii", "braille", "none"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ascii" => Some(Self::Ascii),
+            "braille" => Some(Self::Braille),
+            "none" => Some(Self::Disabled),
+            _ => None,
+        }
+    }
+
+    pub fn frames(&self) -> &'static [char] {
+        match self {
+            Self::Ascii => &['-', '\\', '|', '/'],
+            Self::Braille => &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+            Self::Disabled => &['*'],
+        }
+    }
+}
+
+pub struct Glyphs {
+    pub ellipsis: &'static str,
+    graph_replacements: &'static [(char, char)],
+    ascii: bool,
+}
+impl Glyphs {
+    pub const UNICODE: Self = Self {
+        ellipsis: "…",
+        graph_replacements: &[],
+        ascii: false,
+    };
+    pub const ASCII: Self = Self {
+        ellipsis: "...",
+        graph_replacements: &[
+            ('│', '|'),
+            ('─', '-'),
+            ('╭', '/'),
+            ('╮', '\\'),
+            ('╯', '/'),
+            ('╰', '\\'),
+            ('├', '|'),
+            ('┤', '|'),
+            ('┼', '+'),
+            ('○', 'o'),
+            ('●', '*'),
+            ('◉', '@'),
+        ],
+        ascii: true,
+    };
+
+    pub fn spinner_frames(&self, style: SpinnerStyle) -> &'static [char] {
+        match style {
+            SpinnerStyle::Braille if self.ascii => SpinnerStyle::Ascii.frames(),
+            _ => style.frames(),
+        }
+    }
+
+    pub fn graph_char(&self, c: char) -> char {
+        match self.graph_replacements.iter().find(|&&(from, _)| from == c) {
+            Some(&(_, to)) => to,
+            None => c,
+        }
     }
 }
 
 pub trait SelectEntryDraw {
     fn draw(&self, drawer: &mut Drawer, hovered: bool, full: bool) -> usize;
 }
+impl<T> SelectEntryDraw for &T
+where
+    T: ?Sized + SelectEntryDraw,
+{
+    fn draw(&self, drawer: &mut Drawer, hovered: bool, full: bool) -> usize {
+        (*self).draw(drawer, hovered, full)
+    }
+}
+
+impl SelectEntryDraw for str {
+    fn draw(&self, drawer: &mut Drawer, _: bool, _: bool) -> usize {
+        drawer.str(self);
+        1
+    }
+}
+
+pub const DEFAULT_MAX_LINE_ROWS: usize = 8;
 
 pub struct Drawer {
     buf: Vec<u8>,
     pub viewport_size: (u16, u16),
+    pub glyphs: &'static Glyphs,
+    pub theme: Theme,
+    max_line_rows: usize,
+    overflow: (usize, usize),
+    status_line: Option<(String, Color)>,
 }
 
 impl Drawer {
-    pub fn new(mut buf: Vec<u8>, viewport_size: (u16, u16)) -> Self {
+    pub fn new(
+        mut buf: Vec<u8>,
+        viewport_size: (u16, u16),
+        glyphs: &'static Glyphs,
+        theme: Theme,
+        max_line_rows: usize,
+    ) -> Self {
         buf.clear();
-        Self { buf, viewport_size }
+        Self {
+            buf,
+            viewport_size,
+            glyphs,
+            theme,
+            max_line_rows,
+            overflow: (0, 0),
+            status_line: None,
+        }
+    }
+
+    pub fn title(&mut self, title: &str) {
+        self.buf.extend_from_slice(BEGIN_TITLE_CODE);
+        self.buf.extend_from_slice(title.as_bytes());
+        self.buf.extend_from_slice(END_TITLE_CODE);
     }
 
     pub fn take_buf(self) -> Vec<u8> {
@@ -96,40 +388,67 @@ impl Drawer {
     }
 
     pub fn clear_to_bottom(&mut self) {
-        set_background_color(&mut self.buf, Color::Black);
+        set_background_color(&mut self.buf, self.theme.background);
         clear_to_end(&mut self.buf);
+
+        let y = (self.viewport_size.1 as usize).saturating_sub(1);
+        if let Some((status, color)) = self.status_line.take() {
+            move_cursor_to(&mut self.buf, 0, y);
+            set_foreground_color(&mut self.buf, color);
+            self.buf.extend_from_slice(status.as_bytes());
+            set_foreground_color(&mut self.buf, self.theme.foreground);
+        }
+
+        let text = match self.overflow {
+            (0, 0) => return,
+            (above, 0) => format!(" {} more above ", above),
+            (0, below) => format!(" {} more below ", below),
+            (above, below) => format!(" {} more above, {} more below ", above, below),
+        };
+        let x = (self.viewport_size.0 as usize).saturating_sub(text.len());
+        move_cursor_to(&mut self.buf, x, y);
+        set_foreground_color(&mut self.buf, Color::DarkGray);
+        self.buf.extend_from_slice(text.as_bytes());
+        set_foreground_color(&mut self.buf, self.theme.foreground);
     }
 
     pub fn header(
         &mut self,
         current_mode_name: &str,
-        left_help: &str,
-        right_help: &str,
-        spinner: u8,
+        (left_help, right_help): (&str, &str),
+        counter: Option<(usize, &str)>,
+        operation: Option<&str>,
+        spinner: char,
+        key_bindings: &KeyBindings,
     ) {
-        const ALL_MODES: &[(&str, u8)] = &[
-            ("status", b's'),
-            ("log", b'l'),
-            ("branches", b'b'),
-            ("tags", b't'),
+        const ALL_MODES: &[(&str, char)] = &[
+            ("status", 's'),
+            ("log", 'l'),
+            ("details", 'h'),
+            ("branches", 'b'),
+            ("tags", 't'),
+            ("stash", 'S'),
+            ("reflog", 'L'),
+            ("remotes", 'u'),
+            ("sparse", 'w'),
         ];
-        fn mode_tabs_len(tabs: &[(&str, u8)]) -> usize {
-            let mut len = 0;
-            for (name, _) in tabs {
-                len += "[x]".len() + name.len() + 1;
-            }
-            len
-        }
+        let mode_tabs = |tabs: &[(&str, char)]| -> Vec<String> {
+            tabs.iter()
+                .map(|&(name, shortcut)| {
+                    key_bindings.help("global", &format!("[{}]{} ", shortcut, name))
+                })
+                .collect()
+        };
 
-        let background_color = Color::Black;
-        let foreground_color = Color::DarkYellow;
+        let background_color = self.theme.header_background;
+        let foreground_color = self.theme.header_foreground;
 
         move_cursor_to_zero(&mut self.buf);
 
         set_background_color(&mut self.buf, background_color);
         set_foreground_color(&mut self.buf, foreground_color);
         self.buf.push(b' ');
-        self.buf.push(spinner);
+        self.fmt(format_args!("{}", spinner));
         self.buf.push(b' ');
 
         set_background_color(&mut self.buf, foreground_color);
@@ -141,25 +460,47 @@ impl Drawer {
         set_background_color(&mut self.buf, background_color);
         set_foreground_color(&mut self.buf, foreground_color);
 
-        let (modes_before, modes_after) =
-            match ALL_MODES.iter().position(|&(m, _)| m == current_mode_name) {
-                Some(i) => (&ALL_MODES[..i], &ALL_MODES[i + 1..]),
-                None => (ALL_MODES, &[][..]),
-            };
-        let modes_before_len = mode_tabs_len(modes_before);
-        let modes_after_len = mode_tabs_len(modes_after);
+        let counter_len = match counter {
+            Some((count, label)) => {
+                let len = self.buf.len();
+                self.fmt(format_args!(" {} {}", count, label));
+                self.buf.len() - len
+            }
+            None => 0,
+        };
+
+        let operation_len = match operation {
+            Some(operation) => {
+                let len = self.buf.len();
+                set_foreground_color(&mut self.buf, Color::DarkRed);
+                let color_len = self.buf.len() - len;
+                self.fmt(format_args!(" {} in progress", operation));
+                set_foreground_color(&mut self.buf, foreground_color);
+                self.buf.len() - len - color_len
+            }
+            None => 0,
+        };
+
+        let (modes_before, modes_after) = match ALL_MODES
+            .iter()
+            .position(|&(m, _)| current_mode_name.split(" (").next() == Some(m))
+        {
+            Some(i) => (&ALL_MODES[..i], &ALL_MODES[i + 1..]),
+            None => (ALL_MODES, &[][..]),
+        };
+        let modes_before = mode_tabs(modes_before);
+        let modes_after = mode_tabs(modes_after);
+        let modes_before_len: usize = modes_before.iter().map(String::len).sum();
+        let modes_after_len: usize = modes_after.iter().map(String::len).sum();
         let current_mode_len = 3 + 1 + current_mode_name.len() + 1;
 
-        let spacer_len = (self.viewport_size.0 as usize)
-            .saturating_sub(modes_before_len + modes_after_len + current_mode_len);
-        self.buf.extend(std::iter::repeat(b' ').take(spacer_len));
+        let spacer_len = (self.viewport_size.0 as usize).saturating_sub(
+            modes_before_len + modes_after_len + current_mode_len + counter_len + operation_len,
+        );
+        self.buf.extend(std::iter::repeat_n(b' ', spacer_len));
 
-        for &(mode_name, shortcut) in modes_before.iter().chain(modes_after) {
-            self.buf.push(b'[');
-            self.buf.push(shortcut);
-            self.buf.push(b']');
-            self.buf.extend_from_slice(mode_name.as_bytes());
-            self.buf.push(b' ');
+        for tab in modes_before.iter().chain(&modes_after) {
+            self.buf.extend_from_slice(tab.as_bytes());
         }
 
         clear_until_new_line(&mut self.buf);
@@ -182,13 +523,13 @@ impl Drawer {
 
         let spacer_len = 1 + available_width - left_help.len() - right_help.len();
         self.buf.extend_from_slice(left_help);
-        self.buf.extend(std::iter::repeat(b' ').take(spacer_len));
+        self.buf.extend(std::iter::repeat_n(b' ', spacer_len));
         self.buf.extend_from_slice(right_help);
 
         move_cursor_to_next_line(&mut self.buf);
 
-        set_background_color(&mut self.buf, Color::Black);
-        set_foreground_color(&mut self.buf, Color::White);
+        set_background_color(&mut self.buf, self.theme.background);
+        set_foreground_color(&mut self.buf, self.theme.foreground);
     }
 
     pub fn str(&mut self, line: &str) {
@@ -200,58 +541,252 @@ impl Drawer {
         self.buf.write_fmt(args).unwrap();
     }
 
+    pub fn graph(&mut self, graph: &str) {
+        let mut utf8_buf = [0; 4];
+        for c in graph.chars() {
+            let c = self.glyphs.graph_char(c);
+            self.buf
+                .extend_from_slice(c.encode_utf8(&mut utf8_buf).as_bytes());
+        }
+    }
+
     pub fn next_line(&mut self) {
         clear_until_new_line(&mut self.buf);
         move_cursor_to_next_line(&mut self.buf);
     }
 
     pub fn output(&mut self, output: &Output) -> usize {
-        let tab_bytes = [b' '; 4];
+        let tab_bytes = [b' '; TAB_WIDTH];
         let mut utf8_buf = [0; 4];
 
+        let width = self.viewport_size.0 as usize;
+        let wrap = output.is_wrapping();
+        let h_scroll = output.h_scroll();
+        let max_line_width = match self.max_line_rows {
+            _ if !wrap => usize::MAX,
+            0 => usize::MAX,
+            rows => rows * width,
+        };
+        let char_width = |c| match c {
+            '\t' => tab_bytes.len(),
+            c => char_display_width(c),
+        };
+
+        let available_height = (self.viewport_size.1 as usize).saturating_sub(RESERVED_LINES_COUNT);
+        let cursor = output
+            .cursor()
+            .and_then(|c| c.checked_sub(output.scroll(available_height)));
+
         let mut line_count = 0;
-        for line in output.lines_from_scroll() {
+        let mut drawn_lines = 0;
+        for (i, line) in output.lines_from_scroll(available_height).enumerate() {
+            drawn_lines += 1;
+            let hovered = cursor == Some(i);
+            if hovered {
+                set_background_color(&mut self.buf, Color::DarkGray);
+            }
+            let line_color = output_line_color(&self.theme, line, output.kind());
+            if let Some(color) = line_color {
+                set_foreground_color(&mut self.buf, color);
+            }
+            let bold = matches!(output.kind(), OutputKind::Diff) && is_diff_header(line);
+            if bold {
+                set_bold(&mut self.buf, true);
+            }
+            let background_color = if hovered {
+                Color::DarkGray
+            } else {
+                self.theme.background
+            };
+            let match_ranges = output.match_ranges(line);
+            let mut match_ranges = match_ranges.iter().peekable();
+
+            let line_width = line_display_width(line);
+            let width_limit = if line_width > max_line_width {
+                let marker_len = truncated_line_marker(self.glyphs, line_width)
+                    .chars()
+                    .count();
+                max_line_width.saturating_sub(marker_len)
+            } else {
+                usize::MAX
+            };
+
             let mut x = 0;
-            for c in line.chars() {
+            let mut drawn_width = 0;
+            for (i, c) in line.char_indices() {
+                drawn_width += char_width(c);
+                if drawn_width > width_limit {
+                    drawn_width -= char_width(c);
+                    break;
+                }
+
+                if let Some(&&(start, end)) = match_ranges.peek() {
+                    if i == start {
+                        set_background_color(&mut self.buf, Color::DarkYellow);
+                        set_foreground_color(&mut self.buf, Color::Black);
+                    } else if i == end {
+                        set_background_color(&mut self.buf, background_color);
+                        set_foreground_color(
+                            &mut self.buf,
+                            line_color.unwrap_or(self.theme.foreground),
+                        );
+                        match_ranges.next();
+                        if let Some(&&(start, _)) = match_ranges.peek() {
+                            if i == start {
+                                set_background_color(&mut self.buf, Color::DarkYellow);
+                                set_foreground_color(&mut self.buf, Color::Black);
+                            }
+                        }
+                    }
+                }
+
+                // without wrapping, lines are panned by h_scroll columns and clipped at the edge
+                if !wrap {
+                    if drawn_width <= h_scroll {
+                        continue;
+                    }
+                    // a wide char cut by the left edge shows as blank columns
+                    if drawn_width - char_width(c) < h_scroll {
+                        let visible = drawn_width - h_scroll;
+                        self.buf.resize(self.buf.len() + visible, b' ');
+                        x += visible;
+                        continue;
+                    }
+                    if x + char_width(c) > width {
+                        break;
+                    }
+                }
+
                 match c {
                     '\t' => {
                         self.buf.extend_from_slice(&tab_bytes);
                         x += tab_bytes.len();
+                        while x > width && width > 0 {
+                            x -= width;
+                            line_count += 1;
+                        }
                     }
                     _ => {
+                        // a wide char that doesn't fit in the last column gets
+                        // wrapped whole by the terminal leaving that column empty.
+                        // a row filled up to the last column only wraps once
+                        // another visible char follows
+                        let char_width = char_display_width(c);
+                        if x + char_width > width {
+                            x = 0;
+                            line_count += 1;
+                        }
+
                         let bytes = c.encode_utf8(&mut utf8_buf).as_bytes();
                         self.buf.extend_from_slice(bytes);
-                        x += 1;
+                        x += char_width;
                     }
                 }
+            }
+
+            if match_ranges.peek().is_some() {
+                set_background_color(&mut self.buf, background_color);
+                set_foreground_color(&mut self.buf, line_color.unwrap_or(self.theme.foreground));
+            }
+
+            if wrap && drawn_width < line_width {
+                let marker = truncated_line_marker(self.glyphs, line_width - drawn_width);
+                set_foreground_color(&mut self.buf, Color::DarkGray);
+                self.buf.extend_from_slice(marker.as_bytes());
+                set_foreground_color(&mut self.buf, line_color.unwrap_or(self.theme.foreground));
 
-                if x >= self.viewport_size.0 as _ {
-                    x -= self.viewport_size.0 as usize;
+                x += marker.chars().count();
+                while x > width && width > 0 {
+                    x -= width;
                     line_count += 1;
                 }
             }
 
             self.next_line();
 
+            if hovered {
+                set_background_color(&mut self.buf, self.theme.background);
+            }
+            if line_color.is_some() {
+                set_foreground_color(&mut self.buf, self.theme.foreground);
+            }
+            if bold {
+                set_bold(&mut self.buf, false);
+            }
+
             line_count += 1;
             if line_count + 1 >= self.viewport_size.1 as _ {
                 break;
             }
         }
 
+        let scroll = output.scroll(available_height);
+        let below = output.line_count().saturating_sub(scroll + drawn_lines);
+        self.overflow = (scroll, below);
+
+        let case = if output.is_case_sensitive() {
+            " (case sensitive)"
+        } else {
+            ""
+        };
+        let search_status = if output.is_searching() {
+            Some(format!(
+                "/{}{} [tab]toggle case",
+                output.search_input().input(),
+                case
+            ))
+        } else if !output.search_pattern().is_empty() {
+            let (current, count) = output.search_matches();
+            if count == 0 {
+                Some(format!("/{}{} not found", output.search_pattern(), case))
+            } else {
+                Some(format!(
+                    "/{}{} match {} of {} [n]next [N]previous",
+                    output.search_pattern(),
+                    case,
+                    current + 1,
+                    count
+                ))
+            }
+        } else {
+            None
+        };
+        self.status_line = search_status.map(|status| (status, Color::DarkYellow));
+
         line_count
     }
 
+    pub fn notice(&mut self, text: &str, color: Color) {
+        self.status_line = Some((text.into(), color));
+    }
+
+    pub fn back_hint(&mut self) {
+        let foreground_color = self.theme.foreground;
+        self.fmt(format_args!(
+            "{}press [enter] to go back{}",
+            Color::DarkGray,
+            foreground_color
+        ));
+    }
+
     pub fn readline(&mut self, readline: &ReadLine, placeholder: &str) {
+        self.validated_readline(readline, placeholder, true);
+    }
+
+    pub fn validated_readline(&mut self, readline: &ReadLine, placeholder: &str, valid: bool) {
         let input = readline.input();
 
-        set_background_color(&mut self.buf, Color::Black);
-        set_foreground_color(&mut self.buf, Color::White);
+        set_background_color(&mut self.buf, self.theme.background);
+        if valid {
+            set_foreground_color(&mut self.buf, self.theme.foreground);
+        } else {
+            set_foreground_color(&mut self.buf, Color::DarkYellow);
+        }
         self.buf.extend_from_slice(input.as_bytes());
 
         set_background_color(&mut self.buf, Color::DarkRed);
         self.buf.push(b' ');
-        set_background_color(&mut self.buf, Color::Black);
+        set_background_color(&mut self.buf, self.theme.background);
 
         if input.is_empty() {
             set_foreground_color(&mut self.buf, Color::DarkGray);
@@ -259,42 +794,209 @@ impl Drawer {
         }
     }
 
-    pub fn select_menu<'entries, I, E>(
+    pub fn form(&mut self, form: &Form, placeholders: &[&str]) {
+        let label_width = form
+            .fields()
+            .iter()
+            .map(|f| f.label.len())
+            .max()
+            .unwrap_or(0);
+
+        for (i, field) in form.fields().iter().enumerate() {
+            let focused = i == form.focused();
+            let input = field.readline.input();
+
+            set_background_color(&mut self.buf, self.theme.background);
+            if focused {
+                set_foreground_color(&mut self.buf, Color::DarkMagenta);
+            } else {
+                set_foreground_color(&mut self.buf, Color::DarkGray);
+            }
+            self.fmt(format_args!(
+                "{:>width$}: ",
+                field.label,
+                width = label_width
+            ));
+
+            set_foreground_color(&mut self.buf, self.theme.foreground);
+            self.buf.extend_from_slice(input.as_bytes());
+
+            if focused {
+                set_background_color(&mut self.buf, Color::DarkRed);
+                self.buf.push(b' ');
+                set_background_color(&mut self.buf, self.theme.background);
+            }
+
+            if input.is_empty() {
+                set_foreground_color(&mut self.buf, Color::DarkGray);
+                let placeholder = placeholders.get(i).copied().unwrap_or("");
+                self.buf.extend_from_slice(placeholder.as_bytes());
+            }
+
+            self.next_line();
+        }
+    }
+
+    pub fn select_menu<I, E>(
         &mut self,
         select: &SelectMenu,
         header_height: u16,
         show_full_hovered_entry: bool,
         entries: I,
     ) where
-        I: 'entries + Iterator<Item = &'entries E>,
-        E: 'entries + SelectEntryDraw,
+        I: Iterator<Item = E>,
+        E: SelectEntryDraw,
     {
         let cursor_index = select.cursor();
 
-        set_background_color(&mut self.buf, Color::Black);
-        set_foreground_color(&mut self.buf, Color::White);
+        set_background_color(&mut self.buf, self.theme.background);
+        set_foreground_color(&mut self.buf, self.theme.foreground);
 
         let mut line_count = 0;
+        let mut hovered_line_count = 1;
         let max_line_count = self.viewport_size.1.saturating_sub(2 + header_height) as usize;
 
-        for (i, entry) in entries.enumerate().skip(select.scroll()) {
+        let mut entries = entries.enumerate().skip(select.scroll());
+        for (i, entry) in entries.by_ref() {
             let hovered = i == cursor_index;
             if hovered {
-                set_background_color(&mut self.buf, Color::DarkMagenta);
+                set_background_color(&mut self.buf, self.theme.highlight);
             }
 
-            line_count += entry.draw(self, hovered, hovered && show_full_hovered_entry);
+            let entry_line_count = entry.draw(self, hovered, hovered && show_full_hovered_entry);
+            if hovered {
+                hovered_line_count = entry_line_count;
+            }
+            line_count += entry_line_count;
 
             clear_until_new_line(&mut self.buf);
             move_cursor_to_next_line(&mut self.buf);
 
             if hovered {
-                set_background_color(&mut self.buf, Color::Black);
+                set_background_color(&mut self.buf, self.theme.background);
             }
 
             if line_count >= max_line_count {
                 break;
             }
         }
+
+        self.overflow = (select.scroll(), entries.count());
+        select.set_drawn_layout(
+            HEADER_LINE_COUNT + header_height as usize,
+            hovered_line_count,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Key;
+
+    // replays the drawn bytes the way a terminal would and counts the rows used
+    fn drawn_rows(buf: &[u8], width: usize) -> usize {
+        let text = String::from_utf8_lossy(buf);
+        let mut chars = text.chars();
+        let mut rows = 0;
+        let mut x = 0;
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                if chars.next() == Some('[') {
+                    if let Some(c) = chars.find(|c| ('\x40'..='\x7e').contains(c)) {
+                        if c == 'E' {
+                            rows += 1;
+                            x = 0;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let char_width = char_display_width(c);
+            if x + char_width > width {
+                rows += 1;
+                x = 0;
+            }
+            x += char_width;
+        }
+        rows
+    }
+
+    fn draw_output(text: &str, width: u16) -> (usize, usize) {
+        let mut output = Output::default();
+        output.set(text.into());
+        let mut drawer = Drawer::new(
+            Vec::new(),
+            (width, 100),
+            &Glyphs::ASCII,
+            Theme::default(),
+            0,
+        );
+        let line_count = drawer.output(&output);
+        (line_count, drawn_rows(&drawer.take_buf(), width as _))
+    }
+
+    #[test]
+    fn panning_clips_wide_chars_by_column() {
+        let mut output = Output::default();
+        output.set(format!("abcdefg漢{}", "x".repeat(20)));
+        output.set_wrap(false);
+        output.on_key(10, Key::ShiftRight);
+        assert_eq!(output.h_scroll(), 8);
+
+        let mut drawer = Drawer::new(Vec::new(), (5, 100), &Glyphs::ASCII, Theme::default(), 0);
+        drawer.output(&output);
+        let buf = drawer.take_buf();
+        let text = String::from_utf8_lossy(&buf);
+        let mut visible = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.find(|c| c.is_ascii_alphabetic());
+            } else {
+                visible.push(c);
+            }
+        }
+        assert_eq!(visible, " xxxx");
+    }
+
+    #[test]
+    fn char_widths() {
+        assert_eq!(char_display_width('a'), 1);
+        assert_eq!(char_display_width('漢'), 2);
+        assert_eq!(char_display_width('\u{301}'), 0);
+        assert_eq!(char_display_width('\u{200b}'), 0);
+    }
+
+    #[test]
+    fn line_count_matches_drawn_rows() {
+        const CASES: &[(&str, usize)] = &[
+            ("abcd", 1),
+            ("abcde", 1),
+            ("abcdef", 2),
+            ("ab漢字x", 2),
+            ("漢字漢", 2),
+            ("漢字漢字漢", 3),
+            ("abc\tx", 2),
+            ("abcde\nfghij\nk", 3),
+            ("a漢\n漢字漢字\nabcdefghijk", 6),
+        ];
+        for &(text, rows) in CASES {
+            assert_eq!(draw_output(text, 5), (rows, rows), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn combining_marks_take_no_columns() {
+        const CASES: &[(&str, usize)] = &[
+            ("e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}", 1),
+            ("abcde\u{301}", 1),
+            ("abcde\u{301}f", 2),
+            ("漢字e\u{301}\u{302}", 1),
+        ];
+        for &(text, rows) in CASES {
+            assert_eq!(draw_output(text, 5), (rows, rows), "{:?}", text);
+        }
     }
 }